@@ -0,0 +1,31 @@
+-- dao activity points, reward payouts and lifetime reward totals
+CREATE TABLE IF NOT EXISTS "dao_activity" (
+    "address" varchar NOT NULL,
+    "day" bigint NOT NULL,
+    "activity_point" bigint NOT NULL,
+    "received_point" bigint NOT NULL,
+    "updated_at" bigint NOT NULL,
+    CONSTRAINT "pk-dao_activity" PRIMARY KEY ("address", "day")
+);
+
+CREATE TABLE IF NOT EXISTS "reward_payout" (
+    "hash" varchar NOT NULL,
+    "address" varchar NOT NULL,
+    "amount" decimal NOT NULL,
+    "reward_type" varchar NOT NULL,
+    "dao_account" varchar,
+    "definition_id" varchar,
+    "snapshot_hash" varchar,
+    "snapshot_number" bigint,
+    "block_number" bigint NOT NULL,
+    "event_time" bigint NOT NULL,
+    "created_at" bigint NOT NULL,
+    CONSTRAINT "pk-reward_payout" PRIMARY KEY ("hash", "address")
+);
+
+CREATE TABLE IF NOT EXISTS "reward_total" (
+    "address" varchar NOT NULL PRIMARY KEY,
+    "total" decimal NOT NULL,
+    "payout_count" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
@@ -0,0 +1,46 @@
+-- snapshots with captured balances and nft owners, and the per-tx balance changes they are built from
+CREATE TABLE IF NOT EXISTS "snapshot" (
+    "hash" varchar NOT NULL PRIMARY KEY,
+    "snapshot_type" varchar NOT NULL,
+    "definition_id" varchar,
+    "memo" varchar,
+    "account_amount" decimal,
+    "token_amount" decimal,
+    "ownership_amount" decimal,
+    "block_number" bigint NOT NULL,
+    "tx_index" integer NOT NULL,
+    "is_captured" bool NOT NULL,
+    "event_time" bigint NOT NULL,
+    "created_at" bigint NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS "snapshot_balance" (
+    "snapshot_hash" varchar NOT NULL,
+    "address" varchar NOT NULL,
+    "free" decimal NOT NULL,
+    "locked" decimal NOT NULL,
+    "created_at" bigint NOT NULL,
+    CONSTRAINT "pk-snapshot_balance" PRIMARY KEY ("snapshot_hash", "address")
+);
+
+CREATE TABLE IF NOT EXISTS "snapshot_nft_owner" (
+    "snapshot_hash" varchar NOT NULL,
+    "token_id" varchar NOT NULL,
+    "owner" varchar NOT NULL,
+    "created_at" bigint NOT NULL,
+    CONSTRAINT "pk-snapshot_nft_owner" PRIMARY KEY ("snapshot_hash", "token_id")
+);
+
+CREATE TABLE IF NOT EXISTS "balance_change" (
+    "id" bigserial NOT NULL PRIMARY KEY,
+    "hash" varchar NOT NULL,
+    "address" varchar NOT NULL,
+    "token" varchar NOT NULL,
+    "free" decimal NOT NULL,
+    "locked" decimal NOT NULL,
+    "block_number" bigint NOT NULL,
+    "tx_index" integer NOT NULL,
+    "created_at" bigint NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "idx-balance_change-position" ON "balance_change" ("block_number", "tx_index");
@@ -0,0 +1,19 @@
+-- token definition registry with supply and holder counts
+CREATE TABLE IF NOT EXISTS "token_definition" (
+    "definition_id" varchar NOT NULL PRIMARY KEY,
+    "name" varchar NOT NULL,
+    "symbol" varchar,
+    "minter_group" varchar,
+    "precision" integer,
+    "is_nft" bool NOT NULL,
+    "nft_minter" varchar,
+    "nft_data_url" varchar,
+    "nft_content_hash" varchar,
+    "rarity" json,
+    "total_minted" decimal NOT NULL,
+    "total_burned" decimal NOT NULL,
+    "holder_count" bigint NOT NULL,
+    "event_time" bigint NOT NULL,
+    "created_at" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
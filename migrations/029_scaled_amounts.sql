@@ -0,0 +1,7 @@
+-- precision-scaled amounts next to the raw values.
+-- existing balance rows start at 0 and are filled by the next balance update of the address.
+ALTER TABLE "balance" ADD COLUMN IF NOT EXISTS "free_scaled" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "balance" ADD COLUMN IF NOT EXISTS "locked_scaled" decimal NOT NULL DEFAULT 0;
+
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "total_balance_scaled" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "cir_supply_raw" decimal NOT NULL DEFAULT 0;
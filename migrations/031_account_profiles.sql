@@ -0,0 +1,49 @@
+-- account profiles, external chain addresses and account history
+ALTER TABLE "account" ADD COLUMN IF NOT EXISTS "eth_address" varchar;
+ALTER TABLE "account" ADD COLUMN IF NOT EXISTS "guardian" varchar;
+ALTER TABLE "account" ADD COLUMN IF NOT EXISTS "external_chain_addresses" json;
+ALTER TABLE "account" ADD COLUMN IF NOT EXISTS "block_number" bigint NOT NULL DEFAULT 0;
+ALTER TABLE "account" ADD COLUMN IF NOT EXISTS "updated_at" bigint NOT NULL DEFAULT 0;
+UPDATE "account" SET "updated_at" = "created_at" WHERE "updated_at" = 0;
+
+CREATE TABLE IF NOT EXISTS "account_history" (
+    "hash" varchar NOT NULL PRIMARY KEY,
+    "address" varchar NOT NULL,
+    "tx_type" varchar NOT NULL,
+    "eth_address" varchar,
+    "guardian" varchar,
+    "external_chain_addresses" json,
+    "public_key_summaries" json,
+    "removed_summaries" json,
+    "block_number" bigint NOT NULL,
+    "event_time" bigint NOT NULL,
+    "created_at" bigint NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS "account_public_key" (
+    "address" varchar NOT NULL,
+    "summary" varchar NOT NULL,
+    "description" varchar NOT NULL,
+    "added_hash" varchar NOT NULL,
+    "block_number" bigint NOT NULL,
+    "removed_hash" varchar,
+    "removed_block_number" bigint,
+    "event_time" bigint NOT NULL,
+    "created_at" bigint NOT NULL,
+    CONSTRAINT "pk-account_public_key" PRIMARY KEY ("address", "summary")
+);
+
+-- source is eth_address or external_chain, each only deactivates its own rows
+CREATE TABLE IF NOT EXISTS "external_address" (
+    "chain" varchar NOT NULL,
+    "external_address" varchar NOT NULL,
+    "address" varchar NOT NULL,
+    "source" varchar NOT NULL,
+    "is_active" bool NOT NULL,
+    "block_number" bigint NOT NULL,
+    "updated_at" bigint NOT NULL,
+    CONSTRAINT "pk-external_address" PRIMARY KEY ("chain", "external_address")
+);
+
+CREATE INDEX IF NOT EXISTS "idx-account_history-address" ON "account_history" ("address");
+CREATE INDEX IF NOT EXISTS "idx-external_address-address" ON "external_address" ("address");
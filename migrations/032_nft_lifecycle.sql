@@ -0,0 +1,18 @@
+-- nft status, custodian and ownership history.
+-- owners recorded before this change are treated as owned.
+ALTER TABLE "nft_owner" ADD COLUMN IF NOT EXISTS "status" varchar NOT NULL DEFAULT 'owned';
+ALTER TABLE "nft_owner" ADD COLUMN IF NOT EXISTS "custodian" varchar;
+
+CREATE TABLE IF NOT EXISTS "nft_history" (
+    "tx_hash" varchar NOT NULL PRIMARY KEY,
+    "token_id" varchar NOT NULL,
+    "action" varchar NOT NULL,
+    "status" varchar NOT NULL,
+    "owner" varchar NOT NULL,
+    "custodian" varchar,
+    "signer" varchar NOT NULL,
+    "event_time" bigint NOT NULL,
+    "created_at" bigint NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "idx-nft_history-token_id" ON "nft_history" ("token_id");
@@ -0,0 +1,14 @@
+-- persisted metadata fetch queue, status is pending, running, failed, done or gave_up
+CREATE TABLE IF NOT EXISTS "nft_fetch" (
+    "token_id" varchar NOT NULL PRIMARY KEY,
+    "data_url" varchar NOT NULL,
+    "status" varchar NOT NULL,
+    "attempts" integer NOT NULL,
+    "last_error" varchar,
+    "next_attempt_at" bigint NOT NULL,
+    "fetched_at" bigint,
+    "created_at" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "idx-nft_fetch-due" ON "nft_fetch" ("status", "next_attempt_at");
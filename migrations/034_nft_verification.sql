@@ -0,0 +1,18 @@
+-- content hash verification and optional media cache.
+-- files stored before this change have no known hash.
+ALTER TABLE "nft_file" ADD COLUMN IF NOT EXISTS "content_hash" varchar NOT NULL DEFAULT '';
+
+CREATE TABLE IF NOT EXISTS "nft_verification" (
+    "token_id" varchar NOT NULL PRIMARY KEY,
+    "status" varchar NOT NULL,
+    "metadata_hash" varchar,
+    "content_hash_match" bool,
+    "media_hash" varchar,
+    "checksum_match" bool,
+    "media_size" bigint,
+    "media_path" varchar,
+    "last_error" varchar,
+    "checked_at" bigint,
+    "next_check_at" bigint NOT NULL,
+    "created_at" bigint NOT NULL
+);
@@ -0,0 +1,26 @@
+-- collection stats, the definition and rarity of owned nfts are taken from nft_file.
+-- nft_collection is filled by the first full recount after start.
+ALTER TABLE "nft_owner" ADD COLUMN IF NOT EXISTS "definition_id" varchar NOT NULL DEFAULT '';
+ALTER TABLE "nft_owner" ADD COLUMN IF NOT EXISTS "rarity" varchar;
+UPDATE "nft_owner" o SET "definition_id" = f."token_def_id", "rarity" = f."rarity"
+FROM "nft_file" f WHERE f."token_id" = o."token_id" AND o."definition_id" = '';
+
+ALTER TABLE "nft_history" ADD COLUMN IF NOT EXISTS "definition_id" varchar NOT NULL DEFAULT '';
+UPDATE "nft_history" h SET "definition_id" = o."definition_id"
+FROM "nft_owner" o WHERE o."token_id" = h."token_id" AND h."definition_id" = '';
+
+CREATE TABLE IF NOT EXISTS "nft_collection" (
+    "definition_id" varchar NOT NULL PRIMARY KEY,
+    "collection_name" varchar NOT NULL,
+    "item_count" bigint NOT NULL,
+    "burned_count" bigint NOT NULL,
+    "owner_count" bigint NOT NULL,
+    "rarity_counts" json NOT NULL,
+    "unranked_count" bigint NOT NULL,
+    "first_mint_at" bigint,
+    "last_mint_at" bigint,
+    "transfer_count" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "idx-nft_owner-definition_id" ON "nft_owner" ("definition_id");
@@ -0,0 +1,12 @@
+-- block height cursors, nft rows keep the block they were indexed from
+CREATE TABLE IF NOT EXISTS "agent_cursor" (
+    "name" varchar NOT NULL PRIMARY KEY,
+    "block_number" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
+
+ALTER TABLE "nft" ADD COLUMN IF NOT EXISTS "block_number" bigint NOT NULL DEFAULT 0;
+UPDATE "nft" n SET "block_number" = t."block_number"
+FROM "tx" t WHERE t."hash" = n."tx_hash" AND n."block_number" = 0;
+
+CREATE INDEX IF NOT EXISTS "idx-nft-block_number" ON "nft" ("block_number");
@@ -0,0 +1,11 @@
+-- chain position of nft rows. the tx index of rows indexed before this change is unknown
+-- and left at 0, so any later tx for the token replaces the owner.
+ALTER TABLE "nft" ADD COLUMN IF NOT EXISTS "tx_index" integer NOT NULL DEFAULT 0;
+
+ALTER TABLE "nft_owner" ADD COLUMN IF NOT EXISTS "block_number" bigint NOT NULL DEFAULT 0;
+ALTER TABLE "nft_owner" ADD COLUMN IF NOT EXISTS "tx_index" integer NOT NULL DEFAULT 0;
+
+ALTER TABLE "nft_history" ADD COLUMN IF NOT EXISTS "block_number" bigint NOT NULL DEFAULT 0;
+ALTER TABLE "nft_history" ADD COLUMN IF NOT EXISTS "tx_index" integer NOT NULL DEFAULT 0;
+UPDATE "nft_history" h SET "block_number" = t."block_number"
+FROM "tx" t WHERE t."hash" = h."tx_hash" AND h."block_number" = 0;
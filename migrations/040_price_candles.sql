@@ -0,0 +1,20 @@
+-- minute, hour and day price candles and the backfill position per provider
+CREATE TABLE IF NOT EXISTS "price_candle" (
+    "interval" varchar NOT NULL,
+    "open_time" bigint NOT NULL,
+    "open" decimal NOT NULL,
+    "high" decimal NOT NULL,
+    "low" decimal NOT NULL,
+    "close" decimal NOT NULL,
+    "close_time" bigint NOT NULL,
+    "sample_count" bigint NOT NULL,
+    "source" varchar NOT NULL,
+    "updated_at" bigint NOT NULL,
+    CONSTRAINT "pk-price_candle" PRIMARY KEY ("interval", "open_time")
+);
+
+CREATE TABLE IF NOT EXISTS "price_backfill" (
+    "name" varchar NOT NULL PRIMARY KEY,
+    "backfilled_to" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
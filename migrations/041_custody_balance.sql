@@ -0,0 +1,13 @@
+-- balances held on other chains, one row per configured address
+CREATE TABLE IF NOT EXISTS "custody_balance" (
+    "chain" varchar NOT NULL,
+    "address" varchar NOT NULL,
+    "name" varchar NOT NULL,
+    "source" varchar NOT NULL,
+    "balance" decimal NOT NULL,
+    "block_number" bigint NOT NULL,
+    "last_error" varchar,
+    "fetched_at" bigint NOT NULL,
+    "updated_at" bigint NOT NULL,
+    CONSTRAINT "pk-custody_balance" PRIMARY KEY ("chain", "address")
+);
@@ -0,0 +1,17 @@
+-- hourly and daily chain analytics
+CREATE TABLE IF NOT EXISTS "chain_stat" (
+    "interval" varchar NOT NULL,
+    "bucket" bigint NOT NULL,
+    "metric" varchar NOT NULL,
+    "key" varchar NOT NULL,
+    "value" decimal NOT NULL,
+    "updated_at" bigint NOT NULL,
+    CONSTRAINT "pk-chain_stat" PRIMARY KEY ("interval", "bucket", "metric", "key")
+);
+
+CREATE TABLE IF NOT EXISTS "chain_stat_active" (
+    "interval" varchar NOT NULL,
+    "bucket" bigint NOT NULL,
+    "address" varchar NOT NULL,
+    CONSTRAINT "pk-chain_stat_active" PRIMARY KEY ("interval", "bucket", "address")
+);
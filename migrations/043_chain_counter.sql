@@ -0,0 +1,6 @@
+-- incremental summary counters, also holds the unknown_tx:<tag> counts
+CREATE TABLE IF NOT EXISTS "chain_counter" (
+    "name" varchar NOT NULL PRIMARY KEY,
+    "value" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
@@ -0,0 +1,34 @@
+-- rich list, holder concentration and address labels
+CREATE TABLE IF NOT EXISTS "address_label" (
+    "address" varchar NOT NULL PRIMARY KEY,
+    "label" varchar NOT NULL,
+    "category" varchar NOT NULL,
+    "exclude_from_supply" bool NOT NULL,
+    "source" varchar NOT NULL,
+    "updated_at" bigint NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS "holder_rank" (
+    "token" varchar NOT NULL,
+    "rank" integer NOT NULL,
+    "address" varchar NOT NULL,
+    "free" decimal NOT NULL,
+    "locked" decimal NOT NULL,
+    "total" decimal NOT NULL,
+    "share" decimal NOT NULL,
+    "label" varchar,
+    "updated_at" bigint NOT NULL,
+    CONSTRAINT "pk-holder_rank" PRIMARY KEY ("token", "rank")
+);
+
+CREATE TABLE IF NOT EXISTS "token_distribution" (
+    "token" varchar NOT NULL PRIMARY KEY,
+    "holder_count" bigint NOT NULL,
+    "total_balance" decimal NOT NULL,
+    "excluded_balance" decimal NOT NULL,
+    "top10_share" double precision NOT NULL,
+    "top100_share" double precision NOT NULL,
+    "gini" double precision NOT NULL,
+    "nakamoto" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
@@ -0,0 +1,9 @@
+-- chain derived circulating supply next to the vendor figure
+ALTER TABLE "token_definition" ADD COLUMN IF NOT EXISTS "total_locked" decimal NOT NULL DEFAULT 0;
+
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "minted" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "burned" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "locked" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "treasury" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "chain_cir_supply" decimal NOT NULL DEFAULT 0;
+ALTER TABLE "summary" ADD COLUMN IF NOT EXISTS "cir_supply_diff" decimal NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- indexed events for stream clients, seq is given once the row is visible in chain order
+CREATE TABLE IF NOT EXISTS "chain_event" (
+    "id" bigserial NOT NULL PRIMARY KEY,
+    "seq" bigint,
+    "kind" varchar NOT NULL,
+    "addresses" json NOT NULL,
+    "token" varchar,
+    "block_number" bigint NOT NULL,
+    "payload" json NOT NULL,
+    "created_at" bigint NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "idx-chain_event-seq" ON "chain_event" ("seq");
@@ -0,0 +1,31 @@
+-- webhook watch list and signed deliveries
+CREATE TABLE IF NOT EXISTS "webhook_watch" (
+    "id" bigserial NOT NULL PRIMARY KEY,
+    "name" varchar NOT NULL,
+    "url" varchar NOT NULL,
+    "secret" varchar NOT NULL,
+    "address" varchar,
+    "definition_id" varchar,
+    "events" varchar,
+    "active" bool NOT NULL,
+    "created_at" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS "webhook_delivery" (
+    "id" bigserial NOT NULL PRIMARY KEY,
+    "watch_id" bigint NOT NULL,
+    "event_seq" bigint NOT NULL,
+    "kind" varchar NOT NULL,
+    "body" json NOT NULL,
+    "status" varchar NOT NULL,
+    "attempts" integer NOT NULL,
+    "next_attempt_at" bigint NOT NULL,
+    "response_status" integer,
+    "last_error" varchar,
+    "delivered_at" bigint,
+    "created_at" bigint NOT NULL,
+    "updated_at" bigint NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "idx-webhook_delivery-due" ON "webhook_delivery" ("status", "next_attempt_at");
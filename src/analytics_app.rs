@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use bigdecimal::BigDecimal;
+use itertools::Itertools;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+
+use log::{error, info};
+use tokio::time::sleep;
+
+use crate::{
+    block_entity, chain_stat, chain_stat_active,
+    library::common::{bucket, now, parse_from_json_str, DAY, HOUR},
+    service::cursor_service::Cursor,
+    transaction::{token_transaction::TokenTx, Transaction, TransactionWithResult},
+    tx_entity, tx_state,
+};
+
+const CURSOR: &str = "analytics";
+const BATCH_BLOCKS: i64 = 1000;
+
+type StatKey = (String, i64, String, String);
+
+// per bucket sums of a batch, added onto the stored values.
+#[derive(Debug, Default)]
+pub struct Stats(BTreeMap<StatKey, BigDecimal>);
+
+impl Stats {
+    pub fn add(&mut self, ts: i64, metric: &str, key: &str, value: BigDecimal) {
+        for (interval, secs) in [HOUR, DAY] {
+            *self
+                .0
+                .entry((interval.to_string(), bucket(ts, secs), metric.to_string(), key.to_string()))
+                .or_default() += &value;
+        }
+    }
+
+    pub fn get(&self, interval: &str, bucket: i64, metric: &str, key: &str) -> Option<&BigDecimal> {
+        self.0.get(&(interval.to_string(), bucket, metric.to_string(), key.to_string()))
+    }
+
+    // blocks ordered by number, prev_time is the block right below the first one.
+    pub fn add_blocks(&mut self, blocks: &[block_entity::Model], mut prev_time: Option<i64>) {
+        for b in blocks {
+            self.add(b.event_time, chain_stat::BLOCKS, "", BigDecimal::from(1));
+            if let Some(prev) = prev_time {
+                self.add(b.event_time, chain_stat::BLOCK_TIME_SUM, "", BigDecimal::from(b.event_time - prev));
+            }
+            prev_time = Some(b.event_time);
+        }
+    }
+
+    pub fn add_txs(&mut self, txs: &[tx_entity::Model]) {
+        let one = || BigDecimal::from(1);
+        for tx in txs {
+            let t = tx.event_time;
+            self.add(t, chain_stat::TX, &format!("{}/{}", tx.tx_type, tx.sub_type), one());
+            match (tx.tx_type.as_str(), tx.sub_type.as_str()) {
+                ("Account", "CreateAccount" | "CreateAccountWithExternalChainAddresses") => {
+                    self.add(t, chain_stat::NEW_ACCOUNTS, "", one())
+                }
+                ("Token", "MintNft" | "MintNftWithMemo") => self.add(t, chain_stat::NFT_MINT, "", one()),
+                ("Token", "TransferNft") => self.add(t, chain_stat::NFT_TRANSFER, "", one()),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn add_transfer(&mut self, event_time: i64, tx: &TransactionWithResult) {
+        if let Transaction::TokenTx(TokenTx::TransferFungibleToken(t)) = &tx.signed_tx.value {
+            let token = &t.token_definition_id;
+            self.add(event_time, chain_stat::TRANSFER_COUNT, token, BigDecimal::from(1));
+            self.add(event_time, chain_stat::TRANSFER_VOLUME, token, t.volume(&tx.signed_tx.sig.account));
+        }
+    }
+
+    pub fn into_models(self, now: i64) -> Vec<chain_stat::Model> {
+        self.0
+            .into_iter()
+            .map(|((interval, bucket, metric, key), value)| chain_stat::Model {
+                interval,
+                bucket,
+                metric,
+                key,
+                value,
+                updated_at: now,
+            })
+            .collect()
+    }
+}
+
+pub fn active_rows(txs: &[tx_entity::Model]) -> Vec<chain_stat_active::Model> {
+    txs.iter()
+        .flat_map(|tx| {
+            [HOUR, DAY].map(|(interval, secs)| (interval.to_string(), bucket(tx.event_time, secs), tx.signer.clone()))
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|(interval, bucket, address)| chain_stat_active::Model { interval, bucket, address })
+        .collect()
+}
+
+async fn save_stats<C: ConnectionTrait>(
+    db: &C,
+    stats: Stats,
+    active: Vec<chain_stat_active::Model>,
+) -> Result<(), DbErr> {
+    let now = now();
+    for chunk in stats.into_models(now).chunks(1000) {
+        chain_stat::Entity::insert_many(chunk.iter().cloned().map(|m| m.into_active()))
+            .on_conflict(chain_stat::Model::on_conflict_add())
+            .exec_without_returning(db)
+            .await?;
+    }
+    let buckets = active.iter().map(|a| (a.interval.clone(), a.bucket)).unique().collect_vec();
+    for chunk in active.chunks(1000) {
+        chain_stat_active::Entity::insert_many(chunk.iter().cloned().map(chain_stat_active::ActiveModel::from).map(|m| m.reset_all()))
+            .on_conflict(
+                OnConflict::columns([
+                    chain_stat_active::Column::Interval,
+                    chain_stat_active::Column::Bucket,
+                    chain_stat_active::Column::Address,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
+            .exec_without_returning(db)
+            .await?;
+    }
+    // distinct signers can't be summed across batches, recount the touched buckets
+    for (interval, bucket) in buckets {
+        let count = chain_stat_active::Entity::find()
+            .filter(chain_stat_active::Column::Interval.eq(interval.clone()))
+            .filter(chain_stat_active::Column::Bucket.eq(bucket))
+            .count(db)
+            .await?;
+        let stat = chain_stat::Model {
+            interval,
+            bucket,
+            metric: chain_stat::ACTIVE_ACCOUNTS.to_string(),
+            key: String::new(),
+            value: BigDecimal::from(count),
+            updated_at: now,
+        };
+        chain_stat::Entity::insert(stat.into_active())
+            .on_conflict(
+                OnConflict::columns(chain_stat::Model::pk())
+                    .update_columns([chain_stat::Column::Value, chain_stat::Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+    }
+    Ok(())
+}
+
+// folds the next batch of built blocks above the cursor into the stats, returns the new cursor.
+pub async fn update_stats(cursor: i64, db: &DatabaseConnection) -> Result<i64, DbErr> {
+    let built = Cursor::built_height(db, cursor).await?;
+    if built <= cursor {
+        return Ok(cursor);
+    }
+    let upper = built.min(cursor + BATCH_BLOCKS);
+    let mut blocks = block_entity::Entity::find()
+        .filter(block_entity::Column::Number.gte(cursor))
+        .filter(block_entity::Column::Number.lte(upper))
+        .order_by_asc(block_entity::Column::Number)
+        .all(db)
+        .await?;
+    let prev_time = blocks.first().filter(|b| b.number == cursor).map(|b| b.event_time);
+    blocks.retain(|b| b.number > cursor);
+    let txs = tx_entity::Entity::find()
+        .filter(tx_entity::Column::BlockNumber.gt(cursor))
+        .filter(tx_entity::Column::BlockNumber.lte(upper))
+        .all(db)
+        .await?;
+
+    let mut stats = Stats::default();
+    stats.add_blocks(&blocks, prev_time);
+    stats.add_txs(&txs);
+    let transfers: HashMap<String, i64> = txs
+        .iter()
+        .filter(|tx| tx.sub_type == "TransferFungibleToken")
+        .map(|tx| (tx.hash.clone(), tx.event_time))
+        .collect();
+    for chunk in transfers.keys().cloned().collect_vec().chunks(1000) {
+        for state in tx_state::Entity::find().filter(tx_state::Column::Hash.is_in(chunk.to_vec())).all(db).await? {
+            match parse_from_json_str::<TransactionWithResult>(&state.json) {
+                Ok(tx) => stats.add_transfer(transfers[&state.hash], &tx),
+                Err(e) => error!("{e}"),
+            }
+        }
+    }
+
+    let txn = db.begin().await?;
+    save_stats(&txn, stats, active_rows(&txs)).await?;
+    Cursor::set(&txn, CURSOR, upper).await?;
+    txn.commit().await?;
+    info!("analytics {cursor} ~ {upper}: {} blocks {} txs", blocks.len(), txs.len());
+    Ok(upper)
+}
+
+pub async fn analytics_loop(db: DatabaseConnection) {
+    info!("analytics loop start");
+    tokio::spawn(async move {
+        let mut cursor = Cursor::get(&db, CURSOR).await.unwrap_or(0);
+        loop {
+            let next = update_stats(cursor, &db).await.unwrap_or_else(|err| {
+                error!("analytics fail: {err}");
+                cursor
+            });
+            // keep going while catching up
+            if next == cursor {
+                sleep(Duration::from_secs(30)).await;
+            }
+            cursor = next;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
@@ -0,0 +1,298 @@
+use std::net::SocketAddr;
+
+use std::convert::Infallible;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use futures::{pin_mut, Stream, StreamExt};
+use itertools::Itertools;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use dotenvy::var;
+use log::{error, info};
+
+use crate::service::event_service::{EventFilter, EventHub};
+use crate::service::graphql_service::{schema, LmSchema};
+use crate::{
+    account_mapper, balance_entity, block_entity, chain_event, library::common::parse_from_json_str, nft_file, nft_history,
+    nft_owner, summary, transaction::TransactionWithResult, tx_entity, tx_state,
+};
+
+const DEFAULT_LIMIT: u64 = 20;
+const MAX_LIMIT: u64 = 100;
+
+pub struct ApiError(StatusCode, String);
+
+impl ApiError {
+    pub fn not_found(what: &str) -> ApiError {
+        ApiError(StatusCode::NOT_FOUND, format!("{what} not found"))
+    }
+
+    pub fn bad_request(msg: &str) -> ApiError {
+        ApiError(StatusCode::BAD_REQUEST, msg.to_string())
+    }
+
+    pub fn gone(msg: String) -> ApiError {
+        ApiError(StatusCode::GONE, msg)
+    }
+}
+
+impl From<DbErr> for ApiError {
+    fn from(err: DbErr) -> ApiError {
+        error!("api db error: {err}");
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, "internal error".to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<u64>,
+    pub cursor: Option<String>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> u64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // pass back as cursor for the next page, none on the last one
+    pub next_cursor: Option<String>,
+}
+
+// "{event_time}:{hash}" of the last item of a page
+pub fn parse_cursor(cursor: &str) -> Option<(i64, String)> {
+    let (time, hash) = cursor.split_once(':')?;
+    Some((time.parse().ok()?, hash.to_string()))
+}
+
+async fn latest_blocks(State(db): State<DatabaseConnection>, Query(q): Query<PageQuery>) -> ApiResult<Vec<block_entity::Model>> {
+    let blocks = block_entity::Entity::find()
+        .order_by_desc(block_entity::Column::Number)
+        .limit(q.limit())
+        .all(&db)
+        .await?;
+    Ok(Json(blocks))
+}
+
+// number or hash
+async fn block(State(db): State<DatabaseConnection>, Path(id): Path<String>) -> ApiResult<block_entity::Model> {
+    let query = match id.parse::<i64>() {
+        Ok(number) => block_entity::Entity::find().filter(block_entity::Column::Number.eq(number)),
+        Err(_) => block_entity::Entity::find().filter(block_entity::Column::Hash.eq(id)),
+    };
+    query.one(&db).await?.map(Json).ok_or(ApiError::not_found("block"))
+}
+
+async fn tx(State(db): State<DatabaseConnection>, Path(hash): Path<String>) -> ApiResult<Value> {
+    let tx = tx_entity::Entity::find_by_id(hash.clone())
+        .one(&db)
+        .await?
+        .ok_or(ApiError::not_found("tx"))?;
+    let decoded = tx_state::Entity::find_by_id(hash)
+        .one(&db)
+        .await?
+        .and_then(|state| parse_from_json_str::<TransactionWithResult>(&state.json).ok());
+    Ok(Json(json!({ "tx": tx, "transaction": decoded })))
+}
+
+async fn account_txs(
+    State(db): State<DatabaseConnection>,
+    Path(address): Path<String>,
+    Query(q): Query<PageQuery>,
+) -> ApiResult<Page<tx_entity::Model>> {
+    let mut query = account_mapper::Entity::find()
+        .filter(account_mapper::Column::Address.eq(address))
+        .order_by_desc(account_mapper::Column::EventTime)
+        .order_by_desc(account_mapper::Column::Hash)
+        .limit(q.limit() + 1);
+    if let Some(cursor) = &q.cursor {
+        let (time, hash) = parse_cursor(cursor).ok_or(ApiError::bad_request("invalid cursor"))?;
+        query = query.filter(
+            Condition::any()
+                .add(account_mapper::Column::EventTime.lt(time))
+                .add(
+                    Condition::all()
+                        .add(account_mapper::Column::EventTime.eq(time))
+                        .add(account_mapper::Column::Hash.lt(hash)),
+                ),
+        );
+    }
+    let mut rows = query.all(&db).await?;
+    let next_cursor = if rows.len() as u64 > q.limit() {
+        rows.truncate(q.limit() as usize);
+        rows.last().map(|r| format!("{}:{}", r.event_time, r.hash))
+    } else {
+        None
+    };
+    let mut txs = tx_entity::Entity::find()
+        .filter(tx_entity::Column::Hash.is_in(rows.iter().map(|r| r.hash.clone())))
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|tx| (tx.hash.clone(), tx))
+        .collect::<std::collections::HashMap<_, _>>();
+    let items = rows.iter().filter_map(|r| txs.remove(&r.hash)).collect_vec();
+    Ok(Json(Page { items, next_cursor }))
+}
+
+async fn account_balance(State(db): State<DatabaseConnection>, Path(address): Path<String>) -> ApiResult<balance_entity::Model> {
+    balance_entity::Entity::find_by_id(address)
+        .one(&db)
+        .await?
+        .map(Json)
+        .ok_or(ApiError::not_found("balance"))
+}
+
+async fn nft(State(db): State<DatabaseConnection>, Path(token_id): Path<String>) -> ApiResult<Value> {
+    let owner = nft_owner::Entity::find_by_id(token_id.clone()).one(&db).await?;
+    let file = nft_file::Entity::find_by_id(token_id.clone()).one(&db).await?;
+    if owner.is_none() && file.is_none() {
+        return Err(ApiError::not_found("nft"));
+    }
+    let history = nft_history::Entity::find()
+        .filter(nft_history::Column::TokenId.eq(token_id))
+        .order_by_asc(nft_history::Column::BlockNumber)
+        .order_by_asc(nft_history::Column::TxIndex)
+        .all(&db)
+        .await?;
+    Ok(Json(json!({ "owner": owner, "file": file, "history": history })))
+}
+
+async fn latest_summary(State(db): State<DatabaseConnection>) -> ApiResult<summary::Model> {
+    summary::Entity::find()
+        .order_by_desc(summary::Column::BlockNumber)
+        .order_by_desc(summary::Column::Id)
+        .one(&db)
+        .await?
+        .map(Json)
+        .ok_or(ApiError::not_found("summary"))
+}
+
+async fn graphql(State(schema): State<LmSchema>, req: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(req.into_inner()).await.into()
+}
+
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    pub topics: Option<String>,
+    pub address: Option<String>,
+    pub token: Option<String>,
+    pub since: Option<i64>,
+}
+
+impl StreamQuery {
+    fn into_parts(self) -> (EventFilter, Option<i64>) {
+        let filter = EventFilter { topics: self.topics, address: self.address, token: self.token };
+        (filter, self.since)
+    }
+}
+
+// a since older than the retention window would silently skip the pruned events.
+async fn event_stream(db: DatabaseConnection, q: StreamQuery) -> Result<impl Stream<Item = chain_event::Model>, ApiError> {
+    let (filter, since) = q.into_parts();
+    if let Some(since) = since {
+        if let Some(oldest) = EventHub::pruned_since(&db, since).await? {
+            return Err(ApiError::gone(format!("events after {since} were pruned, the oldest kept is {oldest}")));
+        }
+    }
+    Ok(EventHub::subscribe(db, filter, since))
+}
+
+async fn stream_sse(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Query(mut q): Query<StreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    // a reconnecting EventSource resumes from the last id it saw
+    if q.since.is_none() {
+        q.since = headers
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+    }
+    let events = event_stream(db, q).await?.map(|e| {
+        let event = Event::default().event(e.kind.clone()).id(e.seq.unwrap_or_default().to_string());
+        Ok(event.json_data(&e).unwrap_or_else(|_| Event::default().comment("unserializable event")))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+async fn stream_ws(
+    State(db): State<DatabaseConnection>,
+    Query(q): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    match event_stream(db, q).await {
+        Ok(events) => ws.on_upgrade(move |socket| forward_events(socket, events)),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn forward_events(mut socket: WebSocket, events: impl Stream<Item = chain_event::Model>) {
+    pin_mut!(events);
+    loop {
+        tokio::select! {
+            Some(e) = events.next() => {
+                let Ok(text) = serde_json::to_string(&e) else { continue };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                _ => {}
+            },
+            else => return,
+        }
+    }
+}
+
+pub fn router(db: DatabaseConnection) -> Router {
+    let graphql = Router::new()
+        .route("/v1/graphql", post(graphql))
+        .with_state(schema(db.clone()));
+    Router::new()
+        .route("/v1/blocks", get(latest_blocks))
+        .route("/v1/blocks/:id", get(block))
+        .route("/v1/txs/:hash", get(tx))
+        .route("/v1/accounts/:address/txs", get(account_txs))
+        .route("/v1/accounts/:address/balance", get(account_balance))
+        .route("/v1/nfts/:token_id", get(nft))
+        .route("/v1/summary", get(latest_summary))
+        .route("/v1/stream/sse", get(stream_sse))
+        .route("/v1/stream/ws", get(stream_ws))
+        .with_state(db)
+        .merge(graphql)
+}
+
+// served only when API_ADDR is set
+pub async fn api_loop(db: DatabaseConnection) {
+    let Some(addr) = var("API_ADDR").ok().filter(|a| !a.trim().is_empty()) else {
+        return;
+    };
+    let addr: SocketAddr = addr.parse().expect("API_ADDR must be host:port");
+    info!("api listening on {addr}");
+    if let Err(err) = axum::Server::bind(&addr).serve(router(db).into_make_service()).await {
+        error!("api server stopped: {err}");
+    }
+}
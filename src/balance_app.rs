@@ -1,18 +1,34 @@
 use crate::library::common::now;
 use crate::transaction::{Job, TransactionWithResult};
+use crate::transaction::token_transaction::NftStatus;
 use crate::entity::*;
 use bigdecimal::{BigDecimal, Zero};
-use log::LevelFilter;
+use itertools::Itertools;
+use log::{error, LevelFilter};
 use sea_orm::DatabaseConnection;
 use sea_orm::*;
-use sea_query::OnConflict;
+use sea_query::{Alias, ColumnDef, Expr, OnConflict, Table};
+use serde_json::json;
+use crate::service::cursor_service::Cursor;
+use crate::service::precision_service::Precision;
 use tokio::time::sleep;
 use std::collections::HashMap;
 use std::sync::Mutex;
 use std::time::Duration;
 use std::vec;
 
-pub static mut BAL_VEC: Mutex<Vec<(TransactionWithResult, String)>> = Mutex::new(Vec::new());
+// tx, hash, block number and tx index
+pub static mut BAL_VEC: Mutex<Vec<(TransactionWithResult, String, i64, i32)>> = Mutex::new(Vec::new());
+
+// height up to which every balance change is in the remote ledger
+pub const CURSOR: &str = "balance";
+
+// balances and ledger rows whose remote save failed, retried together with the next round
+#[derive(Default)]
+struct Unsaved {
+    balances: HashMap<String, balance_entity::Model>,
+    changes: Vec<balance_change::ActiveModel>,
+}
 
 async fn db_connn(url: String) -> DatabaseConnection {
     let mut opt = ConnectOptions::new(url);
@@ -38,6 +54,25 @@ async fn init_db(db: &DatabaseConnection) {
     let _ = db.execute(db.get_database_backend().build(&stmt)).await;
     let _ = db.execute(db.get_database_backend().build(&stmt2)).await;
     let _ = db.execute(db.get_database_backend().build(&stmt3)).await;
+    // spend rows queued before the spending position was kept
+    for stmt in [
+        Table::alter().table(spend_tx::Entity)
+            .add_column(ColumnDef::new(Alias::new("spent_by")).string().not_null().default("")).to_owned(),
+        Table::alter().table(spend_tx::Entity)
+            .add_column(ColumnDef::new(Alias::new("block_number")).big_integer().not_null().default(0)).to_owned(),
+        Table::alter().table(spend_tx::Entity)
+            .add_column(ColumnDef::new(Alias::new("tx_index")).integer().not_null().default(0)).to_owned(),
+    ] {
+        let _ = db.execute(db.get_database_backend().build(&stmt)).await;
+    }
+    // balance tables created before the scaled columns existed
+    for col in ["free_scaled", "locked_scaled"] {
+        let stmt = Table::alter()
+            .table(balance_entity::Entity)
+            .add_column(ColumnDef::new(Alias::new(col)).decimal().not_null().default(0))
+            .to_owned();
+        let _ = db.execute(db.get_database_backend().build(&stmt)).await;
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -60,7 +95,13 @@ fn find_lock_tx(hash: String) -> Select<balance_tx::Entity> {
 async fn balance_check_and_update(
     remote_db: &DatabaseConnection,
     local_db: &DatabaseConnection,
+    unsaved: &mut Unsaved,
 ) {
+    // read before draining so every block counted here has its txs queued already
+    let built = match Cursor::get(remote_db, CURSOR).await {
+        Ok(cursor) => Cursor::built_height(remote_db, cursor).await,
+        Err(err) => Err(err),
+    };
     let txs;
     unsafe {
         let mut v = BAL_VEC.lock().unwrap();
@@ -69,78 +110,68 @@ async fn balance_check_and_update(
     }
     let mut bal_map: HashMap<String, balance_entity::Model> = HashMap::new();
     let mut next: Vec<spend_tx::Model> = vec![];
-    let mut v_bal_op : Vec<BalanceOp> = vec![];
+    let mut v_bal_op : Vec<(BalanceOp, String, i64, i32)> = vec![];
     let mut v_bal_spend: Vec<(String, BigDecimal, BigDecimal)> = vec![];
-    let mut m_to_owner: HashMap<String, (BigDecimal, String, String)> = HashMap::new();
+    let mut m_to_owner: HashMap<String, (BigDecimal, String, String, spend_tx::Model)> = HashMap::new();
     let mut v_add = vec![];
+    let mut v_change: Vec<balance_change::ActiveModel> = vec![];
 
-    for (tx_res, hash) in txs {
-        v_bal_op.append(&mut tx_res.update_balance(hash.clone()).await);
+    for (tx_res, hash, block_number, tx_index) in txs {
+        for op in tx_res.update_balance(hash.clone()).await {
+            v_bal_op.push((op, hash.clone(), block_number, tx_index));
+        }
     }
 
     let spends = spend_tx::Entity::find().all(local_db).await.unwrap();
-    for spend_tx::Model { target, hash, token, t } in spends {
+    for spend in spends {
+        let spend_tx::Model { target, hash, token, t, .. } = spend.clone();
+        let change = |address: &str, free: BigDecimal, lock: BigDecimal| balance_change::Model::change(
+            &spend.spent_by, address, &token, free, lock, spend.block_number, spend.tx_index,
+        );
         match t {
             0 => match find_bal_tx(hash.clone(), target.clone()).one(local_db).await {
                 Ok(Some(mut m)) => {
+                    v_change.push(change(&m.address, -m.free.clone(), BigDecimal::zero()));
                     v_bal_spend.push((m.address.clone(), -m.free.clone(), BigDecimal::zero()));
                     m.spend = true;
                     let _ = balance_tx::Entity::update(m.into_active_model())
                         .exec(local_db).await;
                 }
-                _ => {
-                    next.push(spend_tx::Model {
-                        target: target.clone(),
-                        hash: hash,
-                        token: token,
-                        t: 0,
-                    });
-                }
+                _ => next.push(spend.clone()),
             }
             1 => match find_lock_tx(hash.clone()).one(local_db).await {
                 Ok(Some(mut m)) => {
+                    v_change.push(change(&m.address, BigDecimal::zero(), -m.lock.clone()));
                     v_bal_spend.push((m.address.clone(), BigDecimal::zero(), -m.lock.clone()));
                     m.spend = true;
                     let _ = balance_tx::Entity::update(m.into_active_model())
                         .exec(local_db).await;
                 }
-                _ => {
-                    next.push(spend_tx::Model {
-                        target: "-".to_string(),
-                        hash: hash,
-                        token: token,
-                        t: 1,
-                    });
-                }
+                _ => next.push(spend.clone()),
             }
             2 => match find_lock_tx(hash.clone()).one(local_db).await {
                 Ok(Some(mut m)) => {
                     match m_to_owner.get(&hash) {
-                        Some((prev, a, t)) => m_to_owner.insert(hash.clone(),  (prev + m.lock.clone(), a.to_owned(), t.to_owned())),
-                        None => m_to_owner.insert(hash.clone(),  (m.lock.clone(), m.address.clone(), token.clone())),
+                        Some((prev, a, t, s)) => m_to_owner.insert(hash.clone(),  (prev + m.lock.clone(), a.to_owned(), t.to_owned(), s.to_owned())),
+                        None => m_to_owner.insert(hash.clone(),  (m.lock.clone(), m.address.clone(), token.clone(), spend.clone())),
                     };
+                    v_change.push(change(&m.address, BigDecimal::zero(), -m.lock.clone()));
                     v_bal_spend.push((m.address.clone(), BigDecimal::zero(), -m.lock.clone()));
                     m.spend = true;
                     let _ = balance_tx::Entity::update(m.into_active_model())
                         .exec(local_db).await;
                 } 
-                _ => {
-                    next.push(spend_tx::Model {
-                        target: target.clone(),
-                        hash: hash,
-                        token: token,
-                        t: 2,
-                    });
-                }
+                _ => next.push(spend.clone()),
             } 
             _ => panic!("spend 't' is wrong value")
         }
     }
     spend_tx::Entity::delete_many().exec(local_db).await.unwrap();
 
-    for bal_op in v_bal_op.clone() {
+    for (bal_op, tx_hash, block_number, tx_index) in v_bal_op.clone() {
         match bal_op {
             BalanceOp::AddFree { hash, address, free, token } => {
+                v_change.push(balance_change::Model::change(&tx_hash, &address, &token, free.clone(), BigDecimal::zero(), block_number, tx_index));
                 v_add.push(balance_tx::Model {
                     hash,
                     address,
@@ -151,6 +182,7 @@ async fn balance_check_and_update(
                 });
             }
             BalanceOp::AddLock { hash, address, free, lock, token } => {
+                v_change.push(balance_change::Model::change(&tx_hash, &address, &token, free.clone(), lock.clone(), block_number, tx_index));
                 v_add.push(balance_tx::Model {
                     hash,
                     address,
@@ -164,7 +196,7 @@ async fn balance_check_and_update(
         }
     }
 
-    for bal_op in v_bal_op {
+    for (bal_op, spent_by, block_number, tx_index) in v_bal_op {
         match bal_op {
             BalanceOp::SpendFree { hash, address, token } => {
                 next.push(spend_tx::Model {
@@ -172,6 +204,9 @@ async fn balance_check_and_update(
                     hash: hash,
                     token: token,
                     t: 0,
+                    spent_by,
+                    block_number,
+                    tx_index,
                 });
             }
             BalanceOp::SpendLock { hash, token } => {
@@ -180,6 +215,9 @@ async fn balance_check_and_update(
                     hash: hash,
                     token: token,
                     t: 1,
+                    spent_by,
+                    block_number,
+                    tx_index,
                 });
             } 
             BalanceOp::ToOwner { new_hash, hash, token } => {
@@ -188,16 +226,22 @@ async fn balance_check_and_update(
                     hash: hash,
                     token: token,
                     t: 2,
+                    spent_by,
+                    block_number,
+                    tx_index,
                 });
             } 
             _ => (),
         }
     }
 
+    // a queued spend holds the ledger below its block, rows from before positions were kept have none
+    let waiting = next.iter().map(|m| m.block_number).filter(|n| *n > 0).min();
     let v: Vec<spend_tx::ActiveModel> = next.into_iter().map(|m| m.into_active_model()).collect();
     let _ = spend_tx::Entity::insert_many(v).do_nothing().exec(local_db).await;
     
-    for (hash, (free, address, token)) in m_to_owner {
+    for (hash, (free, address, token, spend)) in m_to_owner {
+        v_change.push(balance_change::Model::change(&spend.spent_by, &address, &token, free.clone(), BigDecimal::zero(), spend.block_number, spend.tx_index));
         v_add.push(balance_tx::Model { hash, address, free: free, lock: BigDecimal::zero(), spend: false, token });
     }
 
@@ -217,6 +261,8 @@ async fn balance_check_and_update(
                             address: m.address.clone(),
                             free: m.free,
                             locked: m.lock,
+                            free_scaled: BigDecimal::zero(),
+                            locked_scaled: BigDecimal::zero(),
                             created_at: now(),
                             updated_at: now(),
                         }
@@ -240,6 +286,8 @@ async fn balance_check_and_update(
                             address: address.clone(),
                             free: free,
                             locked: lock,
+                            free_scaled: BigDecimal::zero(),
+                            locked_scaled: BigDecimal::zero(),
                             created_at: now(),
                             updated_at: now(),
                         }
@@ -250,28 +298,149 @@ async fn balance_check_and_update(
         bal_map.insert(address, b);
     }
 
+    let precision = Precision::of(remote_db, "LM").await;
+    let mut bal_map: HashMap<String, balance_entity::Model> = bal_map.into_iter().map(|(k, m)| (k, m.scaled(precision))).collect();
     let v: Vec::<balance_entity::ActiveModel> = bal_map.clone().values().into_iter().map(|m| m.to_owned().into_active_model()).collect();
     let _ = balance_entity::Entity::insert_many(v.clone()).on_conflict(
         OnConflict::column(balance_entity::Column::Address)
-            .update_columns([balance_entity::Column::Free, balance_entity::Column::Locked])    
+            .update_columns([
+                balance_entity::Column::Free,
+                balance_entity::Column::Locked,
+                balance_entity::Column::FreeScaled,
+                balance_entity::Column::LockedScaled,
+            ])    
             .value(balance_entity::Column::UpdatedAt, now())
             .to_owned()
     ).do_nothing().exec(local_db).await;
+    for (address, m) in std::mem::take(&mut unsaved.balances) {
+        bal_map.entry(address).or_insert(m);
+    }
+    let mut changes = std::mem::take(&mut unsaved.changes);
+    changes.append(&mut v_change);
+    // a balance event is sequenced with the block of the last change it includes
+    let blocks = changes
+        .iter()
+        .filter(|c| c.token.as_ref() == "LM")
+        .map(|c| (c.address.as_ref().clone(), *c.block_number.as_ref()))
+        .into_grouping_map()
+        .max();
+    let latest = blocks.values().max().copied().unwrap_or_default();
+    let events = bal_map.values().map(|m| chain_event::Model::event(
+        chain_event::BALANCE,
+        vec![m.address.clone()],
+        Some("LM".to_string()),
+        blocks.get(&m.address).copied().unwrap_or(latest),
+        json!({ "address": m.address, "free": m.free, "locked": m.locked }),
+    )).collect_vec();
+    let height = match (built, waiting) {
+        (Ok(built), Some(n)) => Some(built.min(n - 1)),
+        (Ok(built), None) => Some(built),
+        (Err(err), _) => {
+            error!("balance height read fail: {err}");
+            None
+        }
+    };
     let v: Vec::<balance_entity::ActiveModel> = bal_map.clone().values().into_iter().map(|m| m.to_owned().to_bal()).collect();
-    let _ = balance_entity::Entity::insert_many(v).on_conflict(
-        OnConflict::column(balance_entity::Column::Address)
-            .update_columns([balance_entity::Column::Free, balance_entity::Column::Locked])    
-            .value(balance_entity::Column::UpdatedAt, now())
-            .to_owned()
-    ).do_nothing().exec(remote_db).await;
+    // balances and their ledger rows are saved together so snapshots never read a partial ledger
+    let rows = changes.clone();
+    let res = remote_db.transaction::<_, (), DbErr>(|txn| {
+        Box::pin(async move {
+            if !v.is_empty() {
+                balance_entity::Entity::insert_many(v).on_conflict(
+                    OnConflict::column(balance_entity::Column::Address)
+                        .update_columns([
+                            balance_entity::Column::Free,
+                            balance_entity::Column::Locked,
+                            balance_entity::Column::FreeScaled,
+                            balance_entity::Column::LockedScaled,
+                        ])
+                        .value(balance_entity::Column::UpdatedAt, now())
+                        .to_owned()
+                ).exec_without_returning(txn).await?;
+            }
+            for chunk in rows.chunks(1000) {
+                balance_change::Entity::insert_many(chunk.to_vec()).exec_without_returning(txn).await?;
+            }
+            if let Some(height) = height {
+                if height > Cursor::get(txn, CURSOR).await? {
+                    Cursor::set(txn, CURSOR, height).await?;
+                }
+            }
+            Ok(())
+        })
+    }).await;
+    // a balance that wasn't saved must not be announced
+    if let Err(err) = res {
+        error!("balance save fail, retrying with the next round: {err}");
+        unsaved.balances = bal_map;
+        unsaved.changes = changes;
+    } else if !events.is_empty() {
+        if let Err(err) = chain_event::Entity::insert_many(events).exec_without_returning(remote_db).await {
+            error!("balance event save fail: {err}");
+        }
+    }
+}
+
+// nfts never go through balance_tx, their holders are the distinct owners of live tokens
+pub async fn nft_holder_counts(db: &DatabaseConnection) -> Result<Vec<(String, i64)>, DbErr> {
+    nft_owner::Entity::find()
+        .select_only()
+        .column(nft_owner::Column::DefinitionId)
+        .column_as(Expr::col(nft_owner::Column::Owner).count_distinct(), "holders")
+        .filter(nft_owner::Column::Status.ne(NftStatus::Burned.as_str()))
+        .group_by(nft_owner::Column::DefinitionId)
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+async fn update_holder_count(
+    remote_db: &DatabaseConnection,
+    local_db: &DatabaseConnection,
+) {
+    let counts: Vec<(String, i64)> = match balance_tx::Entity::find()
+        .select_only()
+        .column(balance_tx::Column::Token)
+        .column_as(Expr::col(balance_tx::Column::Address).count_distinct(), "holders")
+        .filter(balance_tx::Column::Spend.eq(false))
+        .filter(
+            Condition::any()
+                .add(balance_tx::Column::Free.gt(BigDecimal::zero()))
+                .add(balance_tx::Column::Lock.gt(BigDecimal::zero())),
+        )
+        .group_by(balance_tx::Column::Token)
+        .into_tuple()
+        .all(local_db)
+        .await {
+            Ok(v) => v,
+            Err(err) => {
+                error!("holder count query failed {err}");
+                return;
+            }
+        };
+    let nft_counts = match nft_holder_counts(remote_db).await {
+        Ok(v) => v,
+        Err(err) => {
+            error!("nft holder count query failed {err}");
+            vec![]
+        }
+    };
+    for (token, holders) in counts.into_iter().chain(nft_counts) {
+        let _ = token_definition::Entity::update_many()
+            .col_expr(token_definition::Column::HolderCount, Expr::value(holders))
+            .filter(token_definition::Column::DefinitionId.eq(token))
+            .exec(remote_db).await;
+    }
 }
 
 pub async fn balance_loop(remote_db: DatabaseConnection, sqlite_url: String) {
     tokio::spawn(async move { 
         let local_db = db_connn(sqlite_url).await;
         init_db(&local_db).await;
+        let mut unsaved = Unsaved::default();
         loop {
-            balance_check_and_update(&remote_db, &local_db).await;
+            balance_check_and_update(&remote_db, &local_db, &mut unsaved).await;
+            update_holder_count(&remote_db, &local_db).await;
             sleep(Duration::from_secs(10)).await;
         }
     })
@@ -1,18 +1,25 @@
+use std::collections::HashMap;
 use std::time::Duration;
 use std::vec;
 
 use crate::{
-    balance_app::BAL_VEC, block_entity::Model as BlockModel, block_state::Entity as BlockState, entity::*, library::common::*, model::{block::Block, node_status::NodeStatus}, service::api_service::ApiService, transaction::{
-        common::Common, Job, TransactionWithResult
+    balance_app::BAL_VEC, block_entity::Model as BlockModel, block_state::Entity as BlockState, entity::*, library::common::*, model::{block::Block, node_status::NodeStatus}, service::{api_service::ApiService, counter_service::Counter, cursor_service::Cursor}, transaction::{
+        common::Common, unknown::UnknownTx, Job, TransactionWithResult
     }
 };
-use sea_orm::sea_query::{Expr, OnConflict};
+use bigdecimal::{BigDecimal, Zero};
+use itertools::Itertools;
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
 use sea_orm::DatabaseConnection;
 use sea_orm::*;
 
-use log::{error, info};
+use log::{error, info, warn};
+use serde_json::json;
 use tokio::time::sleep;
 
+// an unbuilt block below the head older than this is rebuilt
+const STALL_SECS: i64 = 60;
+
 async fn get_last_built_or_genesis_block_hash(
     node_status: &NodeStatus,
     db: &DatabaseConnection,
@@ -40,6 +47,7 @@ async fn save_diff_state_proc(
         let mut block_states = vec![];
         let mut txs = vec![];
         let mut txss = vec![];
+        let mut unknown_tags: HashMap<String, i64> = HashMap::new();
         let block = ApiService::get_block_always(&curr_block_hash.to_owned())
             .await
             .ok()
@@ -61,6 +69,10 @@ async fn save_diff_state_proc(
                 Ok(json) => {
                     match parse_from_json_str::<TransactionWithResult>(&json) {
                         Ok(tx) => {
+                            for tag in tx.unknown_tags() {
+                                UnknownTx::record(&tag);
+                                *unknown_tags.entry(tag).or_default() += 1;
+                            }
                             let tx_state = tx_state::Model::from(
                                 tx_hash.as_str(),
                                 curr_block_hash.as_str(),
@@ -94,10 +106,20 @@ async fn save_diff_state_proc(
             .exec(&txn)
             .await;
 
+        let mut r3 = Ok(());
+        for (tag, n) in &unknown_tags {
+            r3 = Counter::add(&txn, &format!("{}{tag}", chain_counter::UNKNOWN_TX), *n).await;
+            if r3.is_err() {
+                break;
+            }
+        }
+
         if r1.is_err() {
             panic!("block state store is fail {:?}", &curr_block_hash);
         } else if r2.is_err() {
             error!("txs state store is fail {:?}", &block.transaction_hashes);
+        } else if let Err(e) = r3 {
+            error!("unknown tx counter store is fail {e}");
         } else {
             txn.commit().await.unwrap();
         }
@@ -110,6 +132,336 @@ async fn save_diff_state_proc(
     }
 }
 
+// payouts are stored on every build, the ledger totals only the first time a block is stored.
+pub async fn save_rewards(
+    txn: &DatabaseTransaction,
+    block_number: i64,
+    snapshots: &[snapshot::Model],
+    mut payouts: Vec<reward_payout::Model>,
+    activities: Vec<dao_activity::Model>,
+    is_new_block: bool,
+) -> Result<(), DbErr> {
+    link_reward_snapshot(txn, &mut payouts, block_number, snapshots).await?;
+    if !payouts.is_empty() {
+        let v = payouts.iter().cloned().map(|m| m.into_active_model()).collect_vec();
+        reward_payout::Entity::insert_many(v)
+            .on_conflict(
+                OnConflict::columns([reward_payout::Column::Hash, reward_payout::Column::Address])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec_without_returning(txn)
+            .await?;
+    }
+    if is_new_block {
+        save_reward_ledger(txn, activities, &payouts).await?;
+    }
+    Ok(())
+}
+
+async fn link_reward_snapshot(
+    txn: &DatabaseTransaction,
+    payouts: &mut [reward_payout::Model],
+    block_number: i64,
+    snapshots: &[snapshot::Model],
+) -> Result<(), DbErr> {
+    // payouts indexed before their snapshot block are re-linked here.
+    for m in snapshots.iter().filter(|m| m.snapshot_type == "BuildSnapshot") {
+        reward_payout::Entity::update_many()
+            .col_expr(reward_payout::Column::SnapshotHash, Expr::value(m.hash.clone()))
+            .col_expr(reward_payout::Column::SnapshotNumber, Expr::value(block_number))
+            .filter(reward_payout::Column::BlockNumber.gte(block_number))
+            .filter(
+                Condition::any()
+                    .add(reward_payout::Column::SnapshotNumber.is_null())
+                    .add(reward_payout::Column::SnapshotNumber.lt(block_number)),
+            )
+            .exec(txn)
+            .await?;
+    }
+    if payouts.is_empty() {
+        return Ok(());
+    }
+    let latest_snapshot = snapshot::Entity::find()
+        .filter(snapshot::Column::SnapshotType.eq("BuildSnapshot"))
+        .filter(snapshot::Column::BlockNumber.lte(block_number))
+        .order_by_desc(snapshot::Column::BlockNumber)
+        .one(txn)
+        .await?;
+    if let Some(snapshot) = latest_snapshot {
+        for payout in payouts.iter_mut() {
+            payout.snapshot_hash = Some(snapshot.hash.clone());
+            payout.snapshot_number = Some(snapshot.block_number);
+        }
+    }
+    Ok(())
+}
+
+async fn save_reward_ledger(
+    txn: &DatabaseTransaction,
+    activities: Vec<dao_activity::Model>,
+    payouts: &[reward_payout::Model],
+) -> Result<(), DbErr> {
+    let mut activity_map: HashMap<(String, i64), dao_activity::Model> = HashMap::new();
+    for m in activities {
+        let key = (m.address.clone(), m.day);
+        let next = match activity_map.remove(&key) {
+            Some(prev) => prev.merge(&m),
+            None => m,
+        };
+        activity_map.insert(key, next);
+    }
+    if !activity_map.is_empty() {
+        let v = activity_map.into_values().map(|m| m.into_active_model()).collect_vec();
+        dao_activity::Entity::insert_many(v)
+            .on_conflict(
+                OnConflict::columns([dao_activity::Column::Address, dao_activity::Column::Day])
+                    .value(
+                        dao_activity::Column::ActivityPoint,
+                        Expr::col((dao_activity::Entity, dao_activity::Column::ActivityPoint))
+                            .add(Expr::col((Alias::new("excluded"), dao_activity::Column::ActivityPoint))),
+                    )
+                    .value(
+                        dao_activity::Column::ReceivedPoint,
+                        Expr::col((dao_activity::Entity, dao_activity::Column::ReceivedPoint))
+                            .add(Expr::col((Alias::new("excluded"), dao_activity::Column::ReceivedPoint))),
+                    )
+                    .value(dao_activity::Column::UpdatedAt, now())
+                    .to_owned(),
+            )
+            .exec_without_returning(txn)
+            .await?;
+    }
+
+    let mut total_map: HashMap<String, reward_total::Model> = HashMap::new();
+    for payout in payouts {
+        let prev = total_map.remove(&payout.address).unwrap_or(reward_total::Model {
+            address: payout.address.clone(),
+            total: BigDecimal::zero(),
+            payout_count: 0,
+            updated_at: now(),
+        });
+        total_map.insert(payout.address.clone(), prev.add_payout(&payout.amount));
+    }
+    if !total_map.is_empty() {
+        let v = total_map.into_values().map(|m| m.into_active_model()).collect_vec();
+        reward_total::Entity::insert_many(v)
+            .on_conflict(
+                OnConflict::column(reward_total::Column::Address)
+                    .value(
+                        reward_total::Column::Total,
+                        Expr::col((reward_total::Entity, reward_total::Column::Total))
+                            .add(Expr::col((Alias::new("excluded"), reward_total::Column::Total))),
+                    )
+                    .value(
+                        reward_total::Column::PayoutCount,
+                        Expr::col((reward_total::Entity, reward_total::Column::PayoutCount))
+                            .add(Expr::col((Alias::new("excluded"), reward_total::Column::PayoutCount))),
+                    )
+                    .value(reward_total::Column::UpdatedAt, now())
+                    .to_owned(),
+            )
+            .exec_without_returning(txn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn save_account_profiles(
+    txn: &DatabaseTransaction,
+    histories: Vec<account_history::Model>,
+) -> Result<(), DbErr> {
+    if histories.is_empty() {
+        return Ok(());
+    }
+    let v = histories.iter().cloned().map(|m| m.into_active_model()).collect_vec();
+    account_history::Entity::insert_many(v)
+        .on_conflict(
+            OnConflict::column(account_history::Column::Hash)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec_without_returning(txn)
+        .await?;
+
+    // applied in tx order, an older block processed late doesn't overwrite a newer profile.
+    for m in histories {
+        if let Some(profile) = m.profile() {
+            account_entity::Entity::update_many()
+                .set(profile)
+                .filter(account_entity::Column::Address.eq(m.address.clone()))
+                .filter(account_entity::Column::BlockNumber.lte(m.block_number))
+                .exec(txn)
+                .await?;
+        }
+        if let (Some(addrs), Some(source)) = (m.external_addresses(), m.external_address_source()) {
+            let is_latest = account_entity::Entity::find_by_id(m.address.clone())
+                .one(txn)
+                .await?
+                .is_none_or(|acc| acc.block_number <= m.block_number);
+            if is_latest {
+                external_address::Entity::update_many()
+                    .col_expr(external_address::Column::IsActive, Expr::value(false))
+                    .filter(external_address::Column::Address.eq(m.address.clone()))
+                    .filter(external_address::Column::Source.eq(source))
+                    .filter(external_address::Column::BlockNumber.lte(m.block_number))
+                    .exec(txn)
+                    .await?;
+            }
+            let v = addrs
+                .into_iter()
+                .unique_by(|a| (a.chain.clone(), a.external_address.clone()))
+                .map(|a| external_address::Model { is_active: is_latest, ..a }.into_active_model())
+                .collect_vec();
+            if !v.is_empty() {
+                external_address::Entity::insert_many(v)
+                    .on_conflict(
+                        OnConflict::columns([external_address::Column::Chain, external_address::Column::ExternalAddress])
+                            .update_columns([
+                                external_address::Column::Address,
+                                external_address::Column::Source,
+                                external_address::Column::IsActive,
+                                external_address::Column::BlockNumber,
+                                external_address::Column::UpdatedAt,
+                            ])
+                            .action_and_where(
+                                Expr::col((external_address::Entity, external_address::Column::BlockNumber))
+                                    .lte(Expr::col((Alias::new("excluded"), external_address::Column::BlockNumber))),
+                            )
+                            .to_owned(),
+                    )
+                    .exec_without_returning(txn)
+                    .await?;
+            }
+        }
+        if let Some(serde_json::Value::Object(summaries)) = &m.public_key_summaries {
+            let v = summaries
+                .iter()
+                .map(|(summary, desc)| {
+                    account_public_key::Model::from(
+                        &m.address,
+                        summary,
+                        desc.as_str().unwrap_or_default(),
+                        &m.hash,
+                        m.block_number,
+                        m.event_time,
+                    )
+                    .into_active_model()
+                })
+                .collect_vec();
+            if !v.is_empty() {
+                account_public_key::Entity::insert_many(v)
+                    .on_conflict(
+                        OnConflict::columns([account_public_key::Column::Address, account_public_key::Column::Summary])
+                            .update_columns([
+                                account_public_key::Column::Description,
+                                account_public_key::Column::AddedHash,
+                                account_public_key::Column::BlockNumber,
+                                account_public_key::Column::RemovedHash,
+                                account_public_key::Column::RemovedBlockNumber,
+                                account_public_key::Column::EventTime,
+                            ])
+                            .action_and_where(
+                                Expr::col((account_public_key::Entity, account_public_key::Column::BlockNumber))
+                                    .lte(Expr::col((Alias::new("excluded"), account_public_key::Column::BlockNumber))),
+                            )
+                            .to_owned(),
+                    )
+                    .exec_without_returning(txn)
+                    .await?;
+            }
+        }
+        if let Some(serde_json::Value::Object(removed)) = &m.removed_summaries {
+            if !removed.is_empty() {
+                account_public_key::Entity::update_many()
+                    .col_expr(account_public_key::Column::RemovedHash, Expr::value(m.hash.clone()))
+                    .col_expr(account_public_key::Column::RemovedBlockNumber, Expr::value(m.block_number))
+                    .filter(account_public_key::Column::Address.eq(m.address.clone()))
+                    .filter(account_public_key::Column::Summary.is_in(removed.keys().cloned()))
+                    .filter(account_public_key::Column::BlockNumber.lte(m.block_number))
+                    .exec(txn)
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn save_token_registry(
+    txn: &DatabaseTransaction,
+    definitions: Vec<token_definition::Model>,
+    supplies: Vec<token_definition::Model>,
+    is_new_block: bool,
+) -> Result<(), DbErr> {
+    if !definitions.is_empty() {
+        let v = definitions.into_iter()
+            .map(|m| (m.definition_id.clone(), m))
+            .collect::<HashMap<_, _>>()
+            .into_values()
+            .map(|m| m.into_active_model())
+            .collect_vec();
+        token_definition::Entity::insert_many(v)
+            .on_conflict(
+                OnConflict::column(token_definition::Column::DefinitionId)
+                    .update_columns([
+                        token_definition::Column::Name,
+                        token_definition::Column::Symbol,
+                        token_definition::Column::MinterGroup,
+                        token_definition::Column::Precision,
+                        token_definition::Column::IsNft,
+                        token_definition::Column::NftMinter,
+                        token_definition::Column::NftDataUrl,
+                        token_definition::Column::NftContentHash,
+                        token_definition::Column::Rarity,
+                        token_definition::Column::EventTime,
+                    ])
+                    .value(token_definition::Column::UpdatedAt, now())
+                    .to_owned(),
+            )
+            .exec_without_returning(txn)
+            .await?;
+    }
+
+    // mint and burn amounts are accumulated, so they are applied once per block.
+    let mut supply_map: HashMap<String, token_definition::Model> = HashMap::new();
+    for m in supplies {
+        let next = match supply_map.remove(&m.definition_id) {
+            Some(prev) => prev.add_supply(&m),
+            None => m,
+        };
+        supply_map.insert(next.definition_id.clone(), next);
+    }
+    if is_new_block && !supply_map.is_empty() {
+        let v = supply_map.into_values().map(|m| m.into_active_model()).collect_vec();
+        token_definition::Entity::insert_many(v)
+            .on_conflict(
+                OnConflict::column(token_definition::Column::DefinitionId)
+                    .value(
+                        token_definition::Column::TotalMinted,
+                        Expr::col((token_definition::Entity, token_definition::Column::TotalMinted))
+                            .add(Expr::col((Alias::new("excluded"), token_definition::Column::TotalMinted))),
+                    )
+                    .value(
+                        token_definition::Column::TotalBurned,
+                        Expr::col((token_definition::Entity, token_definition::Column::TotalBurned))
+                            .add(Expr::col((Alias::new("excluded"), token_definition::Column::TotalBurned))),
+                    )
+                    .value(
+                        token_definition::Column::TotalLocked,
+                        Expr::col((token_definition::Entity, token_definition::Column::TotalLocked))
+                            .add(Expr::col((Alias::new("excluded"), token_definition::Column::TotalLocked))),
+                    )
+                    .value(token_definition::Column::UpdatedAt, now())
+                    .to_owned(),
+            )
+            .exec_without_returning(txn)
+            .await?;
+    }
+    Ok(())
+}
+
 async fn parse_tx_and_update(
     db: DatabaseConnection,
     blc: Block,
@@ -120,6 +472,14 @@ async fn parse_tx_and_update(
     let mut nft_tx_vec: Vec<nft_tx::ActiveModel> = vec![];
     let mut new_acc_vec: Vec<account_entity::ActiveModel> = vec![];
     let mut acc_map_vec: Vec<account_mapper::Model> = vec![];
+    let mut dao_act_vec: Vec<dao_activity::Model> = vec![];
+    let mut reward_vec: Vec<reward_payout::Model> = vec![];
+    let mut snapshot_vec: Vec<snapshot::Model> = vec![];
+    let mut token_def_vec: Vec<token_definition::Model> = vec![];
+    let mut supply_vec: Vec<token_definition::Model> = vec![];
+    let mut acc_history_vec: Vec<account_history::Model> = vec![];
+    let mut event_vec: Vec<chain_event::ActiveModel> = vec![];
+    let mut bal_vec = vec![];
 
     for (tx_res, tx_hash) in txs {
 
@@ -130,45 +490,105 @@ async fn parse_tx_and_update(
             blc.header.number.clone(),
             tx_res.clone(),
         );
-        if let Some(nft) = tx.get_nft_active_model(&tx_entity, tx_res.signed_tx.sig.account.clone()) {
+        let tx_index = blc.transaction_hashes.iter().position(|h| h == &tx_hash).unwrap_or_default() as i32;
+        if let Some(nft) = tx.get_nft_active_model(&tx_entity, tx_index, tx_res.signed_tx.sig.account.clone()) {
+            if matches!(nft.action.as_ref().as_str(), "TransferNft" | "DisposeEntrustedNft") {
+                event_vec.push(chain_event::Model::event(
+                    chain_event::NFT_TRANSFER,
+                    vec![nft.from_addr.as_ref().clone(), nft.to_addr.as_ref().clone()],
+                    Some(nft.token_id.as_ref().clone()),
+                    blc.header.number,
+                    json!({
+                        "hash": tx_hash,
+                        "token_id": nft.token_id.as_ref(),
+                        "definition_id": tx.definition_id(),
+                        "from": nft.from_addr.as_ref(),
+                        "to": nft.to_addr.as_ref(),
+                    }),
+                ));
+            }
             nft_tx_vec.push(nft);
         }
         if let Some(acc) = tx.get_acc_active_model() {
+            event_vec.push(chain_event::Model::event(
+                chain_event::ACCOUNT,
+                vec![acc.address.as_ref().clone()],
+                None,
+                blc.header.number,
+                json!({ "hash": tx_hash, "address": acc.address.as_ref() }),
+            ));
             new_acc_vec.push(acc);
         }
-        acc_map_vec.append(&mut tx.get_account_mapper(tx_res.signed_tx.sig.account.clone(), tx_hash.clone(), tx.created_at()));
+        let mut acc_map = tx.get_account_mapper(tx_res.signed_tx.sig.account.clone(), tx_hash.clone(), tx.created_at());
+        event_vec.push(chain_event::Model::event(
+            chain_event::TX,
+            acc_map.iter().map(|m| m.address.clone()).unique().collect(),
+            Some(tx_entity.token_type.as_ref().clone()),
+            blc.header.number,
+            json!({
+                "hash": tx_hash,
+                "tx_type": tx_entity.tx_type.as_ref(),
+                "sub_type": tx_entity.sub_type.as_ref(),
+                "definition_id": tx.definition_id(),
+                "signer": tx_res.signed_tx.sig.account,
+            }),
+        ));
+        acc_map_vec.append(&mut acc_map);
+        dao_act_vec.append(&mut tx.get_dao_activities());
+        reward_vec.append(&mut tx_res.get_reward_payouts(&tx_hash, blc.header.number));
+        if let Some(mut snapshot) = tx.get_snapshot(tx_hash.clone(), blc.header.number) {
+            snapshot.tx_index = tx_index;
+            snapshot_vec.push(snapshot);
+        }
+        if let Some(def) = tx.get_token_definition() {
+            token_def_vec.push(def);
+        }
+        if let Some(supply) = tx.get_supply_change() {
+            supply_vec.push(supply);
+        }
+        if let Some(history) = tx_res.get_account_history(&tx_hash, blc.header.number) {
+            acc_history_vec.push(history);
+        }
         tx_entities.push(tx_entity);
         if tx_res.is_free_fungible() {
-            unsafe {
-                let mut v = BAL_VEC.lock().unwrap();
-                v.push((tx_res, tx_hash));
-            }
+            bal_vec.push((tx_res, tx_hash, blc.header.number, tx_index));
         }
     }
     let block_entity = BlockModel::from(&blc, blc_hash.clone());
+    let block_number = blc.header.number;
+    event_vec.insert(0, chain_event::Model::block(
+        block_number,
+        &blc_hash,
+        blc.transaction_hashes.len(),
+        block_entity.event_time.as_ref().to_owned(),
+    ));
 
     let save_res = &db
-        .transaction::<_, (), DbErr>(|txn| {
+        .transaction::<_, bool, DbErr>(|txn| {
             Box::pin(async move {
-                Insert::one(block_entity)
-                    .on_conflict(
-                        OnConflict::column(block_entity::Column::Hash)
-                            .do_nothing()
-                            .to_owned(),
-                    )
-                    .do_nothing()
-                    .exec(txn)
-                    .await?;
-                if !tx_entities.is_empty() {
-                    Insert::many(tx_entities)
+                // additive ledgers are only applied the first time a block is stored.
+                let is_new_block = matches!(
+                    Insert::one(block_entity)
                         .on_conflict(
-                            OnConflict::column(tx_entity::Column::Hash)
+                            OnConflict::column(block_entity::Column::Hash)
                                 .do_nothing()
                                 .to_owned(),
                         )
                         .do_nothing()
                         .exec(txn)
+                        .await?,
+                    TryInsertResult::Inserted(_)
+                );
+                if !tx_entities.is_empty() {
+                    let inserted = Insert::many(tx_entities)
+                        .on_conflict(
+                            OnConflict::column(tx_entity::Column::Hash)
+                                .do_nothing()
+                                .to_owned(),
+                        )
+                        .exec_without_returning(txn)
                         .await?;
+                    Counter::add(txn, chain_counter::TX, inserted as i64).await?;
                 }
                 if !nft_tx_vec.is_empty() {
                     Insert::many(nft_tx_vec)
@@ -182,16 +602,17 @@ async fn parse_tx_and_update(
                         .await?;
                 }
                 if !new_acc_vec.is_empty() {
-                    Insert::many(new_acc_vec)
+                    let inserted = Insert::many(new_acc_vec)
                         .on_conflict(
                             OnConflict::column(account_entity::Column::Address)
                                 .do_nothing()
                                 .to_owned(),
                         )
-                        .do_nothing()
                         .exec_without_returning(txn)
                         .await?;
+                    Counter::add(txn, chain_counter::ACCOUNT, inserted as i64).await?;
                 }
+                save_account_profiles(txn, acc_history_vec).await?;
                 block_state::Entity::update_many()
                     .col_expr(block_state::Column::IsBuild, Expr::value(true))
                     .filter(block_state::Column::Hash.eq(blc_hash))
@@ -203,21 +624,87 @@ async fn parse_tx_and_update(
                     .exec(txn)
                     .await?;
                 }
+                if !snapshot_vec.is_empty() {
+                    let v = snapshot_vec.iter().cloned().map(|m| m.into_active_model()).collect_vec();
+                    snapshot::Entity::insert_many(v)
+                        .on_conflict(
+                            OnConflict::column(snapshot::Column::Hash)
+                                .do_nothing()
+                                .to_owned(),
+                        )
+                        .do_nothing()
+                        .exec_without_returning(txn)
+                        .await?;
+                }
+                save_rewards(txn, block_number, &snapshot_vec, reward_vec, dao_act_vec, is_new_block).await?;
+                if is_new_block {
+                    chain_event::Entity::insert_many(event_vec)
+                        .exec_without_returning(txn)
+                        .await?;
+                }
+                save_token_registry(txn, token_def_vec, supply_vec, is_new_block).await?;
 
-                Ok(())
+                Ok(is_new_block)
             })
         })
         .await;
 
-    if let Err(err) = save_res {
-        panic!("save transaction process err: {err}");
+    match save_res {
+        // balances are applied once, after the block is stored
+        Ok(true) => unsafe {
+            let mut v = BAL_VEC.lock().unwrap();
+            v.extend(bal_vec);
+        },
+        Ok(false) => (),
+        // the block stays unbuilt and is picked up by rebuild_stalled
+        Err(err) => error!("block {} save transaction process err: {err}", blc.header.number),
     }
 }
 
+// rebuilds blocks whose first build failed from the stored block and tx states.
+async fn rebuild_stalled(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let stalled = Cursor::unbuilt_below_head(db, now() - STALL_SECS, 100).await?;
+    for state in stalled {
+        warn!("block {} below the head is unbuilt since {}, rebuilding", state.number, state.created_at);
+        let block = match parse_from_json_str::<Block>(&state.json) {
+            Ok(b) => b,
+            Err(e) => {
+                error!("block {} state is unreadable: {e}", state.number);
+                continue;
+            }
+        };
+        let txss = tx_state::Entity::find()
+            .filter(tx_state::Column::BlockHash.eq(state.hash.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|t| match parse_from_json_str::<TransactionWithResult>(&t.json) {
+                Ok(tx) => Some((tx, t.hash)),
+                Err(e) => {
+                    error!("{e}");
+                    None
+                }
+            })
+            .collect_vec();
+        if txss.len() != block.transaction_hashes.len() {
+            error!("block {} has {} of {} tx states, rebuilding anyway", state.number, txss.len(), block.transaction_hashes.len());
+        }
+        parse_tx_and_update(db.clone(), block, txss, state.hash).await;
+    }
+    Ok(())
+}
+
 pub async fn check_loop(db: DatabaseConnection) {
     info!("check loop start");
     tokio::spawn(async move {
+        let mut last_rebuild = now();
         loop {
+            if now() - last_rebuild >= STALL_SECS {
+                if let Err(err) = rebuild_stalled(&db).await {
+                    error!("stalled block rebuild fail: {err}");
+                }
+                last_rebuild = now();
+            }
             match ApiService::get_node_status_always().await.ok() {
                Some(node_status) => {
                     let target_hash = get_last_built_or_genesis_block_hash(&node_status, &db).await;
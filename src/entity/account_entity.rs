@@ -2,15 +2,21 @@ use sea_orm::entity::prelude::*;
 use sea_orm::*;
 
 use crate::library::common::{as_timestamp, now};
-use crate::transaction::account_transaction::CreateAccount;
+use crate::transaction::account_transaction::{CreateAccount, CreateAccountWithExternalChainAddresses};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "account")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub address: String,
+    pub eth_address: Option<String>,
+    pub guardian: Option<String>,
+    pub external_chain_addresses: Option<Json>,
+    // block of the last profile change applied
+    pub block_number: i64,
     pub event_time: i64,
     pub created_at: i64,
+    pub updated_at: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -18,13 +24,26 @@ pub enum Relation {}
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Model {
-    pub fn from(tx: &CreateAccount) -> ActiveModel {
+    pub fn from_address(address: &str, event_time: i64) -> ActiveModel {
         ActiveModel {
-            address: Set(tx.account.to_owned()),
-            event_time: Set(as_timestamp(&tx.created_at)),
+            address: Set(address.to_owned()),
+            eth_address: Set(None),
+            guardian: Set(None),
+            external_chain_addresses: Set(None),
+            block_number: Set(0),
+            event_time: Set(event_time),
             created_at: Set(now()),
+            updated_at: Set(now()),
         }
     }
+
+    pub fn from(tx: &CreateAccount) -> ActiveModel {
+        Model::from_address(&tx.account, as_timestamp(&tx.created_at))
+    }
+
+    pub fn from_external(tx: &CreateAccountWithExternalChainAddresses) -> ActiveModel {
+        Model::from_address(&tx.account, as_timestamp(&tx.created_at))
+    }
 }
 
 impl Related<super::tx_entity::Entity> for Entity {
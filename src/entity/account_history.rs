@@ -0,0 +1,95 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::library::common::now;
+
+use super::{account_entity, external_address};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "account_history")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub hash: String,
+    pub address: String,
+    pub tx_type: String,
+    pub eth_address: Option<String>,
+    pub guardian: Option<String>,
+    pub external_chain_addresses: Option<Json>,
+    pub public_key_summaries: Option<Json>,
+    // summaries the node dropped while adding new ones
+    pub removed_summaries: Option<Json>,
+    pub block_number: i64,
+    pub event_time: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    fn sets_eth_address(&self) -> bool {
+        matches!(self.tx_type.as_str(), "CreateAccount" | "UpdateAccount")
+    }
+
+    fn sets_external_addresses(&self) -> bool {
+        matches!(
+            self.tx_type.as_str(),
+            "CreateAccountWithExternalChainAddresses" | "UpdateAccountWithExternalChainAddresses"
+        )
+    }
+
+    // profile columns this tx overwrites, the rest stay NotSet.
+    pub fn profile(&self) -> Option<account_entity::ActiveModel> {
+        if !self.sets_eth_address() && !self.sets_external_addresses() {
+            return None;
+        }
+        let mut am = account_entity::ActiveModel {
+            guardian: Set(self.guardian.clone()),
+            block_number: Set(self.block_number),
+            updated_at: Set(now()),
+            ..Default::default()
+        };
+        if self.sets_eth_address() {
+            am.eth_address = Set(self.eth_address.clone());
+        } else {
+            am.external_chain_addresses = Set(self.external_chain_addresses.clone());
+        }
+        Some(am)
+    }
+
+    // an eth address update and an external chain update each replace only their own addresses.
+    pub fn external_address_source(&self) -> Option<&'static str> {
+        if self.sets_eth_address() {
+            Some(external_address::ETH_ADDRESS)
+        } else if self.sets_external_addresses() {
+            Some(external_address::EXTERNAL_CHAIN)
+        } else {
+            None
+        }
+    }
+
+    // the full set of external addresses after this tx, None when it doesn't touch them.
+    pub fn external_addresses(&self) -> Option<Vec<external_address::Model>> {
+        let pairs = if self.sets_eth_address() {
+            self.eth_address.iter().map(|addr| ("eth".to_string(), addr.clone())).collect()
+        } else if self.sets_external_addresses() {
+            match &self.external_chain_addresses {
+                Some(Json::Object(map)) => map
+                    .iter()
+                    .filter_map(|(chain, addr)| addr.as_str().map(|a| (chain.clone(), a.to_string())))
+                    .collect(),
+                _ => vec![],
+            }
+        } else {
+            return None;
+        };
+        let source = self.external_address_source()?;
+        Some(
+            pairs
+                .into_iter()
+                .map(|(chain, addr)| external_address::Model::new(source, chain, addr, self.address.clone(), self.block_number))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+
+use crate::library::common::now;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "account_public_key")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub summary: String,
+    pub description: String,
+    pub added_hash: String,
+    pub block_number: i64,
+    pub removed_hash: Option<String>,
+    pub removed_block_number: Option<i64>,
+    pub event_time: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn from(address: &str, summary: &str, description: &str, hash: &str, block_number: i64, event_time: i64) -> Model {
+        Model {
+            address: address.to_owned(),
+            summary: summary.to_owned(),
+            description: description.to_owned(),
+            added_hash: hash.to_owned(),
+            block_number,
+            removed_hash: None,
+            removed_block_number: None,
+            event_time,
+            created_at: now(),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// known addresses such as exchanges, treasury and dao accounts.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "address_label")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+    pub label: String,
+    pub category: String,
+    // held balance is not counted as circulating
+    #[serde(default)]
+    pub exclude_from_supply: bool,
+    // config or dao
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
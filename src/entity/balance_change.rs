@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::library::common::now;
+
+// every balance movement applied by the balance app, positioned in the chain so past balances can be rebuilt.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "balance_change")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    // the tx that moved the balance
+    pub hash: String,
+    pub address: String,
+    pub token: String,
+    pub free: BigDecimal,
+    pub locked: BigDecimal,
+    pub block_number: i64,
+    pub tx_index: i32,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn change(
+        hash: &str,
+        address: &str,
+        token: &str,
+        free: BigDecimal,
+        locked: BigDecimal,
+        block_number: i64,
+        tx_index: i32,
+    ) -> ActiveModel {
+        ActiveModel {
+            hash: Set(hash.to_owned()),
+            address: Set(address.to_owned()),
+            token: Set(token.to_owned()),
+            free: Set(free),
+            locked: Set(locked),
+            block_number: Set(block_number),
+            tx_index: Set(tx_index),
+            created_at: Set(now()),
+            ..Default::default()
+        }
+    }
+}
@@ -1,14 +1,18 @@
 use sea_orm::{entity::prelude::*, IntoActiveModel};
 
-use crate::balance_entity;
+use crate::{balance_entity, service::precision_service::Precision};
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "balance")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub address: String,
     pub free: BigDecimal,
     pub locked: BigDecimal,
+    // free and locked divided by the token precision
+    pub free_scaled: BigDecimal,
+    pub locked_scaled: BigDecimal,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -27,11 +31,20 @@ impl Model {
             ..self
         }
     }
+    pub fn scaled(self, precision: i32) -> Model {
+        Model {
+            free_scaled: Precision::scale(&self.free, precision),
+            locked_scaled: Precision::scale(&self.locked, precision),
+            ..self
+        }
+    }
     pub fn to_bal(self) -> balance_entity::ActiveModel {
         balance_entity::Model {
             address: self.address,
             free: self.free,
             locked: self.locked,
+            free_scaled: self.free_scaled,
+            locked_scaled: self.locked_scaled,
             created_at: self.created_at,
             updated_at: self.updated_at,
         } .into_active_model()
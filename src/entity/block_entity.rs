@@ -4,11 +4,12 @@ use crate::{
 };
 use sea_orm::entity::prelude::*;
 use sea_orm::*;
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "block")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub hash: String,
     pub number: i64,
     pub parent_hash: String,
@@ -8,7 +8,7 @@ use crate::library::common::{as_timestamp, now};
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "block_state")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub hash: String,
     pub number: i64,
     pub is_build: bool,
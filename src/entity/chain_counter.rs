@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+
+// running row counts kept with the inserts, read by the summary loop.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "chain_counter")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub value: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+pub const TX: &str = "tx";
+pub const ACCOUNT: &str = "account";
+pub const NFT_FILE: &str = "nft_file";
+// per-tag counters of transactions with an unrecognised variant, e.g. "unknown_tx:NewTx".
+pub const UNKNOWN_TX: &str = "unknown_tx:";
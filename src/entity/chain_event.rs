@@ -0,0 +1,66 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::library::common::now;
+
+// indexed data published to stream clients, seq is given by the publisher once the row is visible.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "chain_event")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip)]
+    pub id: i64,
+    pub seq: Option<i64>,
+    pub kind: String,
+    // addresses the event concerns, json array
+    pub addresses: Json,
+    pub token: Option<String>,
+    // block of the change, balance events carry the block of the last change they include
+    pub block_number: i64,
+    pub payload: Json,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+pub const BLOCK: &str = "block";
+pub const TX: &str = "tx";
+pub const BALANCE: &str = "balance";
+pub const NFT_TRANSFER: &str = "nft_transfer";
+pub const ACCOUNT: &str = "account";
+
+impl Model {
+    pub fn event(kind: &str, addresses: Vec<String>, token: Option<String>, block_number: i64, payload: Json) -> ActiveModel {
+        ActiveModel {
+            kind: Set(kind.to_owned()),
+            seq: Set(None),
+            addresses: Set(json!(addresses)),
+            token: Set(token),
+            block_number: Set(block_number),
+            payload: Set(payload),
+            created_at: Set(now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn block(number: i64, hash: &str, tx_count: usize, event_time: i64) -> ActiveModel {
+        Self::event(
+            BLOCK,
+            vec![],
+            None,
+            number,
+            json!({ "number": number, "hash": hash, "tx_count": tx_count, "event_time": event_time }),
+        )
+    }
+
+    pub fn addresses(&self) -> Vec<&str> {
+        self.addresses
+            .as_array()
+            .map(|v| v.iter().filter_map(|a| a.as_str()).collect())
+            .unwrap_or_default()
+    }
+}
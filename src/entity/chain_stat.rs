@@ -0,0 +1,61 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
+use sea_orm::Set;
+
+// hourly and daily chain metrics, key splits a metric by tx type or token.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "chain_stat")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub interval: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bucket: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub metric: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key: String,
+    pub value: BigDecimal,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+pub const TX: &str = "tx";
+pub const NEW_ACCOUNTS: &str = "new_accounts";
+pub const ACTIVE_ACCOUNTS: &str = "active_accounts";
+pub const NFT_MINT: &str = "nft_mint";
+pub const NFT_TRANSFER: &str = "nft_transfer";
+pub const TRANSFER_COUNT: &str = "transfer_count";
+pub const TRANSFER_VOLUME: &str = "transfer_volume";
+pub const BLOCKS: &str = "blocks";
+// seconds since the previous block, average block time is block_time_sum / blocks
+pub const BLOCK_TIME_SUM: &str = "block_time_sum";
+
+impl Model {
+    pub fn into_active(self) -> ActiveModel {
+        ActiveModel {
+            interval: Set(self.interval),
+            bucket: Set(self.bucket),
+            metric: Set(self.metric),
+            key: Set(self.key),
+            value: Set(self.value),
+            updated_at: Set(self.updated_at),
+        }
+    }
+
+    pub fn pk() -> [Column; 4] {
+        [Column::Interval, Column::Bucket, Column::Metric, Column::Key]
+    }
+
+    pub fn on_conflict_add() -> OnConflict {
+        OnConflict::columns(Self::pk())
+            .value(
+                Column::Value,
+                Expr::col((Entity, Column::Value)).add(Expr::col((Alias::new("excluded"), Column::Value))),
+            )
+            .update_column(Column::UpdatedAt)
+            .to_owned()
+    }
+}
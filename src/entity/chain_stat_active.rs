@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+
+// signers seen per bucket, backs the active_accounts stat.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "chain_stat_active")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub interval: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bucket: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
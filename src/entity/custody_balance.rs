@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+
+// last known LM balance of each external custody address.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "custody_balance")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chain: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+    pub name: String,
+    pub source: String,
+    // raw token units, kept from the last success when a fetch fails
+    pub balance: BigDecimal,
+    pub block_number: i64,
+    pub last_error: Option<String>,
+    pub fetched_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
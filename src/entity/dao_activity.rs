@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+
+use crate::library::common::now;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "dao_activity")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+    // start of the utc day the activity was recorded for
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub day: i64,
+    pub activity_point: i64,
+    pub received_point: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn new(address: String, day: i64) -> Model {
+        Model {
+            address,
+            day,
+            activity_point: 0,
+            received_point: 0,
+            updated_at: now(),
+        }
+    }
+
+    pub fn merge(self, other: &Model) -> Model {
+        Model {
+            activity_point: self.activity_point + other.activity_point,
+            received_point: self.received_point + other.received_point,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+
+use crate::library::common::now;
+
+// reverse lookup from an external chain address to the LM account.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "external_address")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chain: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub external_address: String,
+    pub address: String,
+    // account field the address came from, see ETH_ADDRESS / EXTERNAL_CHAIN
+    pub source: String,
+    // false once a later account update dropped this address
+    pub is_active: bool,
+    pub block_number: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+pub const ETH_ADDRESS: &str = "eth_address";
+pub const EXTERNAL_CHAIN: &str = "external_chain";
+
+impl Model {
+    pub fn new(source: &str, chain: String, external_address: String, address: String, block_number: i64) -> Model {
+        Model {
+            chain: chain.to_lowercase(),
+            // eth addresses are case insensitive
+            external_address: external_address.to_lowercase(),
+            address,
+            source: source.to_owned(),
+            is_active: true,
+            block_number,
+            updated_at: now(),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+
+// top holders per token by free + locked, rewritten on each refresh.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "holder_rank")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub rank: i32,
+    pub address: String,
+    pub free: BigDecimal,
+    pub locked: BigDecimal,
+    pub total: BigDecimal,
+    // fraction of the balance held by all holders
+    pub share: BigDecimal,
+    pub label: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
@@ -11,3 +11,31 @@ pub mod tx_entity;
 pub mod tx_state;
 pub mod balance_tx;
 pub mod spend_tx;
+pub mod dao_activity;
+pub mod reward_payout;
+pub mod reward_total;
+pub mod snapshot;
+pub mod snapshot_balance;
+pub mod snapshot_nft_owner;
+pub mod token_definition;
+pub mod account_history;
+pub mod account_public_key;
+pub mod external_address;
+pub mod nft_history;
+pub mod nft_fetch;
+pub mod nft_verification;
+pub mod nft_collection;
+pub mod agent_cursor;
+pub mod price_candle;
+pub mod custody_balance;
+pub mod chain_stat;
+pub mod chain_stat_active;
+pub mod chain_counter;
+pub mod address_label;
+pub mod holder_rank;
+pub mod token_distribution;
+pub mod chain_event;
+pub mod webhook_watch;
+pub mod webhook_delivery;
+pub mod balance_change;
+pub mod price_backfill;
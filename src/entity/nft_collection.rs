@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+
+use sea_orm::entity::prelude::*;
+
+// per token definition, nft_app applies each batch as a delta and recounts periodically.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "nft_collection")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub definition_id: String,
+    pub collection_name: String,
+    // live items, burned ones are counted apart
+    pub item_count: i64,
+    pub burned_count: i64,
+    pub owner_count: i64,
+    // live items per on-chain rarity
+    pub rarity_counts: Json,
+    // live items whose rarity has no weight in the definition
+    pub unranked_count: i64,
+    pub first_mint_at: Option<i64>,
+    pub last_mint_at: Option<i64>,
+    pub transfer_count: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    // (rarity counts, unranked count) checked against the DefineToken rarity weights.
+    pub fn rarity_stats(counts: &[(Option<String>, i64)], weights: &Option<Json>) -> (Json, i64) {
+        let mut map: BTreeMap<String, i64> = BTreeMap::new();
+        let mut unranked = 0;
+        for (rarity, count) in counts {
+            let rarity = rarity.clone().unwrap_or_default();
+            let ranked = match weights {
+                Some(Json::Object(w)) => w.get(&rarity).is_some_and(|v| !v.is_null()),
+                _ => true,
+            };
+            if !ranked {
+                unranked += count;
+            }
+            *map.entry(rarity).or_insert(0) += count;
+        }
+        (serde_json::to_value(map).unwrap_or_default(), unranked)
+    }
+}
@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::library::common::now;
+
+// metadata fetch queue, one job per nft. done jobs come due again once stale.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "nft_fetch")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token_id: String,
+    pub data_url: String,
+    // pending, running, done, failed or gave_up
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: i64,
+    pub fetched_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn pending(token_id: String, data_url: String) -> ActiveModel {
+        ActiveModel {
+            token_id: Set(token_id),
+            data_url: Set(data_url),
+            status: Set("pending".to_string()),
+            attempts: Set(0),
+            last_error: Set(None),
+            next_attempt_at: Set(0),
+            fetched_at: Set(None),
+            created_at: Set(now()),
+            updated_at: Set(now()),
+        }
+    }
+}
@@ -4,11 +4,12 @@ use sea_orm::*;
 use crate::{
     library::common::{as_timestamp, now}, nft_app::NftMetaInfo, transaction::token_transaction::MintNft
 };
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "nft_file")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub token_id: String,
     pub token_def_id: String,
     pub collection_name: String,
@@ -16,6 +17,8 @@ pub struct Model {
     pub nft_uri: String,
     pub creator_description: String,
     pub data_url: String,
+    // on-chain hash of the metadata document
+    pub content_hash: String,
     pub rarity: String,
     pub creator: String,
     pub event_time: i64,
@@ -37,10 +40,24 @@ impl Model {
             nft_uri: Set(info.nft_uri.clone()),
             creator_description: Set(info.creator_description),
             data_url: Set(url),
+            content_hash: Set(tx.content_hash.clone()),
             rarity: Set(info.rarity),
             creator: Set(info.creator),
             event_time: Set(as_timestamp(&tx.created_at)),
             created_at: Set(now()),
         }
     }
+
+    // only the columns read from the metadata json
+    pub fn metadata(info: NftMetaInfo) -> ActiveModel {
+        ActiveModel {
+            collection_name: Set(info.collection_name),
+            nft_name: Set(info.nft_name),
+            nft_uri: Set(info.nft_uri),
+            creator_description: Set(info.creator_description),
+            rarity: Set(info.rarity),
+            creator: Set(info.creator),
+            ..Default::default()
+        }
+    }
 }
@@ -0,0 +1,54 @@
+use sea_orm::entity::prelude::*;
+
+use crate::{library::common::now, transaction::token_transaction::NftTransition};
+use serde::Serialize;
+
+// one row per nft-affecting tx, the state the nft was left in.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "nft_history")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub tx_hash: String,
+    pub token_id: String,
+    pub definition_id: String,
+    pub action: String,
+    pub status: String,
+    pub owner: String,
+    pub custodian: Option<String>,
+    pub signer: String,
+    pub block_number: i64,
+    pub tx_index: i32,
+    pub event_time: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn from(
+        tx_hash: &str,
+        action: String,
+        signer: &str,
+        owner: String,
+        block_number: i64,
+        tx_index: i32,
+        t: &NftTransition,
+    ) -> Model {
+        Model {
+            tx_hash: tx_hash.to_owned(),
+            token_id: t.token_id.clone(),
+            definition_id: t.definition_id.clone(),
+            action,
+            status: t.status.as_str().to_owned(),
+            owner,
+            custodian: t.custodian.clone(),
+            signer: signer.to_owned(),
+            block_number,
+            tx_index,
+            event_time: t.event_time,
+            created_at: now(),
+        }
+    }
+}
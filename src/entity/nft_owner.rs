@@ -1,11 +1,21 @@
 use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "nft_owner")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub token_id: String,
+    pub definition_id: String,
     pub owner: String,
+    // minted, owned, entrusted, burned or updated
+    pub status: String,
+    pub custodian: Option<String>,
+    pub rarity: Option<String>,
+    // chain position of the tx that left the nft in this state
+    pub block_number: i64,
+    pub tx_index: i32,
 
     pub event_time: i64,
     pub created_at: i64,
@@ -14,3 +24,30 @@ pub struct Model {
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    // upsert that only moves an nft forward in chain order.
+    pub fn on_conflict_newer() -> OnConflict {
+        let excluded = |c: Column| Expr::col((Alias::new("excluded"), c));
+        let current = |c: Column| Expr::col((Entity, c));
+        OnConflict::column(Column::TokenId)
+            .update_columns([
+                Column::DefinitionId,
+                Column::Owner,
+                Column::Status,
+                Column::Custodian,
+                Column::Rarity,
+                Column::BlockNumber,
+                Column::TxIndex,
+                Column::EventTime,
+            ])
+            .action_and_where(
+                current(Column::BlockNumber).lt(excluded(Column::BlockNumber)).or(
+                    current(Column::BlockNumber)
+                        .eq(excluded(Column::BlockNumber))
+                        .and(current(Column::TxIndex).lte(excluded(Column::TxIndex))),
+                ),
+            )
+            .to_owned()
+    }
+}
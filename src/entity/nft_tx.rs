@@ -1,19 +1,24 @@
 use sea_orm::entity::prelude::*;
 use sea_orm::*;
 
+use serde::Serialize;
+
 use crate::{
     library::common::now, transaction::token_transaction::TokenTx, tx_entity
 };
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "nft")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub tx_hash: String,
     pub token_id: String,
     pub action: String,
     pub from_addr: String,
     pub to_addr: String,
+    pub block_number: i64,
+    // position in the block's transaction hashes
+    pub tx_index: i32,
     pub event_time: i64,
     pub created_at: i64,
 }
@@ -23,13 +28,15 @@ pub enum Relation {}
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Model {
-    pub fn from(tx: &TokenTx, tx_entity: &tx_entity::ActiveModel, from: String, to: String) -> ActiveModel {
+    pub fn from(tx: &TokenTx, tx_entity: &tx_entity::ActiveModel, tx_index: i32, from: String, to: String) -> ActiveModel {
         ActiveModel {
             tx_hash: tx_entity.hash.clone(),
             token_id: Set(tx.token_id()),
             action: Set(tx.sub_type()),
             from_addr: Set(from),
             to_addr: Set(to),
+            block_number: tx_entity.block_number.clone(),
+            tx_index: Set(tx_index),
             event_time: tx_entity.event_time.clone(),
             created_at: Set(now()),
         }
@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::library::common::now;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "nft_verification")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token_id: String,
+    // pending, verified, tampered, invalid or dead
+    pub status: String,
+    // sha256 of the metadata document, compared with the on-chain content hash
+    pub metadata_hash: Option<String>,
+    pub content_hash_match: Option<bool>,
+    // sha256 of the media behind NFT_URI, compared with NFT_checksum
+    pub media_hash: Option<String>,
+    pub checksum_match: Option<bool>,
+    pub media_size: Option<i64>,
+    // file in the local content-addressed store, when caching is on
+    pub media_path: Option<String>,
+    pub last_error: Option<String>,
+    pub checked_at: Option<i64>,
+    pub next_check_at: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn pending(token_id: String) -> ActiveModel {
+        ActiveModel {
+            token_id: Set(token_id),
+            status: Set("pending".to_string()),
+            metadata_hash: Set(None),
+            content_hash_match: Set(None),
+            media_hash: Set(None),
+            checksum_match: Set(None),
+            media_size: Set(None),
+            media_path: Set(None),
+            last_error: Set(None),
+            checked_at: Set(None),
+            next_check_at: Set(0),
+            created_at: Set(now()),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+
+use crate::library::common::now;
+
+// how far the price history was backfilled, in unix seconds.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "price_backfill")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub backfilled_to: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub async fn get<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<i64>, DbErr> {
+        Ok(Entity::find_by_id(name.to_owned()).one(db).await?.map(|m| m.backfilled_to))
+    }
+
+    pub async fn set<C: ConnectionTrait>(db: &C, name: &str, backfilled_to: i64) -> Result<(), DbErr> {
+        Entity::insert(ActiveModel {
+            name: Set(name.to_owned()),
+            backfilled_to: Set(backfilled_to),
+            updated_at: Set(now()),
+        })
+        .on_conflict(
+            OnConflict::column(Column::Name)
+                .update_columns([Column::BackfilledTo, Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Alias, Expr, OnConflict, SimpleExpr};
+use sea_orm::Set;
+
+// usd price of LM per minute, hour and day bucket.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "price_candle")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub interval: String,
+    // bucket start, unix seconds
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub open_time: i64,
+    pub open: BigDecimal,
+    pub high: BigDecimal,
+    pub low: BigDecimal,
+    pub close: BigDecimal,
+    // time of the quote the close came from
+    pub close_time: i64,
+    pub sample_count: i64,
+    pub source: String,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn into_active(self) -> ActiveModel {
+        ActiveModel {
+            interval: Set(self.interval),
+            open_time: Set(self.open_time),
+            open: Set(self.open),
+            high: Set(self.high),
+            low: Set(self.low),
+            close: Set(self.close),
+            close_time: Set(self.close_time),
+            sample_count: Set(self.sample_count),
+            source: Set(self.source),
+            updated_at: Set(self.updated_at),
+        }
+    }
+
+    // merge into an existing candle, the open stays and the close only moves forward.
+    pub fn on_conflict_merge() -> OnConflict {
+        let cur = |c: Column| Expr::col((Entity, c));
+        let new = |c: Column| Expr::col((Alias::new("excluded"), c));
+        let newer = || new(Column::CloseTime).gte(cur(Column::CloseTime));
+        let pick = |cond: SimpleExpr, a: Expr, b: Expr| -> SimpleExpr { Expr::case(cond, a).finally(b).into() };
+        OnConflict::columns([Column::Interval, Column::OpenTime])
+            .value(Column::High, pick(new(Column::High).gt(cur(Column::High)), new(Column::High), cur(Column::High)))
+            .value(Column::Low, pick(new(Column::Low).lt(cur(Column::Low)), new(Column::Low), cur(Column::Low)))
+            .value(Column::Close, pick(newer(), new(Column::Close), cur(Column::Close)))
+            .value(Column::CloseTime, pick(newer(), new(Column::CloseTime), cur(Column::CloseTime)))
+            .value(Column::SampleCount, cur(Column::SampleCount).add(new(Column::SampleCount)))
+            .update_columns([Column::Source, Column::UpdatedAt])
+            .to_owned()
+    }
+}
@@ -0,0 +1,24 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "reward_payout")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub hash: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+    pub amount: BigDecimal,
+    pub reward_type: String,
+    pub dao_account: Option<String>,
+    pub definition_id: Option<String>,
+    // hash of the BuildSnapshot tx the payout was computed from
+    pub snapshot_hash: Option<String>,
+    pub snapshot_number: Option<i64>,
+    pub block_number: i64,
+    pub event_time: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,66 @@
+use sea_orm::entity::prelude::*;
+
+use crate::{
+    library::common::{as_timestamp, now},
+    transaction::{reward_transaction::BuildSnapshot, token_transaction::CreateSnapshot},
+};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "snapshot")]
+pub struct Model {
+    // snapshot id, the hash of the tx that created it
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub hash: String,
+    pub snapshot_type: String,
+    pub definition_id: Option<String>,
+    pub memo: Option<String>,
+    pub account_amount: Option<BigDecimal>,
+    pub token_amount: Option<BigDecimal>,
+    pub ownership_amount: Option<BigDecimal>,
+    pub block_number: i64,
+    // position of the snapshot tx in its block, state is taken just before it
+    pub tx_index: i32,
+    pub is_captured: bool,
+    pub event_time: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn from_create(tx: &CreateSnapshot, hash: String, block_number: i64) -> Model {
+        Model {
+            hash,
+            snapshot_type: "CreateSnapshot".to_string(),
+            definition_id: Some(tx.definition_id.clone()),
+            memo: tx.memo.clone(),
+            account_amount: None,
+            token_amount: None,
+            ownership_amount: None,
+            block_number,
+            tx_index: 0,
+            is_captured: false,
+            event_time: as_timestamp(&tx.created_at),
+            created_at: now(),
+        }
+    }
+
+    pub fn from_build(tx: &BuildSnapshot, hash: String, block_number: i64) -> Model {
+        Model {
+            hash,
+            snapshot_type: "BuildSnapshot".to_string(),
+            definition_id: None,
+            memo: None,
+            account_amount: Some(tx.account_amount.clone()),
+            token_amount: Some(tx.token_amount.clone()),
+            ownership_amount: Some(tx.ownership_amount.clone()),
+            block_number,
+            tx_index: 0,
+            is_captured: false,
+            event_time: as_timestamp(&tx.created_at),
+            created_at: now(),
+        }
+    }
+}
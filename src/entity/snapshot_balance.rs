@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::library::common::now;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "snapshot_balance")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub snapshot_hash: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub address: String,
+    pub free: BigDecimal,
+    pub locked: BigDecimal,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn from(snapshot_hash: &str, address: String, free: BigDecimal, locked: BigDecimal) -> ActiveModel {
+        ActiveModel {
+            snapshot_hash: Set(snapshot_hash.to_owned()),
+            address: Set(address),
+            free: Set(free),
+            locked: Set(locked),
+            created_at: Set(now()),
+        }
+    }
+}
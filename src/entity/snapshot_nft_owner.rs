@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::{library::common::now, nft_history};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "snapshot_nft_owner")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub snapshot_hash: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token_id: String,
+    pub owner: String,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn from(snapshot_hash: &str, owner: nft_history::Model) -> ActiveModel {
+        ActiveModel {
+            snapshot_hash: Set(snapshot_hash.to_owned()),
+            token_id: Set(owner.token_id),
+            owner: Set(owner.owner),
+            created_at: Set(now()),
+        }
+    }
+}
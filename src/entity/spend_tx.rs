@@ -9,6 +9,10 @@ pub struct Model {
     pub hash: String,
     pub token: String,
     pub t: u8,
+    // the spending tx and its place in the chain
+    pub spent_by: String,
+    pub block_number: i64,
+    pub tx_index: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
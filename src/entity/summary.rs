@@ -1,8 +1,9 @@
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Set};
 
 use crate::library::common::now;
+use serde::Serialize;
 
-#[derive(Clone, Debug, DeriveEntityModel)]
+#[derive(Clone, Debug, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "summary")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -15,7 +16,19 @@ pub struct Model {
     pub total_accounts: i64,
     pub total_balance: BigDecimal,
     pub created_at: i64,
+    // total_balance scaled down and cir_supply scaled up by the LM precision
+    pub total_balance_scaled: BigDecimal,
+    pub cir_supply_raw: BigDecimal,
     pub total_nft: u64,
+    // LM supply derived from the indexed chain, raw units
+    pub minted: BigDecimal,
+    pub burned: BigDecimal,
+    pub locked: BigDecimal,
+    pub treasury: BigDecimal,
+    // minted - burned - locked - treasury, scaled like cir_supply
+    pub chain_cir_supply: BigDecimal,
+    // cir_supply from the price vendor minus chain_cir_supply
+    pub cir_supply_diff: BigDecimal,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -44,6 +57,14 @@ impl Model {
             total_balance: Set(total_balance),
             created_at: Set(now()),
             total_nft: Set(total_nft),
+            total_balance_scaled: NotSet,
+            cir_supply_raw: NotSet,
+            minted: NotSet,
+            burned: NotSet,
+            locked: NotSet,
+            treasury: NotSet,
+            chain_cir_supply: NotSet,
+            cir_supply_diff: NotSet,
         }
     }
 }
@@ -0,0 +1,100 @@
+use sea_orm::entity::prelude::*;
+
+use crate::{
+    library::common::{as_timestamp, now},
+    transaction::token_transaction::DefineToken,
+};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "token_definition")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub definition_id: String,
+    pub name: String,
+    pub symbol: Option<String>,
+    pub minter_group: Option<String>,
+    pub precision: Option<i32>,
+    pub is_nft: bool,
+    pub nft_minter: Option<String>,
+    pub nft_data_url: Option<String>,
+    pub nft_content_hash: Option<String>,
+    // rarity name to weight, only for nft definitions
+    pub rarity: Option<Json>,
+    pub total_minted: BigDecimal,
+    pub total_burned: BigDecimal,
+    // entrusted and not yet disposed
+    pub total_locked: BigDecimal,
+    pub holder_count: i64,
+    pub event_time: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn new(definition_id: String) -> Model {
+        Model {
+            definition_id,
+            name: String::new(),
+            symbol: None,
+            minter_group: None,
+            precision: None,
+            is_nft: false,
+            nft_minter: None,
+            nft_data_url: None,
+            nft_content_hash: None,
+            rarity: None,
+            total_minted: BigDecimal::from(0),
+            total_burned: BigDecimal::from(0),
+            total_locked: BigDecimal::from(0),
+            holder_count: 0,
+            event_time: 0,
+            created_at: now(),
+            updated_at: now(),
+        }
+    }
+
+    pub fn from(tx: &DefineToken) -> Model {
+        let nft = tx.nft_info.as_ref().map(|info| &info.some.value);
+        Model {
+            name: tx.name.clone(),
+            symbol: tx.symbol.clone(),
+            minter_group: tx.minter_group.clone(),
+            precision: tx.precision,
+            is_nft: nft.is_some(),
+            nft_minter: nft.map(|v| v.minter.clone()),
+            nft_data_url: nft.map(|v| v.data_url.clone()),
+            nft_content_hash: nft.map(|v| v.content_hash.clone()),
+            rarity: nft.and_then(|v| serde_json::to_value(&v.rarity).ok()),
+            event_time: as_timestamp(&tx.created_at),
+            ..Model::new(tx.definition_id.clone())
+        }
+    }
+
+    pub fn supply(definition_id: String, minted: BigDecimal, burned: BigDecimal) -> Model {
+        Model {
+            total_minted: minted,
+            total_burned: burned,
+            ..Model::new(definition_id)
+        }
+    }
+
+    pub fn locked(definition_id: String, delta: BigDecimal) -> Model {
+        Model {
+            total_locked: delta,
+            ..Model::new(definition_id)
+        }
+    }
+
+    pub fn add_supply(self, other: &Model) -> Model {
+        Model {
+            total_minted: self.total_minted + &other.total_minted,
+            total_burned: self.total_burned + &other.total_burned,
+            total_locked: self.total_locked + &other.total_locked,
+            ..self
+        }
+    }
+}
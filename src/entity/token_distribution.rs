@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+
+// holder concentration of a token, labeled supply exclusions left out of the metrics.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "token_distribution")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token: String,
+    pub holder_count: i64,
+    pub total_balance: BigDecimal,
+    // held by addresses labeled exclude_from_supply
+    pub excluded_balance: BigDecimal,
+    pub top10_share: f64,
+    pub top100_share: f64,
+    pub gini: f64,
+    // fewest holders controlling more than half
+    pub nakamoto: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
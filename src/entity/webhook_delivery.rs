@@ -0,0 +1,51 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+use serde_json::json;
+
+use crate::{chain_event, library::common::now};
+
+// one event sent to one watch, kept as the delivery log.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "webhook_delivery")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub watch_id: i64,
+    pub event_seq: i64,
+    pub kind: String,
+    // request body, signed as is
+    pub body: Json,
+    // pending, delivered or failed once attempts run out
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: i64,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn pending(watch_id: i64, e: &chain_event::Model) -> ActiveModel {
+        ActiveModel {
+            watch_id: Set(watch_id),
+            event_seq: Set(e.seq.unwrap_or_default()),
+            kind: Set(e.kind.clone()),
+            body: Set(json!({ "watch_id": watch_id, "event": e })),
+            status: Set("pending".to_string()),
+            attempts: Set(0),
+            next_attempt_at: Set(0),
+            response_status: Set(None),
+            last_error: Set(None),
+            delivered_at: Set(None),
+            created_at: Set(now()),
+            updated_at: Set(now()),
+            ..Default::default()
+        }
+    }
+}
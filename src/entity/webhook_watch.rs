@@ -0,0 +1,58 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::*;
+
+use crate::{chain_event, library::common::now};
+
+// a webhook subscription, matched against published chain events by address and/or token definition.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "webhook_watch")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    // hmac key of the X-Lmscan-Signature header
+    pub secret: String,
+    pub address: Option<String>,
+    pub definition_id: Option<String>,
+    // comma separated event kinds, none means tx, balance and nft_transfer
+    pub events: Option<String>,
+    pub active: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
+
+const DEFAULT_EVENTS: [&str; 3] = [chain_event::TX, chain_event::BALANCE, chain_event::NFT_TRANSFER];
+
+impl Model {
+    pub fn watch(name: &str, url: &str, secret: &str, address: Option<String>, definition_id: Option<String>) -> ActiveModel {
+        ActiveModel {
+            name: Set(name.to_owned()),
+            url: Set(url.to_owned()),
+            secret: Set(secret.to_owned()),
+            address: Set(address),
+            definition_id: Set(definition_id),
+            events: Set(None),
+            active: Set(true),
+            created_at: Set(now()),
+            updated_at: Set(now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, e: &chain_event::Model) -> bool {
+        let kind = match &self.events {
+            Some(events) => events.split(',').any(|k| k.trim() == e.kind),
+            None => DEFAULT_EVENTS.contains(&e.kind.as_str()),
+        };
+        let address = self.address.as_ref().is_none_or(|a| e.addresses().contains(&a.as_str()));
+        let definition = self.definition_id.as_ref().is_none_or(|d| {
+            e.payload.get("definition_id").and_then(|v| v.as_str()) == Some(d) || e.token.as_ref() == Some(d)
+        });
+        kind && address && definition
+    }
+}
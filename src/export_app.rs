@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{
+    balance_tx, block_entity, library::common::parse_from_json_str, library::common::now, nft_tx,
+    service::cursor_service::Cursor, summary, transaction::TransactionWithResult, tx_entity, tx_state,
+};
+use chrono::DateTime;
+use itertools::Itertools;
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use dotenvy::var;
+use log::{info, warn};
+
+const CHUNK_BLOCKS: i64 = 1000;
+// keeps IN lists under the bind parameter limits
+const HASH_CHUNK: usize = 1000;
+
+const USAGE: &str = "usage: export (--from-block N [--to-block M] | --from-time T [--to-time T] | --incremental) \
+[--format csv|jsonl|parquet] [--out DIR] [--datasets block,tx,tx_state,balance_tx,nft,summary]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl Format {
+    fn parse(s: &str) -> Result<Format, String> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "jsonl" => Ok(Format::Jsonl),
+            "parquet" => Ok(Format::Parquet),
+            _ => Err(format!("unknown format {s}")),
+        }
+    }
+
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Int,
+    Bool,
+    // strings, decimals and nested json
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dataset {
+    Block,
+    Tx,
+    TxState,
+    BalanceTx,
+    Nft,
+    Summary,
+}
+
+impl Dataset {
+    pub const ALL: [Dataset; 6] =
+        [Dataset::Block, Dataset::Tx, Dataset::TxState, Dataset::BalanceTx, Dataset::Nft, Dataset::Summary];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dataset::Block => "block",
+            Dataset::Tx => "tx",
+            Dataset::TxState => "tx_state",
+            Dataset::BalanceTx => "balance_tx",
+            Dataset::Nft => "nft",
+            Dataset::Summary => "summary",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Dataset, String> {
+        Self::ALL.into_iter().find(|d| d.name() == s).ok_or(format!("unknown dataset {s}"))
+    }
+
+    fn columns(&self) -> &'static [(&'static str, Kind)] {
+        use Kind::*;
+        match self {
+            Dataset::Block => &[
+                ("hash", Text), ("number", Int), ("parent_hash", Text), ("tx_count", Int), ("event_time", Int),
+                ("created_at", Int),
+            ],
+            Dataset::Tx => &[
+                ("hash", Text), ("signer", Text), ("token_type", Text), ("tx_type", Text), ("sub_type", Text),
+                ("block_hash", Text), ("block_number", Int), ("event_time", Int), ("created_at", Int),
+            ],
+            Dataset::TxState => &[
+                ("hash", Text), ("block_hash", Text), ("block_number", Int), ("event_time", Int), ("transaction", Text),
+            ],
+            Dataset::BalanceTx => &[
+                ("hash", Text), ("address", Text), ("free", Text), ("lock", Text), ("spend", Bool), ("token", Text),
+                ("block_number", Int), ("event_time", Int),
+            ],
+            Dataset::Nft => &[
+                ("tx_hash", Text), ("token_id", Text), ("action", Text), ("from_addr", Text), ("to_addr", Text),
+                ("block_number", Int), ("tx_index", Int), ("event_time", Int), ("created_at", Int),
+            ],
+            Dataset::Summary => &[
+                ("id", Int), ("block_number", Int), ("lm_price", Text), ("market_cap", Text), ("cir_supply", Text),
+                ("total_tx_size", Int), ("total_accounts", Int), ("total_balance", Text), ("total_balance_scaled", Text),
+                ("cir_supply_raw", Text), ("total_nft", Int), ("minted", Text), ("burned", Text), ("locked", Text),
+                ("treasury", Text), ("chain_cir_supply", Text), ("cir_supply_diff", Text), ("created_at", Int),
+            ],
+        }
+    }
+
+    // rows are filed under the day of their block, signer times can run behind block order
+    fn block_column(&self) -> &'static str {
+        match self {
+            Dataset::Block => "number",
+            _ => "block_number",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Range {
+    Blocks { from: i64, to: Option<i64> },
+    // unix seconds, to is exclusive
+    Time { from: i64, to: Option<i64> },
+    // from the last exported height up to the built height
+    Incremental,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportArgs {
+    pub format: Format,
+    pub out: PathBuf,
+    pub datasets: Vec<Dataset>,
+    pub range: Range,
+}
+
+impl ExportArgs {
+    pub fn parse(args: &[String]) -> Result<ExportArgs, String> {
+        let mut format = Format::Jsonl;
+        let mut out = PathBuf::from(var("EXPORT_DIR").unwrap_or("export".to_string()));
+        let mut datasets = Dataset::ALL.to_vec();
+        let (mut from_block, mut to_block, mut from_time, mut to_time) = (None, None, None, None);
+        let mut incremental = false;
+        let num = |s: &str| s.parse::<i64>().map_err(|_| format!("not a number: {s}"));
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            if flag == "--incremental" {
+                incremental = true;
+                continue;
+            }
+            let value = it.next().ok_or(USAGE.to_string())?;
+            match flag.as_str() {
+                "--format" => format = Format::parse(value)?,
+                "--out" => out = PathBuf::from(value),
+                "--datasets" => datasets = value.split(',').map(|d| Dataset::parse(d.trim())).try_collect()?,
+                "--from-block" => from_block = Some(num(value)?),
+                "--to-block" => to_block = Some(num(value)?),
+                "--from-time" => from_time = Some(num(value)?),
+                "--to-time" => to_time = Some(num(value)?),
+                _ => return Err(USAGE.to_string()),
+            }
+        }
+        let range = match (from_block, from_time, incremental) {
+            (Some(from), None, false) if to_time.is_none() => Range::Blocks { from, to: to_block },
+            (None, Some(from), false) if to_block.is_none() => Range::Time { from, to: to_time },
+            (None, None, true) if to_block.is_none() && to_time.is_none() => Range::Incremental,
+            _ => return Err(USAGE.to_string()),
+        };
+        Ok(ExportArgs { format, out, datasets, range })
+    }
+
+    fn cursor(&self) -> String {
+        format!("export_{}", self.format.ext())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub from: i64,
+    pub to: i64,
+    pub rows: HashMap<&'static str, usize>,
+    pub files: Vec<PathBuf>,
+}
+
+fn day(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0).map_or("unknown".to_string(), |d| d.format("%Y-%m-%d").to_string())
+}
+
+fn text(v: &Value) -> Option<String> {
+    match v {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        v => Some(v.to_string()),
+    }
+}
+
+enum Writer {
+    Csv(csv::Writer<File>),
+    Jsonl(BufWriter<File>),
+    Parquet(SerializedFileWriter<File>),
+}
+
+impl Writer {
+    fn create(format: Format, dataset: Dataset, path: &PathBuf) -> Result<Writer, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let columns = dataset.columns();
+        Ok(match format {
+            Format::Csv => {
+                let mut w = csv::Writer::from_writer(file);
+                w.write_record(columns.iter().map(|(name, _)| name)).map_err(|e| e.to_string())?;
+                Writer::Csv(w)
+            }
+            Format::Jsonl => Writer::Jsonl(BufWriter::new(file)),
+            Format::Parquet => {
+                let fields = columns
+                    .iter()
+                    .map(|(name, kind)| match kind {
+                        Kind::Int => format!("optional int64 {name};"),
+                        Kind::Bool => format!("optional boolean {name};"),
+                        Kind::Text => format!("optional binary {name} (UTF8);"),
+                    })
+                    .join(" ");
+                let schema = parse_message_type(&format!("message {} {{ {fields} }}", dataset.name()))
+                    .map_err(|e| e.to_string())?;
+                let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                Writer::Parquet(
+                    SerializedFileWriter::new(file, Arc::new(schema), Arc::new(props)).map_err(|e| e.to_string())?,
+                )
+            }
+        })
+    }
+
+    fn write(&mut self, dataset: Dataset, rows: &[&Value]) -> Result<(), String> {
+        let columns = dataset.columns();
+        match self {
+            Writer::Csv(w) => {
+                for row in rows {
+                    w.write_record(columns.iter().map(|(name, _)| text(&row[name]).unwrap_or_default()))
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            Writer::Jsonl(w) => {
+                for row in rows {
+                    serde_json::to_writer(&mut *w, row).map_err(|e| e.to_string())?;
+                    w.write_all(b"\n").map_err(|e| e.to_string())?;
+                }
+            }
+            // one row group per chunk
+            Writer::Parquet(w) => {
+                let mut group = w.next_row_group().map_err(|e| e.to_string())?;
+                for (name, kind) in columns {
+                    let mut col = group.next_column().map_err(|e| e.to_string())?.ok_or("schema mismatch")?;
+                    let res = match kind {
+                        Kind::Int => {
+                            let cells = rows.iter().map(|r| r[name].as_i64()).collect_vec();
+                            let defs = cells.iter().map(|c| c.is_some() as i16).collect_vec();
+                            let values = cells.into_iter().flatten().collect_vec();
+                            col.typed::<Int64Type>().write_batch(&values, Some(&defs), None)
+                        }
+                        Kind::Bool => {
+                            let cells = rows.iter().map(|r| r[name].as_bool()).collect_vec();
+                            let defs = cells.iter().map(|c| c.is_some() as i16).collect_vec();
+                            let values = cells.into_iter().flatten().collect_vec();
+                            col.typed::<BoolType>().write_batch(&values, Some(&defs), None)
+                        }
+                        Kind::Text => {
+                            let cells = rows.iter().map(|r| text(&r[name])).collect_vec();
+                            let defs = cells.iter().map(|c| c.is_some() as i16).collect_vec();
+                            let values = cells.into_iter().flatten().map(|s| ByteArray::from(s.into_bytes())).collect_vec();
+                            col.typed::<ByteArrayType>().write_batch(&values, Some(&defs), None)
+                        }
+                    };
+                    res.map_err(|e| e.to_string())?;
+                    col.close().map_err(|e| e.to_string())?;
+                }
+                group.close().map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(self) -> Result<(), String> {
+        match self {
+            Writer::Csv(mut w) => w.flush().map_err(|e| e.to_string()),
+            Writer::Jsonl(mut w) => w.flush().map_err(|e| e.to_string()),
+            Writer::Parquet(w) => w.close().map(|_| ()).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+// files are written as .tmp and renamed into place once rows move past their day,
+// block days only move forward so at most one day per dataset is open at a time.
+struct Sink {
+    format: Format,
+    out: PathBuf,
+    label: String,
+    files: HashMap<Dataset, (String, PathBuf, Writer)>,
+    done: Vec<PathBuf>,
+    rows: HashMap<&'static str, usize>,
+}
+
+impl Sink {
+    fn write(&mut self, dataset: Dataset, rows: &[Value], days: &HashMap<i64, String>) -> Result<(), String> {
+        let by_day = rows.iter().into_group_map_by(|r| {
+            r[dataset.block_column()].as_i64().and_then(|n| days.get(&n)).cloned().unwrap_or("unknown".to_string())
+        });
+        for (day, rows) in by_day.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+            match self.files.get(&dataset) {
+                Some((open, _, _)) if *open == day => {}
+                Some((open, _, _)) if *open > day => {
+                    return Err(format!("{} rows for {day} after {open} was written", dataset.name()));
+                }
+                _ => {
+                    self.close(dataset)?;
+                    let dir = self.out.join(dataset.name()).join(format!("day={day}"));
+                    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+                    let path = dir.join(format!("{}_{}.{}", dataset.name(), self.label, self.format.ext()));
+                    let writer = Writer::create(self.format, dataset, &self.tmp(&path))?;
+                    self.files.insert(dataset, (day, path, writer));
+                }
+            }
+            let (_, _, writer) = self.files.get_mut(&dataset).unwrap();
+            writer.write(dataset, &rows)?;
+            *self.rows.entry(dataset.name()).or_default() += rows.len();
+        }
+        Ok(())
+    }
+
+    fn tmp(&self, path: &Path) -> PathBuf {
+        path.with_extension(format!("{}.tmp", self.format.ext()))
+    }
+
+    fn close(&mut self, dataset: Dataset) -> Result<(), String> {
+        if let Some((_, path, writer)) = self.files.remove(&dataset) {
+            writer.close()?;
+            fs::rename(self.tmp(&path), &path).map_err(|e| e.to_string())?;
+            self.done.push(path);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(Vec<PathBuf>, HashMap<&'static str, usize>), String> {
+        for dataset in self.files.keys().copied().collect::<Vec<_>>() {
+            self.close(dataset)?;
+        }
+        self.done.sort();
+        Ok((self.done, self.rows))
+    }
+}
+
+fn values<T: serde::Serialize>(models: Vec<T>) -> Vec<Value> {
+    models.into_iter().filter_map(|m| serde_json::to_value(m).ok()).collect()
+}
+
+async fn block_days(db: &DatabaseConnection, from: i64, to: i64) -> Result<HashMap<i64, String>, DbErr> {
+    let times = block_entity::Entity::find()
+        .select_only()
+        .column(block_entity::Column::Number)
+        .column(block_entity::Column::EventTime)
+        .filter(block_entity::Column::Number.between(from, to))
+        .into_tuple::<(i64, i64)>()
+        .all(db)
+        .await?;
+    Ok(times.into_iter().map(|(number, time)| (number, day(time))).collect())
+}
+
+async fn chunk_rows(
+    db: &DatabaseConnection,
+    local: Option<&DatabaseConnection>,
+    datasets: &[Dataset],
+    from: i64,
+    to: i64,
+) -> Result<Vec<(Dataset, Vec<Value>)>, DbErr> {
+    let mut res = vec![];
+    let needs_tx = datasets.iter().any(|d| matches!(d, Dataset::Tx | Dataset::TxState | Dataset::BalanceTx));
+    let txs = if needs_tx {
+        tx_entity::Entity::find()
+            .filter(tx_entity::Column::BlockNumber.between(from, to))
+            .order_by_asc(tx_entity::Column::BlockNumber)
+            .order_by_asc(tx_entity::Column::Hash)
+            .all(db)
+            .await?
+    } else {
+        vec![]
+    };
+    let tx_map: HashMap<&str, &tx_entity::Model> = txs.iter().map(|t| (t.hash.as_str(), t)).collect();
+    let hashes = txs.iter().map(|t| t.hash.clone()).collect_vec();
+    for dataset in datasets {
+        let rows = match dataset {
+            Dataset::Block => values(
+                block_entity::Entity::find()
+                    .filter(block_entity::Column::Number.between(from, to))
+                    .order_by_asc(block_entity::Column::Number)
+                    .all(db)
+                    .await?,
+            ),
+            Dataset::Tx => values(txs.clone()),
+            Dataset::TxState => {
+                let mut rows = vec![];
+                for chunk in hashes.chunks(HASH_CHUNK) {
+                    for state in tx_state::Entity::find()
+                        .filter(tx_state::Column::Hash.is_in(chunk.to_vec()))
+                        .all(db)
+                        .await?
+                    {
+                        let decoded = parse_from_json_str::<TransactionWithResult>(&state.json)
+                            .map_err(|err| warn!("export tx_state {} decode fail: {err}", state.hash))
+                            .ok();
+                        rows.push(json!({
+                            "hash": state.hash,
+                            "block_hash": state.block_hash,
+                            "block_number": tx_map.get(state.hash.as_str()).map(|t| t.block_number),
+                            "event_time": state.event_time,
+                            "transaction": decoded,
+                        }));
+                    }
+                }
+                rows
+            }
+            Dataset::BalanceTx => {
+                let Some(local) = local else {
+                    warn!("export balance_tx skipped, SQLITE_URL is not set");
+                    continue;
+                };
+                let mut rows = vec![];
+                for chunk in hashes.chunks(HASH_CHUNK) {
+                    for bal in balance_tx::Entity::find()
+                        .filter(balance_tx::Column::Hash.is_in(chunk.to_vec()))
+                        .all(local)
+                        .await?
+                    {
+                        let tx = tx_map.get(bal.hash.as_str());
+                        let mut row = json!(bal);
+                        row["block_number"] = json!(tx.map(|t| t.block_number));
+                        row["event_time"] = json!(tx.map(|t| t.event_time));
+                        rows.push(row);
+                    }
+                }
+                rows
+            }
+            Dataset::Nft => values(
+                nft_tx::Entity::find()
+                    .filter(nft_tx::Column::BlockNumber.between(from, to))
+                    .order_by_asc(nft_tx::Column::BlockNumber)
+                    .order_by_asc(nft_tx::Column::TxIndex)
+                    .all(db)
+                    .await?,
+            ),
+            Dataset::Summary => values(
+                summary::Entity::find()
+                    .filter(summary::Column::BlockNumber.between(from, to))
+                    .order_by_asc(summary::Column::Id)
+                    .all(db)
+                    .await?,
+            ),
+        };
+        res.push((*dataset, rows));
+    }
+    Ok(res)
+}
+
+async fn block_bound(db: &DatabaseConnection, cond: Condition, last: bool) -> Result<Option<i64>, DbErr> {
+    let col = block_entity::Column::Number;
+    block_entity::Entity::find()
+        .select_only()
+        .column_as(if last { col.max() } else { col.min() }, "number")
+        .filter(cond)
+        .into_tuple::<Option<i64>>()
+        .one(db)
+        .await
+        .map(Option::flatten)
+}
+
+// inclusive block range of the export, none when there is nothing to do.
+async fn resolve(db: &DatabaseConnection, args: &ExportArgs) -> Result<Option<(i64, i64)>, DbErr> {
+    let range = match args.range {
+        Range::Blocks { from, to } => match to {
+            Some(to) => Some((from, to)),
+            None => block_bound(db, Condition::all(), true).await?.map(|to| (from, to)),
+        },
+        Range::Time { from, to } => {
+            let cond = Condition::all()
+                .add(block_entity::Column::EventTime.gte(from))
+                .add(block_entity::Column::EventTime.lt(to.unwrap_or(now())));
+            let first = block_bound(db, cond.clone(), false).await?;
+            let last = block_bound(db, cond, true).await?;
+            first.zip(last)
+        }
+        Range::Incremental => {
+            let cursor = Cursor::get(db, &args.cursor()).await?;
+            Some((cursor + 1, Cursor::built_height(db, cursor).await?))
+        }
+    };
+    Ok(range.filter(|(from, to)| from <= to))
+}
+
+pub async fn export(
+    db: &DatabaseConnection,
+    local: Option<&DatabaseConnection>,
+    args: ExportArgs,
+) -> Result<ExportReport, String> {
+    let Some((from, to)) = resolve(db, &args).await.map_err(|e| e.to_string())? else {
+        info!("export: nothing to export");
+        return Ok(ExportReport::default());
+    };
+    info!("export blocks {from}..={to} as {}", args.format.ext());
+    let mut sink = Sink {
+        format: args.format,
+        out: args.out.clone(),
+        label: format!("{from}_{to}"),
+        files: HashMap::new(),
+        done: vec![],
+        rows: HashMap::new(),
+    };
+    let mut start = from;
+    while start <= to {
+        let end = (start + CHUNK_BLOCKS - 1).min(to);
+        let days = block_days(db, start, end).await.map_err(|e| e.to_string())?;
+        for (dataset, rows) in chunk_rows(db, local, &args.datasets, start, end).await.map_err(|e| e.to_string())? {
+            sink.write(dataset, &rows, &days)?;
+        }
+        start = end + 1;
+    }
+    let (files, rows) = sink.finish()?;
+    if args.range == Range::Incremental {
+        Cursor::set(db, &args.cursor(), to).await.map_err(|e| e.to_string())?;
+    }
+    Ok(ExportReport { from, to, rows, files })
+}
@@ -6,7 +6,16 @@ pub mod store;
 pub mod summary_app;
 pub mod check_app;
 pub mod nft_app;
+pub mod nft_fetch_app;
+pub mod nft_verify_app;
 pub mod balance_app;
+pub mod snapshot_app;
+pub mod price_app;
+pub mod analytics_app;
+pub mod api_app;
+pub mod stream_app;
+pub mod webhook_app;
+pub mod export_app;
 pub use entity::*;
 pub use model::block;
 pub use model::transaction;
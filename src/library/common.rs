@@ -25,6 +25,15 @@ pub async fn db_connn(database_url: String) -> DatabaseConnection {
     }
 }
 
+pub const MINUTE: (&str, i64) = ("minute", 60);
+pub const HOUR: (&str, i64) = ("hour", 60 * 60);
+pub const DAY: (&str, i64) = ("day", 60 * 60 * 24);
+
+// start of the interval holding ts
+pub fn bucket(ts: i64, secs: i64) -> i64 {
+    ts - ts.rem_euclid(secs)
+}
+
 pub fn now() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -39,6 +48,10 @@ pub fn as_timestamp(str_date: &str) -> i64 {
     }
 }
 
+pub fn as_day(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(60 * 60 * 24)
+}
+
 pub fn as_vec<T: std::hash::Hash + std::cmp::Eq>(set: HashSet<T>) -> Vec<T> {
     set.into_iter().collect()
 }
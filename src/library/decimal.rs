@@ -0,0 +1,37 @@
+// serde_json reads numbers beyond u64 as f64, which rounds raw 18 decimal amounts.
+// These helpers read the number text itself, use with `#[serde(deserialize_with = ...)]`.
+use std::collections::HashMap;
+
+use bigdecimal::BigDecimal;
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+fn parse<E: Error>(raw: &RawValue) -> Result<BigDecimal, E> {
+    raw.get().trim_matches('"').parse::<BigDecimal>().map_err(E::custom)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigDecimal, D::Error> {
+    let raw = Box::<RawValue>::deserialize(deserializer)?;
+    parse(&raw)
+}
+
+pub fn deserialize_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, BigDecimal>, D::Error> {
+    HashMap::<String, Box<RawValue>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, raw)| parse(&raw).map(|v| (k, v)))
+        .collect()
+}
+
+pub fn deserialize_option_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Option<BigDecimal>>, D::Error> {
+    HashMap::<String, Option<Box<RawValue>>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, raw)| match raw {
+            Some(raw) => parse(&raw).map(|v| (k, Some(v))),
+            None => Ok((k, None)),
+        })
+        .collect()
+}
@@ -1,7 +1,10 @@
+use std::sync::Arc;
+
 use lmscan_agent::service::finder_service::Finder;
+use lmscan_agent::service::price_service::PriceService;
 
 use lmscan_agent::library::common::*;
-use lmscan_agent::{check_app, nft_app, summary_app, balance_app};
+use lmscan_agent::{check_app, nft_app, nft_fetch_app, nft_verify_app, summary_app, balance_app, snapshot_app, price_app, analytics_app, api_app, stream_app, webhook_app, export_app};
 
 extern crate dotenvy;
 use dotenvy::{dotenv, var};
@@ -12,15 +15,60 @@ async fn main() {
     log4rs::init_file(var("LOG_CONFIG_FILE_PATH").unwrap(), Default::default()).unwrap();
 
     let database_url = var("DATABASE_URL").expect("DATABASE_URL must be set.");
+    let db = db_connn(database_url).await;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [cmd, rest @ ..] = args.as_slice() {
+        match cmd.as_str() {
+            "webhook-replay" => match webhook_app::Replay::parse(rest) {
+                Ok(target) => match webhook_app::replay(&db, target).await {
+                    Ok(count) => println!("{count} deliveries queued"),
+                    Err(err) => eprintln!("webhook replay fail: {err}"),
+                },
+                Err(usage) => eprintln!("{usage}"),
+            },
+            "export" => match export_app::ExportArgs::parse(rest) {
+                Ok(args) => {
+                    // balance_tx lives in the balance app's sqlite
+                    let local = match var("SQLITE_URL") {
+                        Ok(url) if !url.is_empty() => Some(db_connn(url).await),
+                        _ => None,
+                    };
+                    match export_app::export(&db, local.as_ref(), args).await {
+                        Ok(report) => println!(
+                            "exported blocks {}..={}: {:?} in {} files",
+                            report.from,
+                            report.to,
+                            report.rows,
+                            report.files.len()
+                        ),
+                        Err(err) => eprintln!("export fail: {err}"),
+                    }
+                }
+                Err(usage) => eprintln!("{usage}"),
+            },
+            _ => eprintln!("unknown command {cmd}"),
+        }
+        return;
+    }
+
     let coin_market_api_key = var("COIN_MARKET_API_KEY").expect("COIN_MARKET_API_KEY must be set.");
+    // summary and candles share one quote source so the vendor is polled once
+    let prices = Arc::new(PriceService::from_env(coin_market_api_key));
     let sqlite_url = var("SQLITE_URL").expect("SQLITE_URL must be set");
-
-    let db = db_connn(database_url).await;
     Finder::init(db.clone());
     tokio::join!(
-        summary_app::summary_loop(db.clone(), coin_market_api_key),
+        summary_app::summary_loop(db.clone(), prices.clone()),
+        price_app::price_loop(db.clone(), prices),
         check_app::check_loop(db.clone()),
         nft_app::nft_loop(db.clone()),
+        nft_fetch_app::nft_fetch_loop(db.clone()),
+        nft_verify_app::nft_verify_loop(db.clone()),
         balance_app::balance_loop(db.clone(), sqlite_url),
+        snapshot_app::snapshot_loop(db.clone()),
+        analytics_app::analytics_loop(db.clone()),
+        api_app::api_loop(db.clone()),
+        stream_app::stream_loop(db.clone()),
+        webhook_app::webhook_loop(db.clone()),
     );
 }
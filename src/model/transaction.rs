@@ -1,3 +1,5 @@
+#[macro_use]
+pub mod unknown;
 pub mod account_transaction;
 mod agenda_transaction;
 pub mod common;
@@ -20,7 +22,8 @@ use crate::store::locked_balance::LockedBalanceStore;
 use crate::store::sled_store::SledStore;
 use crate::store::wal::State;
 use crate::tx_entity::{self, ActiveModel};
-use crate::{account_entity, account_mapper, nft_tx};
+use sea_orm::Set;
+use crate::{account_entity, account_history, account_mapper, dao_activity, nft_tx, reward_payout, snapshot, token_definition};
 
 use self::account_transaction::*;
 use self::agenda_transaction::*;
@@ -28,6 +31,7 @@ use self::common::Common;
 use self::group_transaction::*;
 use self::reward_transaction::*;
 use self::token_transaction::*;
+use self::unknown::UnknownTx;
 
 use super::balance::Balance;
 
@@ -39,20 +43,33 @@ pub struct TransactionWithResult {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum TransactionResult {
     AddPublicKeySummariesResult { removed :HashMap<String, String> },
     BurnFungibleTokenResult {
-        #[serde(rename = "outputAmount")]
+        #[serde(rename = "outputAmount", deserialize_with = "crate::library::decimal::deserialize")]
         output_amount: BigDecimal,
     },
-    EntrustFungibleTokenResult { remainder: BigDecimal },
-    ExecuteRewardResult { outputs: HashMap<String, BigDecimal> },
-    ExecuteOwnershipRewardResult { outputs: HashMap<String, BigDecimal> },
+    EntrustFungibleTokenResult {
+        #[serde(deserialize_with = "crate::library::decimal::deserialize")]
+        remainder: BigDecimal,
+    },
+    ExecuteRewardResult {
+        #[serde(deserialize_with = "crate::library::decimal::deserialize_map")]
+        outputs: HashMap<String, BigDecimal>,
+    },
+    ExecuteOwnershipRewardResult {
+        #[serde(deserialize_with = "crate::library::decimal::deserialize_map")]
+        outputs: HashMap<String, BigDecimal>,
+    },
     VoteSimpleAgendaResult {
-        #[serde(rename = "votingAmount")]
+        #[serde(rename = "votingAmount", deserialize_with = "crate::library::decimal::deserialize")]
         voting_amount: BigDecimal,
     },
+    #[serde(skip)]
+    Unknown(UnknownTx),
 }
+unknown_fallback!(TransactionResult);
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SignedTx {
@@ -74,13 +91,17 @@ pub struct Signature {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum Transaction {
     RewardTx(RewardTx),
     TokenTx(TokenTx),
     AccountTx(AccountTx),
     GroupTx(GroupTx),
     AgendaTx(AgendaTx),
+    #[serde(skip)]
+    Unknown(UnknownTx),
 }
+unknown_fallback!(Transaction);
 
 impl Common for Transaction {
     fn created_at(&self) -> i64 {
@@ -90,6 +111,7 @@ impl Common for Transaction {
             Transaction::AccountTx(t) => t.created_at(),
             Transaction::GroupTx(t) => t.created_at(),
             Transaction::AgendaTx(t) => t.created_at(),
+            Transaction::Unknown(t) => t.created_at(),
         }
     }
 
@@ -116,6 +138,11 @@ impl Common for Transaction {
             Transaction::AgendaTx(t) => {
                 t.from(hash, block_hash, block_number, tx)
             }
+            Transaction::Unknown(t) => {
+                let mut model = t.from(&t.tag, hash, block_hash, block_number, tx);
+                model.sub_type = Set(t.inner_tag().unwrap_or(t.tag.clone()));
+                model
+            }
         }
     }
 }
@@ -583,10 +610,47 @@ impl Job for TransactionWithResult {
     }
 }
 
+impl TransactionWithResult {
+    // type tags the agent could not decode, for the unknown type counter.
+    pub fn unknown_tags(&self) -> Vec<String> {
+        let mut tags = vec![];
+        match &self.signed_tx.value {
+            Transaction::Unknown(t) => tags.push(t.tag.clone()),
+            Transaction::TokenTx(TokenTx::Unknown(t)) => tags.push(format!("TokenTx::{}", t.tag)),
+            Transaction::RewardTx(RewardTx::Unknown(t)) => tags.push(format!("RewardTx::{}", t.tag)),
+            _ => (),
+        }
+        if let Some(TransactionResult::Unknown(t)) = &self.result {
+            tags.push(format!("TransactionResult::{}", t.tag));
+        }
+        tags
+    }
+
+    pub fn get_reward_payouts(&self, hash: &str, block_number: i64) -> Vec<reward_payout::Model> {
+        match &self.signed_tx.value {
+            Transaction::RewardTx(tx) => tx.get_reward_payouts(&self.result, hash, block_number),
+            _ => vec![]
+        }
+    }
+
+    pub fn get_account_history(&self, hash: &str, block_number: i64) -> Option<account_history::Model> {
+        match &self.signed_tx.value {
+            Transaction::AccountTx(tx) => Some(tx.get_account_history(&self.result, hash, block_number)),
+            _ => None
+        }
+    }
+}
+
 impl Transaction {
-    pub fn get_nft_active_model(&self, tx_entity: &tx_entity::ActiveModel, from: String) -> Option<nft_tx::ActiveModel> {
+    pub fn get_nft_active_model(&self, tx_entity: &tx_entity::ActiveModel, tx_index: i32, from: String) -> Option<nft_tx::ActiveModel> {
         match self {
-            Transaction::TokenTx(tx) => tx.get_nft_active_model(tx_entity, from),
+            Transaction::TokenTx(tx) => tx.get_nft_active_model(tx_entity, tx_index, from),
+            _ => None
+        }
+    }
+    pub fn definition_id(&self) -> Option<String> {
+        match self {
+            Transaction::TokenTx(tx) => Some(tx.definition_id()).filter(|d| !d.is_empty()),
             _ => None
         }
     }
@@ -596,6 +660,31 @@ impl Transaction {
             _ => None
         }
     }
+    pub fn get_snapshot(&self, hash: String, block_number: i64) -> Option<snapshot::Model> {
+        match self {
+            Transaction::RewardTx(tx) => tx.get_snapshot(hash, block_number),
+            Transaction::TokenTx(tx) => tx.get_snapshot(hash, block_number),
+            _ => None
+        }
+    }
+    pub fn get_token_definition(&self) -> Option<token_definition::Model> {
+        match self {
+            Transaction::TokenTx(tx) => tx.get_token_definition(),
+            _ => None
+        }
+    }
+    pub fn get_supply_change(&self) -> Option<token_definition::Model> {
+        match self {
+            Transaction::TokenTx(tx) => tx.get_supply_change(),
+            _ => None
+        }
+    }
+    pub fn get_dao_activities(&self) -> Vec<dao_activity::Model> {
+        match self {
+            Transaction::RewardTx(tx) => tx.get_dao_activities(),
+            _ => vec![]
+        }
+    }
     pub fn get_account_mapper(&self, signer: String, hash: String, event_time: i64) -> Vec<account_mapper::Model> {
         let v = match self {
             Transaction::RewardTx(tx) => tx.get_accounts(signer.clone()),
@@ -603,6 +692,7 @@ impl Transaction {
             Transaction::AccountTx(tx) => tx.get_accounts(),
             Transaction::GroupTx(tx) => tx.get_accounts(signer.clone()),
             Transaction::AgendaTx(_) => vec![signer],
+            Transaction::Unknown(_) => vec![signer],
         };
         v.into_iter().sorted().dedup().map(|account| account_mapper::Model {
             address: account,
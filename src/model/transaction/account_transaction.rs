@@ -4,10 +4,10 @@ use sea_orm::Set;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    account_entity, library::common::{as_timestamp, now}, tx_entity::ActiveModel
+    account_entity, account_history, library::common::{as_timestamp, now}, tx_entity::ActiveModel
 };
 
-use super::{common::Common, TransactionWithResult};
+use super::{common::Common, TransactionResult, TransactionWithResult};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AccountTx {
@@ -60,16 +60,76 @@ impl AccountTx {
     pub fn get_acc_active_model(&self) -> Option<account_entity::ActiveModel> {
         match self {
             AccountTx::CreateAccount(tx) => Some(account_entity::Model::from(tx)),
+            AccountTx::CreateAccountWithExternalChainAddresses(tx) => Some(account_entity::Model::from_external(tx)),
             _ => None
         }
     }
 
     pub fn get_accounts(&self) -> Vec<String> {
         match self {
+            AccountTx::AddPublicKeySummaries(tx) => vec![tx.account.clone()],
             AccountTx::CreateAccount(tx) => vec![tx.account.clone()],
-            _ => vec![],
+            AccountTx::UpdateAccount(tx) => vec![tx.account.clone()],
+            AccountTx::CreateAccountWithExternalChainAddresses(tx) => vec![tx.account.clone()],
+            AccountTx::UpdateAccountWithExternalChainAddresses(tx) => vec![tx.account.clone()],
         }
     }
+
+    pub fn get_account_history(
+        &self,
+        result: &Option<TransactionResult>,
+        hash: &str,
+        block_number: i64,
+    ) -> account_history::Model {
+        let mut m = account_history::Model {
+            hash: hash.to_owned(),
+            address: String::new(),
+            tx_type: String::new(),
+            eth_address: None,
+            guardian: None,
+            external_chain_addresses: None,
+            public_key_summaries: None,
+            removed_summaries: None,
+            block_number,
+            event_time: self.created_at(),
+            created_at: now(),
+        };
+        match self {
+            AccountTx::AddPublicKeySummaries(tx) => {
+                m.address = tx.account.clone();
+                m.tx_type = "AddPublicKeySummaries".to_string();
+                m.public_key_summaries = serde_json::to_value(&tx.summaries).ok();
+                if let Some(TransactionResult::AddPublicKeySummariesResult { removed }) = result {
+                    m.removed_summaries = serde_json::to_value(removed).ok();
+                }
+            }
+            AccountTx::CreateAccount(tx) => {
+                m.address = tx.account.clone();
+                m.tx_type = "CreateAccount".to_string();
+                m.eth_address = tx.eth_address.clone();
+                m.guardian = tx.guardian.clone();
+            }
+            AccountTx::UpdateAccount(tx) => {
+                m.address = tx.account.clone();
+                m.tx_type = "UpdateAccount".to_string();
+                m.eth_address = tx.eth_address.clone();
+                m.guardian = tx.guardian.clone();
+            }
+            AccountTx::CreateAccountWithExternalChainAddresses(tx) => {
+                m.address = tx.account.clone();
+                m.tx_type = "CreateAccountWithExternalChainAddresses".to_string();
+                m.guardian = tx.guardian.clone();
+                m.external_chain_addresses = serde_json::to_value(&tx.external_chain_addresses).ok();
+            }
+            AccountTx::UpdateAccountWithExternalChainAddresses(tx) => {
+                m.address = tx.account.clone();
+                m.tx_type = "UpdateAccountWithExternalChainAddresses".to_string();
+                m.guardian = tx.guardian.clone();
+                m.external_chain_addresses = serde_json::to_value(&tx.external_chain_addresses).ok();
+            }
+        }
+        m
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -104,7 +164,8 @@ pub struct CreateAccountWithExternalChainAddresses {
     pub created_at: String,
     pub account: String,
     pub guardian: Option<String>,
-    // pub externalChainAddresses: Map<String, String>,
+    #[serde(default)]
+    pub external_chain_addresses: HashMap<String, String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -113,7 +174,8 @@ pub struct UpdateAccountWithExternalChainAddresses {
     pub created_at: String,
     pub account: String,
     pub guardian: Option<String>,
-    // pub externalChainAddresses: Map<String, String>,
+    #[serde(default)]
+    pub external_chain_addresses: HashMap<String, String>,
 }
 
 impl Common for AddPublicKeySummaries {
@@ -233,7 +295,7 @@ impl Common for UpdateAccountWithExternalChainAddresses {
             signer: Set(txr.signed_tx.sig.account.clone()),
             tx_type: Set("Account".to_string()),
             token_type: Set("LM".to_string()),
-            sub_type: Set("CreateAccountWithExternalChainAddresses".to_string()),
+            sub_type: Set("UpdateAccountWithExternalChainAddresses".to_string()),
             block_hash: Set(block_hash),
             block_number: Set(block_number),
             event_time: Set(self.created_at()),
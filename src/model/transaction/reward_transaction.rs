@@ -5,13 +5,16 @@ use sea_orm::Set;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    library::common::{as_timestamp, now},
+    dao_activity,
+    library::common::{as_day, as_timestamp, now},
+    reward_payout, snapshot,
     tx_entity::ActiveModel,
 };
 
-use super::{common::Common, TransactionWithResult};
+use super::{common::Common, unknown::UnknownTx, TransactionResult, TransactionWithResult};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum RewardTx {
     RecordActivity(RecordActivity),
     RegisterDao(RegisterDao),
@@ -20,7 +23,10 @@ pub enum RewardTx {
     ExecuteReward(ExecuteReward),
     ExecuteOwnershipReward(ExecuteOwnershipReward),
     BuildSnapshot(BuildSnapshot),
+    #[serde(skip)]
+    Unknown(UnknownTx),
 }
+unknown_fallback!(RewardTx);
 
 impl Common for RewardTx {
     fn created_at(&self) -> i64 {
@@ -32,6 +38,7 @@ impl Common for RewardTx {
             RewardTx::ExecuteReward(t) => t.created_at(),
             RewardTx::ExecuteOwnershipReward(t) => t.created_at(),
             RewardTx::BuildSnapshot(t) => t.created_at(),
+            RewardTx::Unknown(t) => t.created_at(),
         }
     }
     fn from(
@@ -49,6 +56,7 @@ impl Common for RewardTx {
             RewardTx::ExecuteReward(t) => t.from(hash, block_hash, block_number, tx),
             RewardTx::ExecuteOwnershipReward(t) => t.from(hash, block_hash, block_number, tx),
             RewardTx::BuildSnapshot(t) => t.from(hash, block_hash, block_number, tx),
+            RewardTx::Unknown(t) => t.from("Reward", hash, block_hash, block_number, tx),
         }
     }
 }
@@ -73,6 +81,64 @@ impl RewardTx {
         v.push(signer);
         v
     }
+
+    pub fn get_dao_activities(&self) -> Vec<dao_activity::Model> {
+        match self {
+            RewardTx::RecordActivity(tx) => {
+                let day = as_day(as_timestamp(&tx.timestamp));
+                let mut map: HashMap<String, dao_activity::Model> = HashMap::new();
+                for (address, activities) in &tx.user_activity {
+                    let m = map.entry(address.clone()).or_insert(dao_activity::Model::new(address.clone(), day));
+                    m.activity_point += activities.iter().map(|a| a.point).sum::<i64>();
+                }
+                for (address, activities) in &tx.token_received {
+                    let m = map.entry(address.clone()).or_insert(dao_activity::Model::new(address.clone(), day));
+                    m.received_point += activities.iter().map(|a| a.point).sum::<i64>();
+                }
+                map.into_values().collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn get_snapshot(&self, hash: String, block_number: i64) -> Option<snapshot::Model> {
+        match self {
+            RewardTx::BuildSnapshot(tx) => Some(snapshot::Model::from_build(tx, hash, block_number)),
+            _ => None,
+        }
+    }
+
+    pub fn get_reward_payouts(
+        &self,
+        result: &Option<TransactionResult>,
+        hash: &str,
+        block_number: i64,
+    ) -> Vec<reward_payout::Model> {
+        let (reward_type, dao_account, definition_id, outputs) = match (self, result) {
+            (
+                RewardTx::ExecuteReward(tx),
+                Some(TransactionResult::ExecuteRewardResult { outputs }),
+            ) => ("ExecuteReward", tx.dao_account.clone(), None, outputs),
+            (
+                RewardTx::ExecuteOwnershipReward(tx),
+                Some(TransactionResult::ExecuteOwnershipRewardResult { outputs }),
+            ) => ("ExecuteOwnershipReward", None, Some(tx.definition_id.clone()), outputs),
+            _ => return vec![],
+        };
+        outputs.iter().map(|(address, amount)| reward_payout::Model {
+            hash: hash.to_owned(),
+            address: address.clone(),
+            amount: amount.clone(),
+            reward_type: reward_type.to_owned(),
+            dao_account: dao_account.clone(),
+            definition_id: definition_id.clone(),
+            snapshot_hash: None,
+            snapshot_number: None,
+            block_number,
+            event_time: self.created_at(),
+            created_at: now(),
+        }).collect()
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -105,6 +171,7 @@ pub struct OfferReward {
     pub created_at: String,
     pub token_definition_id: String,
     pub inputs: HashSet<String>,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize_map")]
     pub outputs: HashMap<String, BigDecimal>,
     pub memo: Option<String>,
 }
@@ -138,8 +205,11 @@ pub struct ExecuteOwnershipReward {
 pub struct BuildSnapshot {
     pub created_at: String,
     pub timestamp: String,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize")]
     pub account_amount: BigDecimal,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize")]
     pub token_amount: BigDecimal,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize")]
     pub ownership_amount: BigDecimal,
 }
 
@@ -9,12 +9,13 @@ use crate::{
         as_timestamp, now,
     },
     tx_entity::{self, ActiveModel},
-    nft_tx
+    nft_tx, snapshot, token_definition
 };
 
-use super::{common::Common, TransactionWithResult};
+use super::{common::Common, unknown::UnknownTx, TransactionWithResult};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum TokenTx {
     #[serde(rename = "BurnNFT")]
     BurnNft(BurnNft),
@@ -38,7 +39,10 @@ pub enum TokenTx {
     DisposeEntrustedNft(DisposeEntrustedNft),
     DisposeEntrustedFungibleToken(DisposeEntrustedFungibleToken),
     CreateSnapshot(CreateSnapshot),
+    #[serde(skip)]
+    Unknown(UnknownTx),
 }
+unknown_fallback!(TokenTx);
 
 impl Common for TokenTx {
     fn created_at(&self) -> i64 {
@@ -58,6 +62,7 @@ impl Common for TokenTx {
             TokenTx::BurnNft(t) => t.created_at(),
             TokenTx::BurnFungibleToken(t) => t.created_at(),
             TokenTx::CreateSnapshot(t) => t.created_at(),
+            TokenTx::Unknown(t) => t.created_at(),
         }
     }
 
@@ -84,45 +89,166 @@ impl Common for TokenTx {
             TokenTx::DisposeEntrustedFungibleToken(t) => t.from(hash, block_hash, block_number, tx),
             TokenTx::BurnFungibleToken(t) => t.from(hash, block_hash, block_number, tx),
             TokenTx::CreateSnapshot(t) => t.from(hash, block_hash, block_number, tx),
+            TokenTx::Unknown(t) => t.from("Token", hash, block_hash, block_number, tx),
         }
     }
 }
 
 impl TokenTx {
-    pub fn get_nft_active_model(&self, tx_entity: &tx_entity::ActiveModel, from: String) -> Option<nft_tx::ActiveModel> {
+    pub fn get_nft_active_model(&self, tx_entity: &tx_entity::ActiveModel, tx_index: i32, from: String) -> Option<nft_tx::ActiveModel> {
         match self {
             TokenTx::EntrustNft(tx) => Some(nft_tx::Model::from(
-                self, tx_entity, tx.input.clone(), tx.to.clone()
+                self, tx_entity, tx_index, tx.input.clone(), tx.to.clone()
             )),
             TokenTx::TransferNft(tx) => Some(nft_tx::Model::from(
-                self, tx_entity, tx.input.clone(), tx.output.clone()
+                self, tx_entity, tx_index, tx.input.clone(), tx.output.clone()
             )),
-            TokenTx::MintNft(tx) => Some(nft_tx::Model::from(
-                self, tx_entity, from, tx.output.clone()
+            TokenTx::MintNft(tx) | TokenTx::MintNftWithMemo(tx) | TokenTx::UpdateNft(tx) => Some(nft_tx::Model::from(
+                self, tx_entity, tx_index, from, tx.output.clone()
+            )),
+            // token id is resolved from the input tx by nft_app
+            TokenTx::BurnNft(_) => Some(nft_tx::Model::from(
+                self, tx_entity, tx_index, from, String::new()
             )),
             TokenTx::DisposeEntrustedNft(tx) => {
                 let out = tx.output.as_ref().unwrap_or(&tx.input);
-                Some(nft_tx::Model::from(self, tx_entity, tx.input.clone(), out.to_string()))
+                Some(nft_tx::Model::from(self, tx_entity, tx_index, tx.input.clone(), out.to_string()))
             },
             _ => None
         }
     }
 
+    // state an nft moves to after this tx, owner None keeps the current owner.
+    pub fn get_nft_transition(&self, signer: &str) -> Option<NftTransition> {
+        let (definition_id, token_id, status, owner, custodian) = match self {
+            TokenTx::MintNft(tx) | TokenTx::MintNftWithMemo(tx) => (
+                &tx.token_definition_id,
+                tx.token_id.clone(),
+                NftStatus::Minted,
+                Some(tx.output.clone()),
+                None,
+            ),
+            TokenTx::UpdateNft(tx) => (
+                &tx.token_definition_id,
+                tx.token_id.clone(),
+                NftStatus::Updated,
+                Some(tx.output.clone()),
+                None,
+            ),
+            TokenTx::TransferNft(tx) => (
+                &tx.definition_id,
+                tx.token_id.clone(),
+                NftStatus::Owned,
+                Some(tx.output.clone()),
+                None,
+            ),
+            // the owner signs the entrust, the custodian signs the dispose
+            TokenTx::EntrustNft(tx) => (
+                &tx.definition_id,
+                tx.token_id.clone(),
+                NftStatus::Entrusted,
+                Some(signer.to_owned()),
+                Some(tx.to.clone()),
+            ),
+            TokenTx::DisposeEntrustedNft(tx) => (
+                &tx.definition_id,
+                tx.token_id.clone(),
+                NftStatus::Owned,
+                tx.output.clone(),
+                None,
+            ),
+            TokenTx::BurnNft(tx) => (&tx.definition_id, String::new(), NftStatus::Burned, None, None),
+            _ => return None,
+        };
+        let rarity = match self {
+            TokenTx::MintNft(tx) | TokenTx::MintNftWithMemo(tx) | TokenTx::UpdateNft(tx) => Some(tx.rarity.clone()),
+            _ => None,
+        };
+        Some(NftTransition {
+            definition_id: definition_id.clone(),
+            rarity,
+            token_id,
+            status,
+            owner,
+            custodian,
+            event_time: self.created_at(),
+        })
+    }
+
+    pub fn get_snapshot(&self, hash: String, block_number: i64) -> Option<snapshot::Model> {
+        match self {
+            TokenTx::CreateSnapshot(tx) => Some(snapshot::Model::from_create(tx, hash, block_number)),
+            _ => None
+        }
+    }
+
+    pub fn get_token_definition(&self) -> Option<token_definition::Model> {
+        match self {
+            TokenTx::DefineToken(tx) => Some(token_definition::Model::from(tx)),
+            TokenTx::DefineTokenWithPrecision(tx) => Some(token_definition::Model::from(tx)),
+            _ => None
+        }
+    }
+
+    pub fn get_supply_change(&self) -> Option<token_definition::Model> {
+        match self {
+            TokenTx::MintFungibleToken(tx) => Some(token_definition::Model::supply(
+                tx.definition_id.clone(), tx.outputs.values().sum(), BigDecimal::from(0)
+            )),
+            TokenTx::BurnFungibleToken(tx) => Some(token_definition::Model::supply(
+                tx.definition_id.clone(), BigDecimal::from(0), tx.amount.clone()
+            )),
+            TokenTx::EntrustFungibleToken(tx) => Some(token_definition::Model::locked(
+                tx.definition_id.clone(), tx.amount.clone()
+            )),
+            TokenTx::DisposeEntrustedFungibleToken(tx) => Some(token_definition::Model::locked(
+                tx.definition_id.clone(), -tx.outputs.values().sum::<BigDecimal>()
+            )),
+            _ => None
+        }
+    }
+
     pub fn token_id(&self) -> String {
         match self {
             TokenTx::EntrustNft(tx) => tx.token_id.clone(),
             TokenTx::TransferNft(tx) => tx.token_id.clone(),
             TokenTx::MintNft(tx) => tx.token_id.clone(),
+            TokenTx::MintNftWithMemo(tx) => tx.token_id.clone(),
+            TokenTx::UpdateNft(tx) => tx.token_id.clone(),
             TokenTx::DisposeEntrustedNft(tx) => tx.token_id.clone(),
             _ => String::from("")
         }
     }
 
+    pub fn definition_id(&self) -> String {
+        match self {
+            TokenTx::BurnNft(tx) => tx.definition_id.clone(),
+            TokenTx::EntrustNft(tx) => tx.definition_id.clone(),
+            TokenTx::EntrustFungibleToken(tx) => tx.definition_id.clone(),
+            TokenTx::BurnFungibleToken(tx) => tx.definition_id.clone(),
+            TokenTx::TransferNft(tx) => tx.definition_id.clone(),
+            TokenTx::TransferFungibleToken(tx) => tx.token_definition_id.clone(),
+            TokenTx::MintNft(tx) => tx.token_definition_id.clone(),
+            TokenTx::MintNftWithMemo(tx) => tx.token_definition_id.clone(),
+            TokenTx::UpdateNft(tx) => tx.token_definition_id.clone(),
+            TokenTx::MintFungibleToken(tx) => tx.definition_id.clone(),
+            TokenTx::DefineToken(tx) => tx.definition_id.clone(),
+            TokenTx::DefineTokenWithPrecision(tx) => tx.definition_id.clone(),
+            TokenTx::DisposeEntrustedNft(tx) => tx.definition_id.clone(),
+            TokenTx::DisposeEntrustedFungibleToken(tx) => tx.definition_id.clone(),
+            TokenTx::CreateSnapshot(tx) => tx.definition_id.clone(),
+            TokenTx::Unknown(_) => String::from("")
+        }
+    }
+
     pub fn sub_type(&self) -> String {
         match self {
             TokenTx::EntrustNft(_) => String::from("EntrustNft"),
             TokenTx::TransferNft(_) => String::from("TransferNft"),
             TokenTx::MintNft(_) =>  String::from("MintNft"),
+            TokenTx::MintNftWithMemo(_) => String::from("MintNftWithMemo"),
+            TokenTx::UpdateNft(_) => String::from("UpdateNft"),
+            TokenTx::BurnNft(_) => String::from("BurnNft"),
             TokenTx::DisposeEntrustedNft(_) => String::from("DisposeEntrustedNft"),
             _ => String::from("")
         }
@@ -147,6 +273,40 @@ impl TokenTx {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftStatus {
+    Minted,
+    Owned,
+    Entrusted,
+    Burned,
+    Updated,
+}
+
+impl NftStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NftStatus::Minted => "minted",
+            NftStatus::Owned => "owned",
+            NftStatus::Entrusted => "entrusted",
+            NftStatus::Burned => "burned",
+            NftStatus::Updated => "updated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NftTransition {
+    pub definition_id: String,
+    // empty for BurnNft, which only carries the input tx hash
+    pub token_id: String,
+    pub status: NftStatus,
+    pub owner: Option<String>,
+    pub custodian: Option<String>,
+    // on-chain rarity, only set by mint and update
+    pub rarity: Option<String>,
+    pub event_time: i64,
+}
+
 // TokenTx::MintNft(tx) => 
 //     let nft_meta_info_opt =
 //         ApiService::get_request_until(tx.data_url.clone(), 1).await;
@@ -175,6 +335,7 @@ pub struct EntrustNft {
 pub struct EntrustFungibleToken {
     pub created_at: String,
     pub definition_id: String,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize")]
     pub amount: BigDecimal,
     pub inputs: HashSet<String>,
     pub to: String,
@@ -185,6 +346,7 @@ pub struct EntrustFungibleToken {
 pub struct BurnFungibleToken {
     pub created_at: String,
     pub definition_id: String,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize")]
     pub amount: BigDecimal,
     pub inputs: HashSet<String>,
 }
@@ -206,6 +368,7 @@ pub struct TransferFungibleToken {
     pub created_at: String,
     pub token_definition_id: String,
     pub inputs: HashSet<String>,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize_map")]
     pub outputs: HashMap<String, BigDecimal>,
     pub memo: Option<String>,
 }
@@ -255,6 +418,7 @@ pub struct UpdateNft {
 pub struct MintFungibleToken {
     pub created_at: String,
     pub definition_id: String,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize_map")]
     pub outputs: HashMap<String, BigDecimal>,
 }
 
@@ -267,6 +431,8 @@ pub struct DefineToken {
     pub symbol: Option<String>,
     pub minter_group: Option<String>,
     pub nft_info: Option<NftInfo>,
+    // only set by DefineTokenWithPrecision
+    pub precision: Option<i32>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -282,6 +448,7 @@ pub struct Inner {
 #[serde(rename_all = "camelCase")]
 pub struct Value {
     pub minter: String,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize_option_map")]
     pub rarity: HashMap<String, Option<BigDecimal>>,
     pub data_url: String,
     pub content_hash: String,
@@ -303,6 +470,7 @@ pub struct DisposeEntrustedFungibleToken {
     pub created_at: String,
     pub definition_id: String,
     pub inputs: HashSet<String>,
+    #[serde(deserialize_with = "crate::library::decimal::deserialize_map")]
     pub outputs: HashMap<String, BigDecimal>,
 }
 
@@ -414,6 +582,17 @@ impl Common for TransferNft {
     }
 }
 
+impl TransferFungibleToken {
+    // amount leaving the signer, change sent back is not counted
+    pub fn volume(&self, signer: &str) -> BigDecimal {
+        self.outputs
+            .iter()
+            .filter(|(addr, _)| addr.as_str() != signer)
+            .map(|(_, v)| v)
+            .sum()
+    }
+}
+
 impl Common for TransferFungibleToken {
     fn created_at(&self) -> i64 {
         as_timestamp(self.created_at.as_str())
@@ -0,0 +1,172 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use log::warn;
+use sea_orm::Set;
+use serde::de::Visitor;
+use serde::{forward_to_deserialize_any, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+
+use crate::{library::common::now, tx_entity::ActiveModel};
+
+use super::TransactionWithResult;
+
+lazy_static! {
+    static ref UNKNOWN_COUNT: DashMap<String, u64> = DashMap::new();
+}
+
+// implements Serialize/Deserialize for an enum derived with `#[serde(remote = "Self")]`,
+// falling back to its `Unknown` variant when the node sends a type tag we don't know yet.
+macro_rules! unknown_fallback {
+    ($t:ident) => {
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    $t::Unknown(u) => u.serialize(serializer),
+                    _ => $t::serialize(self, serializer),
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                static KNOWN: std::sync::OnceLock<&'static [&'static str]> = std::sync::OnceLock::new();
+                let known = KNOWN.get_or_init(|| crate::transaction::unknown::variants(|d| $t::deserialize(d).map(|_| ())));
+                let raw = Box::<serde_json::value::RawValue>::deserialize(deserializer)?;
+                crate::transaction::unknown::parse_or_unknown(
+                    &raw,
+                    known,
+                    |s| $t::deserialize(&mut serde_json::Deserializer::from_str(s)),
+                    $t::Unknown,
+                )
+                .map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct UnknownTx {
+    pub tag: String,
+    pub json: String,
+}
+
+// derived enum impls pass their variant names to deserialize_enum, this deserializer only records them.
+pub struct VariantNames<'a>(&'a Cell<&'static [&'static str]>);
+
+impl<'de> Deserializer<'de> for VariantNames<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("not an enum"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        variants: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.set(variants);
+        Err(serde::de::Error::custom("variant names recorded"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+pub(crate) fn variants(
+    deserialize: impl FnOnce(VariantNames) -> Result<(), serde::de::value::Error>,
+) -> &'static [&'static str] {
+    let names = Cell::new(&[][..]);
+    let _ = deserialize(VariantNames(&names));
+    names.get()
+}
+
+pub(crate) fn parse_or_unknown<T>(
+    raw: &RawValue,
+    known: &[&str],
+    parse: impl FnOnce(&str) -> Result<T, serde_json::Error>,
+    wrap: fn(UnknownTx) -> T,
+) -> Result<T, serde_json::Error> {
+    match UnknownTx::tag_of(raw.get()) {
+        Some(tag) if !known.contains(&tag.as_str()) => Ok(wrap(UnknownTx { tag, json: raw.get().to_owned() })),
+        _ => parse(raw.get()),
+    }
+}
+
+impl Serialize for UnknownTx {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match RawValue::from_string(self.json.clone()) {
+            Ok(raw) => raw.serialize(serializer),
+            Err(err) => Err(serde::ser::Error::custom(err)),
+        }
+    }
+}
+
+impl UnknownTx {
+    fn tag_of(json: &str) -> Option<String> {
+        let map = serde_json::from_str::<HashMap<String, &RawValue>>(json).ok()?;
+        match map.len() {
+            1 => map.into_keys().next(),
+            _ => None,
+        }
+    }
+
+    // tag of the value nested under this one, e.g. the sub type of an unknown tx type.
+    pub fn inner_tag(&self) -> Option<String> {
+        let map = serde_json::from_str::<HashMap<String, &RawValue>>(&self.json).ok()?;
+        map.get(&self.tag).and_then(|inner| Self::tag_of(inner.get()))
+    }
+
+    pub fn created_at(&self) -> i64 {
+        fn find(value: &serde_json::Value) -> Option<i64> {
+            match value {
+                serde_json::Value::Object(map) => match map.get("createdAt").and_then(|v| v.as_str()) {
+                    Some(s) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.timestamp()),
+                    None => map.values().find_map(find),
+                },
+                _ => None,
+            }
+        }
+        serde_json::from_str::<serde_json::Value>(&self.json)
+            .ok()
+            .and_then(|v| find(&v))
+            .unwrap_or(0)
+    }
+
+    pub fn from(
+        &self,
+        tx_type: &str,
+        hash: String,
+        block_hash: String,
+        block_number: i64,
+        txr: TransactionWithResult,
+    ) -> ActiveModel {
+        ActiveModel {
+            hash: Set(hash),
+            signer: Set(txr.signed_tx.sig.account.clone()),
+            tx_type: Set(tx_type.to_string()),
+            token_type: Set("".to_string()),
+            sub_type: Set(self.tag.clone()),
+            block_hash: Set(block_hash),
+            block_number: Set(block_number),
+            event_time: Set(self.created_at()),
+            created_at: Set(now()),
+        }
+    }
+
+    pub fn record(tag: &str) {
+        let mut count = UNKNOWN_COUNT.entry(tag.to_owned()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            warn!("unknown transaction type '{tag}' is stored as raw json");
+        }
+    }
+}
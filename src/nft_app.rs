@@ -1,34 +1,67 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use chrono::{DateTime, Local};
 use itertools::Itertools;
 
-use crate::{nft_file, nft_owner, tx_state};
-use crate::transaction::token_transaction::TokenTx;
+use crate::{chain_counter, nft_collection, nft_fetch, nft_file, nft_history, nft_owner, nft_tx, token_definition, tx_state};
+use crate::transaction::token_transaction::{NftStatus, TokenTx};
 use crate::{
-    service::api_service::ApiService,
     transaction::{
         TransactionWithResult, Transaction,
     },
     library::common::*,
+    service::{counter_service::Counter, cursor_service::Cursor},
 };
-use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
 use sea_orm::DatabaseConnection;
 use sea_orm::*;
 
-use log::{error, info};
+use dotenvy::var;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
-async fn get_mint_nft_tx(
-    latest_time: i64,
+pub const CURSOR: &str = "nft";
+// rows per batch, a block is never split across batches
+const BATCH_SIZE: u64 = 1000;
+
+// nft txs of the next batch of built blocks above the cursor, in chain order, with the height it ends at.
+pub async fn get_nft_tx(
+    cursor: i64,
     db: &DatabaseConnection,
-) -> Vec<tx_state::Model> {
-    tx_state::Entity::find()
-        .from_raw_sql(Statement::from_sql_and_values(DbBackend::Postgres, 
-            r#"select ts.* from tx_state ts join (select * from nft where created_at > $1) nft on ts.hash = nft.tx_hash order by ts.event_time desc"#, 
-            [latest_time.into()]))
-        .all(db).await.unwrap()
+) -> Result<(i64, Vec<(nft_tx::Model, tx_state::Model)>), DbErr> {
+    let built = Cursor::built_height(db, cursor).await?;
+    if built <= cursor {
+        return Ok((cursor, vec![]));
+    }
+    let upper = nft_tx::Entity::find()
+        .select_only()
+        .column(nft_tx::Column::BlockNumber)
+        .filter(nft_tx::Column::BlockNumber.gt(cursor))
+        .filter(nft_tx::Column::BlockNumber.lte(built))
+        .order_by_asc(nft_tx::Column::BlockNumber)
+        .offset(BATCH_SIZE - 1)
+        .limit(1)
+        .into_tuple::<i64>()
+        .one(db).await?
+        .unwrap_or(built);
+    let rows = nft_tx::Entity::find()
+        .filter(nft_tx::Column::BlockNumber.gt(cursor))
+        .filter(nft_tx::Column::BlockNumber.lte(upper))
+        .order_by_asc(nft_tx::Column::BlockNumber)
+        .order_by_asc(nft_tx::Column::TxIndex)
+        .all(db).await?;
+    let mut states: HashMap<String, tx_state::Model> = HashMap::new();
+    for chunk in rows.chunks(1000) {
+        let hashes = chunk.iter().map(|r| r.tx_hash.clone()).collect_vec();
+        for state in tx_state::Entity::find().filter(tx_state::Column::Hash.is_in(hashes)).all(db).await? {
+            states.insert(state.hash.clone(), state);
+        }
+    }
+    let txs = rows.into_iter()
+        .filter_map(|r| states.remove(&r.tx_hash).map(|state| (r, state)))
+        .collect_vec();
+    Ok((upper, txs))
 }
 
 pub fn parse_time(str: &String) -> i64 {
@@ -58,42 +91,390 @@ pub struct NftMetaInfo {
     pub nft_uri: String,
 }
 
+async fn resolve_burned_token(
+    input: &str,
+    batch: &HashMap<String, String>,
+    db: &DatabaseConnection,
+) -> Option<String> {
+    if let Some(id) = batch.get(input) {
+        return Some(id.clone());
+    }
+    nft_tx::Entity::find_by_id(input.to_owned())
+        .one(db).await.ok().flatten()
+        .map(|m| m.token_id)
+        .filter(|id| !id.is_empty())
+}
+
+async fn get_nft_owners(
+    ids: Vec<String>,
+    db: &DatabaseConnection,
+) -> HashMap<String, nft_owner::Model> {
+    let mut owners = HashMap::new();
+    for chunk in ids.chunks(1000) {
+        match nft_owner::Entity::find()
+            .filter(nft_owner::Column::TokenId.is_in(chunk.to_vec()))
+            .all(db).await {
+            Ok(v) => owners.extend(v.into_iter().map(|o| (o.token_id.clone(), o))),
+            Err(e) => error!("{e}"),
+        }
+    }
+    owners
+}
+
+pub async fn recount_collection(
+    definition_id: &str,
+    db: &DatabaseConnection,
+) -> Result<nft_collection::ActiveModel, DbErr> {
+    let burned = NftStatus::Burned.as_str();
+    let (item_count, owner_count) = nft_owner::Entity::find()
+        .select_only()
+        .column_as(Expr::col(nft_owner::Column::TokenId).count(), "item_count")
+        .column_as(Expr::col(nft_owner::Column::Owner).count_distinct(), "owner_count")
+        .filter(nft_owner::Column::DefinitionId.eq(definition_id))
+        .filter(nft_owner::Column::Status.ne(burned))
+        .into_tuple::<(i64, i64)>()
+        .one(db).await?
+        .unwrap_or_default();
+    let burned_count = nft_owner::Entity::find()
+        .filter(nft_owner::Column::DefinitionId.eq(definition_id))
+        .filter(nft_owner::Column::Status.eq(burned))
+        .count(db).await? as i64;
+    let rarities = nft_owner::Entity::find()
+        .select_only()
+        .column(nft_owner::Column::Rarity)
+        .column_as(Expr::col(nft_owner::Column::TokenId).count(), "count")
+        .filter(nft_owner::Column::DefinitionId.eq(definition_id))
+        .filter(nft_owner::Column::Status.ne(burned))
+        .group_by(nft_owner::Column::Rarity)
+        .into_tuple::<(Option<String>, i64)>()
+        .all(db).await?;
+    let (first_mint_at, last_mint_at) = nft_history::Entity::find()
+        .select_only()
+        .column_as(Expr::col(nft_history::Column::EventTime).min(), "first_mint_at")
+        .column_as(Expr::col(nft_history::Column::EventTime).max(), "last_mint_at")
+        .filter(nft_history::Column::DefinitionId.eq(definition_id))
+        .filter(nft_history::Column::Action.is_in(["MintNft", "MintNftWithMemo"]))
+        .into_tuple::<(Option<i64>, Option<i64>)>()
+        .one(db).await?
+        .unwrap_or_default();
+    let transfer_count = nft_history::Entity::find()
+        .filter(nft_history::Column::DefinitionId.eq(definition_id))
+        .filter(nft_history::Column::Action.eq("TransferNft"))
+        .count(db).await? as i64;
+    let weights = token_definition::Entity::find_by_id(definition_id.to_owned())
+        .one(db).await?
+        .and_then(|d| d.rarity);
+    let collection_name = nft_file::Entity::find()
+        .filter(nft_file::Column::TokenDefId.eq(definition_id))
+        .filter(nft_file::Column::CollectionName.ne(""))
+        .one(db).await?
+        .map(|f| f.collection_name)
+        .unwrap_or_default();
+    let (rarity_counts, unranked_count) = nft_collection::Model::rarity_stats(&rarities, &weights);
+    Ok(nft_collection::Model {
+        definition_id: definition_id.to_owned(),
+        collection_name,
+        item_count,
+        burned_count,
+        owner_count,
+        rarity_counts,
+        unranked_count,
+        first_mint_at,
+        last_mint_at,
+        transfer_count,
+        updated_at: now(),
+    }.into_active_model())
+}
+
+// full recount of every collection, repairs drift in the per-batch deltas.
+pub async fn repair_collections(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let definition_ids = nft_collection::Entity::find()
+        .select_only()
+        .column(nft_collection::Column::DefinitionId)
+        .into_tuple::<String>()
+        .all(db).await?;
+    for definition_id in definition_ids {
+        let collection = recount_collection(&definition_id, db).await?;
+        nft_collection::Entity::insert(collection)
+            .on_conflict(
+                OnConflict::column(nft_collection::Column::DefinitionId)
+                    .update_columns([
+                        nft_collection::Column::CollectionName,
+                        nft_collection::Column::ItemCount,
+                        nft_collection::Column::BurnedCount,
+                        nft_collection::Column::OwnerCount,
+                        nft_collection::Column::RarityCounts,
+                        nft_collection::Column::UnrankedCount,
+                        nft_collection::Column::FirstMintAt,
+                        nft_collection::Column::LastMintAt,
+                        nft_collection::Column::TransferCount,
+                        nft_collection::Column::UpdatedAt,
+                    ])
+                    .to_owned()
+            )
+            .exec_without_returning(db).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectionDelta {
+    pub items: i64,
+    pub burned: i64,
+    pub owners: i64,
+    pub transfers: i64,
+    pub rarities: HashMap<Option<String>, i64>,
+    pub first_mint_at: Option<i64>,
+    pub last_mint_at: Option<i64>,
+}
+
+// change of each collection from the owner rows before and after a batch, read before the batch is saved.
+pub async fn collection_deltas(
+    before: &HashMap<String, nft_owner::Model>,
+    after: &[nft_owner::Model],
+    history: &[nft_history::Model],
+    db: &DatabaseConnection,
+) -> Result<HashMap<String, CollectionDelta>, DbErr> {
+    let burned = NftStatus::Burned.as_str();
+    let mut deltas: HashMap<String, CollectionDelta> = HashMap::new();
+    let mut holdings: HashMap<(String, String), i64> = HashMap::new();
+    for next in after {
+        for (o, sign) in [(before.get(&next.token_id), -1), (Some(next), 1)] {
+            let Some(o) = o else { continue };
+            let d = deltas.entry(o.definition_id.clone()).or_default();
+            if o.status == burned {
+                d.burned += sign;
+            } else {
+                d.items += sign;
+                *d.rarities.entry(o.rarity.clone()).or_default() += sign;
+                *holdings.entry((o.definition_id.clone(), o.owner.clone())).or_default() += sign;
+            }
+        }
+    }
+    for h in history {
+        let d = deltas.entry(h.definition_id.clone()).or_default();
+        match h.action.as_str() {
+            "TransferNft" => d.transfers += 1,
+            "MintNft" | "MintNftWithMemo" => {
+                d.first_mint_at = Some(d.first_mint_at.map_or(h.event_time, |t| t.min(h.event_time)));
+                d.last_mint_at = Some(d.last_mint_at.map_or(h.event_time, |t| t.max(h.event_time)));
+            }
+            _ => (),
+        }
+    }
+    holdings.retain(|_, n| *n != 0);
+    // an owner is a holder while any live nft of the collection is left
+    let mut held: HashMap<(String, String), i64> = HashMap::new();
+    let owners = holdings.keys().map(|(_, o)| o.clone()).unique().collect_vec();
+    let definition_ids = holdings.keys().map(|(d, _)| d.clone()).unique().collect_vec();
+    for chunk in owners.chunks(1000) {
+        let rows = nft_owner::Entity::find()
+            .select_only()
+            .column(nft_owner::Column::DefinitionId)
+            .column(nft_owner::Column::Owner)
+            .column_as(Expr::col(nft_owner::Column::TokenId).count(), "count")
+            .filter(nft_owner::Column::DefinitionId.is_in(definition_ids.clone()))
+            .filter(nft_owner::Column::Owner.is_in(chunk.to_vec()))
+            .filter(nft_owner::Column::Status.ne(burned))
+            .group_by(nft_owner::Column::DefinitionId)
+            .group_by(nft_owner::Column::Owner)
+            .into_tuple::<(String, String, i64)>()
+            .all(db).await?;
+        held.extend(rows.into_iter().map(|(d, o, n)| ((d, o), n)));
+    }
+    for ((definition_id, owner), n) in holdings {
+        let prev = held.get(&(definition_id.clone(), owner)).copied().unwrap_or_default();
+        let d = deltas.entry(definition_id).or_default();
+        d.owners += (prev + n > 0) as i64 - (prev > 0) as i64;
+    }
+    Ok(deltas)
+}
+
+pub async fn apply_collection_deltas<C: ConnectionTrait>(
+    deltas: HashMap<String, CollectionDelta>,
+    db: &C,
+) -> Result<(), DbErr> {
+    if deltas.is_empty() {
+        return Ok(());
+    }
+    let ids = deltas.keys().cloned().collect_vec();
+    let mut current: HashMap<String, serde_json::Value> = HashMap::new();
+    let mut weights: HashMap<String, Option<serde_json::Value>> = HashMap::new();
+    for chunk in ids.chunks(1000) {
+        for c in nft_collection::Entity::find().filter(nft_collection::Column::DefinitionId.is_in(chunk.to_vec())).all(db).await? {
+            current.insert(c.definition_id, c.rarity_counts);
+        }
+        for d in token_definition::Entity::find().filter(token_definition::Column::DefinitionId.is_in(chunk.to_vec())).all(db).await? {
+            weights.insert(d.definition_id, d.rarity);
+        }
+    }
+    let excluded = |c: nft_collection::Column| Expr::col((Alias::new("excluded"), c));
+    let col = |c: nft_collection::Column| Expr::col((nft_collection::Entity, c));
+    let add = |c: nft_collection::Column| col(c).add(excluded(c));
+    let v = deltas.into_iter().map(|(definition_id, d)| {
+        let rarities = d.rarities.into_iter().filter(|(_, n)| *n != 0).collect_vec();
+        let (_, unranked_count) = nft_collection::Model::rarity_stats(&rarities, weights.get(&definition_id).unwrap_or(&None));
+        // rarity counts are a json map, merged here rather than in sql
+        let mut merged: HashMap<Option<String>, i64> = match current.get(&definition_id) {
+            Some(serde_json::Value::Object(map)) => map.iter().map(|(k, v)| (Some(k.clone()), v.as_i64().unwrap_or_default())).collect(),
+            _ => HashMap::new(),
+        };
+        for (rarity, n) in rarities {
+            *merged.entry(Some(rarity.unwrap_or_default())).or_default() += n;
+        }
+        let merged = merged.into_iter().filter(|(_, n)| *n > 0).collect_vec();
+        let (rarity_counts, _) = nft_collection::Model::rarity_stats(&merged, &None);
+        nft_collection::Model {
+            definition_id,
+            collection_name: String::new(),
+            item_count: d.items,
+            burned_count: d.burned,
+            owner_count: d.owners,
+            rarity_counts,
+            unranked_count,
+            first_mint_at: d.first_mint_at,
+            last_mint_at: d.last_mint_at,
+            transfer_count: d.transfers,
+            updated_at: now(),
+        }.into_active_model()
+    }).collect_vec();
+    for chunk in v.chunks(500) {
+        nft_collection::Entity::insert_many(chunk.to_vec())
+            .on_conflict(
+                OnConflict::column(nft_collection::Column::DefinitionId)
+                    .value(nft_collection::Column::ItemCount, add(nft_collection::Column::ItemCount))
+                    .value(nft_collection::Column::BurnedCount, add(nft_collection::Column::BurnedCount))
+                    .value(nft_collection::Column::OwnerCount, add(nft_collection::Column::OwnerCount))
+                    .value(nft_collection::Column::UnrankedCount, add(nft_collection::Column::UnrankedCount))
+                    .value(nft_collection::Column::TransferCount, add(nft_collection::Column::TransferCount))
+                    .value(
+                        nft_collection::Column::FirstMintAt,
+                        Expr::case(
+                            col(nft_collection::Column::FirstMintAt).is_null()
+                                .or(excluded(nft_collection::Column::FirstMintAt).lt(col(nft_collection::Column::FirstMintAt))),
+                            excluded(nft_collection::Column::FirstMintAt),
+                        ).finally(col(nft_collection::Column::FirstMintAt)),
+                    )
+                    .value(
+                        nft_collection::Column::LastMintAt,
+                        Expr::case(
+                            col(nft_collection::Column::LastMintAt).is_null()
+                                .or(excluded(nft_collection::Column::LastMintAt).gt(col(nft_collection::Column::LastMintAt))),
+                            excluded(nft_collection::Column::LastMintAt),
+                        ).finally(col(nft_collection::Column::LastMintAt)),
+                    )
+                    .update_columns([nft_collection::Column::RarityCounts, nft_collection::Column::UpdatedAt])
+                    .to_owned()
+            )
+            .exec_without_returning(db).await?;
+    }
+    Ok(())
+}
+
 async fn update_nft_from_tx(
-    latest_time: i64,
+    cursor: i64,
     db: &DatabaseConnection,
 ) -> i64 {
-    let model_vec: Vec<tx_state::Model> = get_mint_nft_tx(latest_time, db).await;
+    let (upper, model_vec) = match get_nft_tx(cursor, db).await {
+        Ok(v) => v,
+        Err(err) => {
+            error!("{err}");
+            return cursor;
+        }
+    };
+    if upper == cursor {
+        return cursor;
+    }
     let mut file_map: HashMap<String, nft_file::ActiveModel> = HashMap::new();
-    let mut owner_map: HashMap<String, (String, i64)> = HashMap::new();
-    for m in model_vec.clone() {
-        let tx = match parse_from_json_str::<TransactionWithResult>(&m.json) {
-            Ok(r) => r.signed_tx.value,
+    let mut updated_file_map: HashMap<String, nft_file::ActiveModel> = HashMap::new();
+    let mut fetch_map: HashMap<String, nft_fetch::ActiveModel> = HashMap::new();
+    let mut refetch_map: HashMap<String, nft_fetch::ActiveModel> = HashMap::new();
+    let mut token_of_tx: HashMap<String, String> = HashMap::new();
+    let mut burn_vec: Vec<(String, String)> = vec![];
+    let mut transitions = vec![];
+    for (row, m) in model_vec.iter() {
+        let (signer, token) = match parse_from_json_str::<TransactionWithResult>(&m.json) {
+            Ok(r) => match r.signed_tx.value {
+                Transaction::TokenTx(t) => (r.signed_tx.sig.account, t),
+                _ => continue
+            },
             Err(e) => {
                 error!("{e}");
                 continue;
             }
         };
-        let token = match tx {
-            Transaction::TokenTx(t) => t,
-            _ => continue
-        };
-        if let TokenTx::MintNft(mint) = token.clone() {
-            let d = ApiService::get_request_until::<String, NftMetaInfo>(mint.data_url.clone(), 1).await;
-            let nft_file = nft_file::Model::from(&mint, d, mint.data_url.clone());
-            if !file_map.contains_key(&mint.token_id) {
-                file_map.insert(mint.token_id, nft_file);
+        match &token {
+            // metadata is filled in later by the fetch queue
+            TokenTx::MintNft(mint) | TokenTx::MintNftWithMemo(mint) if !file_map.contains_key(&mint.token_id) => {
+                file_map.insert(mint.token_id.clone(), nft_file::Model::from(mint, None, mint.data_url.clone()));
+                fetch_map.entry(mint.token_id.clone()).or_insert(nft_fetch::Model::pending(mint.token_id.clone(), mint.data_url.clone()));
             }
+            // txs come in chain order, the last update wins
+            TokenTx::UpdateNft(update) => {
+                updated_file_map.insert(update.token_id.clone(), nft_file::Model::from(update, None, update.data_url.clone()));
+                refetch_map.insert(update.token_id.clone(), nft_fetch::Model::pending(update.token_id.clone(), update.data_url.clone()));
+            }
+            _ => ()
         }
-        let (id, owner, et) = match token {
-            TokenTx::MintNft(t) => (t.token_id, t.output, t.created_at),
-            TokenTx::TransferNft(t) => (t.token_id, t.output, t.created_at),
-            TokenTx::DisposeEntrustedNft(t) => (t.token_id, t.output.unwrap_or(t.input), t.created_at),
-            _ => continue
-        };
-        if !owner_map.contains_key(&id) {
-            owner_map.insert(id.clone(),(owner, parse_time(&et)));
+        let Some(mut transition) = token.get_nft_transition(&signer) else { continue };
+        if let TokenTx::BurnNft(burn) = &token {
+            match resolve_burned_token(&burn.input, &token_of_tx, db).await {
+                Some(id) => {
+                    transition.token_id = id.clone();
+                    burn_vec.push((m.hash.clone(), id));
+                }
+                None => {
+                    warn!("burned nft of {} is not indexed", burn.input);
+                    continue;
+                }
+            }
+        }
+        token_of_tx.insert(m.hash.clone(), transition.token_id.clone());
+        transitions.push((m.hash.clone(), token.sub_type(), signer, (row.block_number, row.tx_index), transition));
+    }
+
+    let ids = transitions.iter().map(|(_, _, _, _, t)| t.token_id.clone()).unique().collect_vec();
+    let mut owner_map = get_nft_owners(ids, db).await;
+    let before = owner_map.clone();
+    let mut changed: HashSet<String> = HashSet::new();
+    let mut history_vec: Vec<nft_history::Model> = vec![];
+    for (hash, action, signer, (block_number, tx_index), t) in transitions {
+        let prev = owner_map.get(&t.token_id);
+        let owner = t.owner.clone()
+            .or_else(|| prev.map(|p| p.owner.clone()))
+            .unwrap_or_default();
+        let rarity = t.rarity.clone().or_else(|| prev.and_then(|p| p.rarity.clone()));
+        history_vec.push(nft_history::Model::from(&hash, action, &signer, owner.clone(), block_number, tx_index, &t));
+        // created_at is client supplied, only the chain position orders txs
+        if prev.is_some_and(|p| (p.block_number, p.tx_index) > (block_number, tx_index)) {
+            continue;
         }
+        changed.insert(t.token_id.clone());
+        owner_map.insert(t.token_id.clone(), nft_owner::Model {
+            token_id: t.token_id,
+            definition_id: t.definition_id,
+            owner,
+            status: t.status.as_str().to_owned(),
+            custodian: t.custodian,
+            rarity,
+            block_number,
+            tx_index,
+            event_time: t.event_time,
+            created_at: now(),
+        });
     }
+    let owners = owner_map.into_values()
+        .filter(|o| changed.contains(&o.token_id))
+        .collect_vec();
+    let deltas = match collection_deltas(&before, &owners, &history_vec, db).await {
+        Ok(v) => v,
+        Err(err) => {
+            error!("{err}");
+            return cursor;
+        }
+    };
+    let owner_vec = owners.into_iter().map(|o| o.into_active_model()).collect_vec();
+    let history_vec = history_vec.into_iter().map(|h| h.into_active_model()).collect_vec();
 
     let res = db.transaction::<_, (), DbErr>(|dbtx| { Box::pin(async move {
         if !file_map.is_empty() {
@@ -102,51 +483,103 @@ async fn update_nft_from_tx(
                 .map(|chunk| chunk.to_vec())
                 .collect();
             for input in values {
-                nft_file::Entity::insert_many(input)
+                let inserted = nft_file::Entity::insert_many(input)
                     .on_conflict(OnConflict::column(nft_file::Column::TokenId).do_nothing().to_owned())
-                    .do_nothing()
-                    .exec(dbtx).await?;
+                    .exec_without_returning(dbtx).await?;
+                Counter::add(dbtx, chain_counter::NFT_FILE, inserted as i64).await?;
             }
         }
-        if !owner_map.is_empty() {
-            for (id, (owner, et)) in owner_map.iter() {
-                nft_owner::Entity::insert(
-                    nft_owner::ActiveModel {
-                        token_id: Set(id.clone()),
-                        owner: Set(owner.clone()),
-                        event_time: Set(et.clone()),
-                        created_at: Set(now()),
-                    }
-                )
+        if !updated_file_map.is_empty() {
+            let values: Vec<Vec<nft_file::ActiveModel>> = updated_file_map.into_values().collect_vec()
+                .chunks(500)
+                .map(|chunk| chunk.to_vec())
+                .collect();
+            for input in values {
+                nft_file::Entity::insert_many(input)
                     .on_conflict(
-                        OnConflict::column(nft_owner::Column::TokenId)
-                            .target_and_where(Expr::col(nft_owner::Column::EventTime).lt::<i64>(et.to_owned()))
-                        .update_columns([nft_owner::Column::Owner, nft_owner::Column::EventTime]).to_owned()
+                        OnConflict::column(nft_file::Column::TokenId)
+                            .update_columns([
+                                nft_file::Column::TokenDefId,
+                                nft_file::Column::DataUrl,
+                                nft_file::Column::ContentHash,
+                                nft_file::Column::EventTime,
+                            ])
+                            .to_owned()
                     )
-                    .do_nothing()
                     .exec(dbtx).await?;
             }
         }
+        for chunk in fetch_map.into_values().collect_vec().chunks(1000) {
+            nft_fetch::Entity::insert_many(chunk.to_vec())
+                .on_conflict(OnConflict::column(nft_fetch::Column::TokenId).do_nothing().to_owned())
+                .do_nothing()
+                .exec_without_returning(dbtx).await?;
+        }
+        // an updated nft is fetched again right away from its new url
+        for chunk in refetch_map.into_values().collect_vec().chunks(1000) {
+            nft_fetch::Entity::insert_many(chunk.to_vec())
+                .on_conflict(
+                    OnConflict::column(nft_fetch::Column::TokenId)
+                        .update_columns([
+                            nft_fetch::Column::DataUrl,
+                            nft_fetch::Column::Status,
+                            nft_fetch::Column::Attempts,
+                            nft_fetch::Column::NextAttemptAt,
+                            nft_fetch::Column::UpdatedAt,
+                        ])
+                        .to_owned()
+                )
+                .exec_without_returning(dbtx).await?;
+        }
+        for chunk in owner_vec.chunks(1000) {
+            nft_owner::Entity::insert_many(chunk.to_vec())
+                .on_conflict(nft_owner::Model::on_conflict_newer())
+                .exec_without_returning(dbtx).await?;
+        }
+        for chunk in history_vec.chunks(1000) {
+            nft_history::Entity::insert_many(chunk.to_vec())
+                .on_conflict(OnConflict::column(nft_history::Column::TxHash).do_nothing().to_owned())
+                .do_nothing()
+                .exec_without_returning(dbtx).await?;
+        }
+        for (hash, id) in burn_vec {
+            nft_tx::Entity::update_many()
+                .col_expr(nft_tx::Column::TokenId, Expr::value(id))
+                .filter(nft_tx::Column::TxHash.eq(hash))
+                .exec(dbtx).await?;
+        }
+        apply_collection_deltas(deltas, dbtx).await?;
+        Cursor::set(dbtx, CURSOR, upper).await?;
         Ok(())
 
     }) }).await;
 
     if let Err(err) = res {
         error!("{err}");
+        return cursor;
     }
-    match model_vec.get(0) {
-        Some(o) => o.created_at,
-        None => latest_time
-    }
+    upper
 }
 
 pub async fn nft_loop(db: DatabaseConnection) {
     info!("nft loop start");
     tokio::spawn(async move {
-        let mut latest_time = 0;
+        let mut cursor = Cursor::get(&db, CURSOR).await.unwrap_or(0);
+        let repair_secs = var("NFT_COLLECTION_REPAIR_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60 * 60);
+        let mut last_repair = 0;
         loop {
-            latest_time = update_nft_from_tx(latest_time, &db).await;
-            sleep(Duration::from_secs(30)).await;
+            if now() - last_repair >= repair_secs {
+                if let Err(err) = repair_collections(&db).await {
+                    error!("nft collection repair fail: {err}");
+                }
+                last_repair = now();
+            }
+            let next = update_nft_from_tx(cursor, &db).await;
+            // keep going while catching up
+            if next == cursor {
+                sleep(Duration::from_secs(30)).await;
+            }
+            cursor = next;
         }
     })
     .await
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    library::common::now, nft_app::NftMetaInfo, nft_collection, nft_fetch, nft_file,
+    service::url_resolver::UrlResolver,
+};
+use futures::future::join_all;
+use itertools::Itertools;
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+
+use dotenvy::var;
+use log::{error, info, warn};
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+
+const BATCH_SIZE: u64 = 200;
+const MAX_BACKOFF_SECS: i64 = 60 * 60 * 24;
+// fetched metadata is refreshed after a week
+const STALE_SECS: i64 = 60 * 60 * 24 * 7;
+// a claimed job whose worker died comes due again after this
+const LEASE_SECS: i64 = 60 * 10;
+
+fn host_concurrency() -> usize {
+    var("NFT_FETCH_HOST_CONCURRENCY").ok().and_then(|v| v.parse().ok()).unwrap_or(2)
+}
+
+fn max_attempts() -> i32 {
+    var("NFT_FETCH_MAX_ATTEMPTS").ok().and_then(|v| v.parse().ok()).unwrap_or(10)
+}
+
+// 1m, 2m, 4m ... capped at a day.
+pub fn backoff_secs(attempts: i32) -> i64 {
+    (60_i64 << attempts.clamp(0, 20)).min(MAX_BACKOFF_SECS)
+}
+
+// nft_file rows written empty before the queue existed.
+pub async fn enqueue_missing(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let missing = nft_file::Entity::find()
+        .filter(nft_file::Column::NftName.eq(""))
+        .filter(
+            nft_file::Column::TokenId.not_in_subquery(
+                Query::select()
+                    .column(nft_fetch::Column::TokenId)
+                    .from(nft_fetch::Entity)
+                    .to_owned(),
+            ),
+        )
+        .limit(1000)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|f| nft_fetch::Model::pending(f.token_id, f.data_url))
+        .collect_vec();
+    if !missing.is_empty() {
+        nft_fetch::Entity::insert_many(missing)
+            .on_conflict(
+                sea_query::OnConflict::column(nft_fetch::Column::TokenId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec_without_returning(db)
+            .await?;
+    }
+    Ok(())
+}
+
+// due jobs are leased as running so an overlapping pass doesn't fetch them twice.
+pub async fn claim(db: &DatabaseConnection, now: i64, limit: u64) -> Result<Vec<nft_fetch::Model>, DbErr> {
+    let txn = db.begin().await?;
+    let jobs = nft_fetch::Entity::find()
+        .filter(nft_fetch::Column::NextAttemptAt.lte(now))
+        .filter(nft_fetch::Column::Status.ne("gave_up"))
+        .order_by_asc(nft_fetch::Column::NextAttemptAt)
+        .limit(limit)
+        .all(&txn)
+        .await?;
+    if !jobs.is_empty() {
+        nft_fetch::Entity::update_many()
+            .col_expr(nft_fetch::Column::Status, Expr::value("running"))
+            .col_expr(nft_fetch::Column::NextAttemptAt, Expr::value(now + LEASE_SECS))
+            .col_expr(nft_fetch::Column::UpdatedAt, Expr::value(now))
+            .filter(nft_fetch::Column::TokenId.is_in(jobs.iter().map(|j| j.token_id.clone())))
+            .exec(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(jobs)
+}
+
+pub async fn complete(
+    db: &DatabaseConnection,
+    job: &nft_fetch::Model,
+    res: Result<NftMetaInfo, String>,
+    now: i64,
+) -> Result<(), DbErr> {
+    let next = match res {
+        Ok(info) => {
+            let collection_name = info.collection_name.clone();
+            nft_file::Entity::update_many()
+                .set(nft_file::Model::metadata(info))
+                .filter(nft_file::Column::TokenId.eq(job.token_id.clone()))
+                .exec(db)
+                .await?;
+            // collections are created before any metadata arrives
+            if !collection_name.is_empty() {
+                nft_collection::Entity::update_many()
+                    .col_expr(nft_collection::Column::CollectionName, Expr::value(collection_name))
+                    .filter(nft_collection::Column::CollectionName.eq(""))
+                    .filter(
+                        nft_collection::Column::DefinitionId.in_subquery(
+                            Query::select()
+                                .column(nft_file::Column::TokenDefId)
+                                .from(nft_file::Entity)
+                                .and_where(nft_file::Column::TokenId.eq(job.token_id.clone()))
+                                .to_owned(),
+                        ),
+                    )
+                    .exec(db)
+                    .await?;
+            }
+            nft_fetch::ActiveModel {
+                status: Set("done".to_string()),
+                attempts: Set(0),
+                last_error: Set(None),
+                next_attempt_at: Set(now + STALE_SECS),
+                fetched_at: Set(Some(now)),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+        }
+        Err(err) => {
+            let attempts = job.attempts + 1;
+            let gave_up = attempts >= max_attempts();
+            warn!("nft {} metadata fetch fail ({attempts} attempts): {err}", job.token_id);
+            if gave_up {
+                warn!("nft {} metadata fetch gave up", job.token_id);
+            }
+            nft_fetch::ActiveModel {
+                // only a new url from UpdateNft queues a job that gave up again
+                status: Set(if gave_up { "gave_up" } else { "failed" }.to_string()),
+                attempts: Set(attempts),
+                last_error: Set(Some(err)),
+                next_attempt_at: Set(now + backoff_secs(job.attempts)),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+        }
+    };
+    // an UpdateNft may have queued a new url meanwhile, leave that job alone
+    nft_fetch::Entity::update_many()
+        .set(next)
+        .filter(nft_fetch::Column::TokenId.eq(job.token_id.clone()))
+        .filter(nft_fetch::Column::DataUrl.eq(job.data_url.clone()))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+async fn run_job(db: &DatabaseConnection, job: nft_fetch::Model, host: Arc<Semaphore>) {
+    let Ok(_permit) = host.acquire_owned().await else { return };
+    let res = UrlResolver::global().fetch_json::<NftMetaInfo>(&job.data_url).await;
+    if let Err(err) = complete(db, &job, res, now()).await {
+        error!("nft fetch {} update fail: {err}", job.token_id);
+    }
+}
+
+async fn fetch_due(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    enqueue_missing(db).await?;
+    let jobs = claim(db, now(), BATCH_SIZE).await?;
+    let count = jobs.len();
+    let limit = host_concurrency();
+    let mut hosts: HashMap<String, Arc<Semaphore>> = HashMap::new();
+    let futures = jobs
+        .into_iter()
+        .map(|job| {
+            let host = UrlResolver::global().host(&job.data_url);
+            let permit = hosts.entry(host).or_insert_with(|| Arc::new(Semaphore::new(limit))).clone();
+            run_job(db, job, permit)
+        })
+        .collect_vec();
+    join_all(futures).await;
+    Ok(count)
+}
+
+pub async fn nft_fetch_loop(db: DatabaseConnection) {
+    info!("nft fetch loop start");
+    tokio::spawn(async move {
+        loop {
+            match fetch_due(&db).await {
+                // keep draining while a full batch was due
+                Ok(count) if count as u64 == BATCH_SIZE => continue,
+                Ok(_) => (),
+                Err(err) => error!("nft fetch err: {err}"),
+            }
+            sleep(Duration::from_secs(10)).await;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{
+    library::common::now, nft_app::NftMetaInfo, nft_file, nft_verification,
+    service::url_resolver::UrlResolver,
+};
+use itertools::Itertools;
+use sea_orm::sea_query::{OnConflict, Query};
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+use sha2::{Digest, Sha256};
+
+use dotenvy::var;
+use log::{error, info, warn};
+use tokio::time::sleep;
+
+const BATCH_SIZE: u64 = 50;
+// dead or tampered nfts may be fixed upstream, everything is checked again daily
+const RECHECK_SECS: i64 = 60 * 60 * 24;
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+// on-chain hashes come with or without 0x and in either case.
+pub fn hash_matches(expected: &str, actual: &str) -> bool {
+    let expected = expected.trim();
+    let expected = expected.strip_prefix("0x").unwrap_or(expected);
+    !expected.is_empty() && expected.eq_ignore_ascii_case(actual)
+}
+
+// content addressed, <dir>/ab/abcdef...
+pub fn cache_path(dir: &Path, hash: &str) -> PathBuf {
+    dir.join(&hash[..2.min(hash.len())]).join(hash)
+}
+
+async fn cache_media(dir: &Path, hash: &str, bytes: &[u8]) -> Result<String, String> {
+    let path = cache_path(dir, hash);
+    if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+        }
+        // written aside and renamed so a crash never leaves a partial file under its hash
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bytes).await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&tmp, &path).await.map_err(|e| e.to_string())?;
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+pub async fn verify(resolver: &UrlResolver, file: &nft_file::Model, cache_dir: Option<&Path>) -> nft_verification::ActiveModel {
+    let mut am = nft_verification::ActiveModel {
+        token_id: Set(file.token_id.clone()),
+        checked_at: Set(Some(now())),
+        next_check_at: Set(now() + RECHECK_SECS),
+        ..Default::default()
+    };
+    let metadata = match resolver.fetch(&file.data_url).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            am.status = Set("dead".to_string());
+            am.last_error = Set(Some(err));
+            return am;
+        }
+    };
+    let metadata_hash = sha256_hex(&metadata);
+    let content_hash_match = hash_matches(&file.content_hash, &metadata_hash);
+    am.metadata_hash = Set(Some(metadata_hash));
+    am.content_hash_match = Set(Some(content_hash_match));
+
+    let info = match serde_json::from_slice::<NftMetaInfo>(&metadata) {
+        Ok(info) => info,
+        Err(err) => {
+            am.status = Set("invalid".to_string());
+            am.last_error = Set(Some(err.to_string()));
+            return am;
+        }
+    };
+    let media = match resolver.fetch(&info.nft_uri).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            am.status = Set("dead".to_string());
+            am.last_error = Set(Some(err));
+            return am;
+        }
+    };
+    let media_hash = sha256_hex(&media);
+    let checksum_match = hash_matches(&info.nft_checksum, &media_hash);
+    am.media_size = Set(Some(media.len() as i64));
+    am.checksum_match = Set(Some(checksum_match));
+    am.last_error = Set(None);
+    if let Some(dir) = cache_dir {
+        match cache_media(dir, &media_hash, &media).await {
+            Ok(path) => am.media_path = Set(Some(path)),
+            Err(err) => warn!("nft {} media cache fail: {err}", file.token_id),
+        }
+    }
+    am.media_hash = Set(Some(media_hash));
+    am.status = Set(if content_hash_match && checksum_match { "verified" } else { "tampered" }.to_string());
+    am
+}
+
+// nfts whose metadata was fetched but never checked.
+async fn enqueue_new(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let v = nft_file::Entity::find()
+        .filter(nft_file::Column::NftUri.ne(""))
+        .filter(
+            nft_file::Column::TokenId.not_in_subquery(
+                Query::select()
+                    .column(nft_verification::Column::TokenId)
+                    .from(nft_verification::Entity)
+                    .to_owned(),
+            ),
+        )
+        .limit(1000)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|f| nft_verification::Model::pending(f.token_id))
+        .collect_vec();
+    if !v.is_empty() {
+        nft_verification::Entity::insert_many(v)
+            .on_conflict(
+                OnConflict::column(nft_verification::Column::TokenId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec_without_returning(db)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn verify_due(db: &DatabaseConnection, cache_dir: Option<&Path>) -> Result<(), DbErr> {
+    enqueue_new(db).await?;
+    let ids = nft_verification::Entity::find()
+        .filter(nft_verification::Column::NextCheckAt.lte(now()))
+        .order_by_asc(nft_verification::Column::NextCheckAt)
+        .limit(BATCH_SIZE)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|v| v.token_id)
+        .collect_vec();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let files = nft_file::Entity::find()
+        .filter(nft_file::Column::TokenId.is_in(ids))
+        .all(db)
+        .await?;
+    for file in files {
+        let am = verify(UrlResolver::global(), &file, cache_dir).await;
+        nft_verification::Entity::update_many()
+            .set(am)
+            .filter(nft_verification::Column::TokenId.eq(file.token_id.clone()))
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn nft_verify_loop(db: DatabaseConnection) {
+    if !var("NFT_VERIFY").is_ok_and(|v| v == "true") {
+        info!("nft verify loop disabled");
+        return;
+    }
+    let cache_dir = var("NFT_MEDIA_CACHE_DIR").ok().filter(|d| !d.is_empty()).map(PathBuf::from);
+    info!("nft verify loop start, media cache {:?}", cache_dir);
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = verify_due(&db, cache_dir.as_deref()).await {
+                error!("nft verify err: {err}");
+            }
+            sleep(Duration::from_secs(30)).await;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bigdecimal::BigDecimal;
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+
+use dotenvy::var;
+use log::{error, info, warn};
+use tokio::time::sleep;
+
+use crate::{
+    library::common::{bucket, now, DAY, HOUR, MINUTE},
+    price_backfill, price_candle,
+    service::price_service::{PriceQuote, PriceService},
+};
+
+const BACKFILL_CURSOR: &str = "price_backfill";
+// history windows stay under 90 days so the vendor returns hourly points
+const BACKFILL_WINDOW_SECS: i64 = 60 * 60 * 24 * 89;
+
+// one candle per interval bucket touched by the points.
+pub fn build_candles(points: &[(i64, BigDecimal)], intervals: &[(&str, i64)], source: &str, now: i64) -> Vec<price_candle::Model> {
+    let mut points = points.to_vec();
+    points.sort_by_key(|(t, _)| *t);
+    let mut candles = BTreeMap::<(String, i64), price_candle::Model>::new();
+    for (t, price) in points {
+        for (name, secs) in intervals {
+            let open_time = bucket(t, *secs);
+            candles
+                .entry((name.to_string(), open_time))
+                .and_modify(|c| {
+                    c.high = c.high.clone().max(price.clone());
+                    c.low = c.low.clone().min(price.clone());
+                    c.close = price.clone();
+                    c.close_time = t;
+                    c.sample_count += 1;
+                })
+                .or_insert_with(|| price_candle::Model {
+                    interval: name.to_string(),
+                    open_time,
+                    open: price.clone(),
+                    high: price.clone(),
+                    low: price.clone(),
+                    close: price.clone(),
+                    close_time: t,
+                    sample_count: 1,
+                    source: source.to_string(),
+                    updated_at: now,
+                });
+        }
+    }
+    candles.into_values().collect()
+}
+
+pub async fn save_candles<C: ConnectionTrait>(db: &C, candles: Vec<price_candle::Model>) -> Result<(), DbErr> {
+    if candles.is_empty() {
+        return Ok(());
+    }
+    price_candle::Entity::insert_many(candles.into_iter().map(|c| c.into_active()))
+        .on_conflict(price_candle::Model::on_conflict_merge())
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+// close of the finest candle holding ts, for fiat valuation of past txs.
+pub async fn price_at<C: ConnectionTrait>(db: &C, ts: i64) -> Result<Option<BigDecimal>, DbErr> {
+    for (name, secs) in [MINUTE, HOUR, DAY] {
+        let candle = price_candle::Entity::find_by_id((name.to_string(), bucket(ts, secs)))
+            .one(db)
+            .await?;
+        if let Some(c) = candle {
+            return Ok(Some(c.close));
+        }
+    }
+    Ok(None)
+}
+
+// a vendor serves the same quote until it updates, re-polling it is not a new sample.
+pub async fn is_new_sample<C: ConnectionTrait>(db: &C, q: &PriceQuote) -> Result<bool, DbErr> {
+    let (name, secs) = MINUTE;
+    let candle = price_candle::Entity::find_by_id((name.to_string(), bucket(q.updated_at, secs)))
+        .one(db)
+        .await?;
+    Ok(candle.is_none_or(|c| c.close_time < q.updated_at))
+}
+
+async fn record_quote(db: &DatabaseConnection, prices: &PriceService) {
+    let Some(q) = prices.quote().await else {
+        warn!("no fresh price for candles");
+        return;
+    };
+    match is_new_sample(db, &q).await {
+        Ok(true) => (),
+        Ok(false) => return,
+        Err(err) => {
+            error!("price candle read fail: {err}");
+            return;
+        }
+    }
+    let candles = build_candles(&[(q.updated_at, q.price)], &[MINUTE, HOUR, DAY], &q.source, now());
+    if let Err(err) = save_candles(db, candles).await {
+        error!("price candle save fail: {err}");
+    }
+}
+
+// walks the vendor history from PRICE_BACKFILL_FROM up to now, resuming from the cursor.
+async fn backfill(db: &DatabaseConnection, prices: &PriceService) -> Result<(), DbErr> {
+    let Some(start) = var("PRICE_BACKFILL_FROM").ok().and_then(|v| v.parse::<i64>().ok()) else {
+        return Ok(());
+    };
+    let done = price_backfill::Model::get(db, BACKFILL_CURSOR).await?;
+    let mut from = done.unwrap_or(start).max(start);
+    while from < now() {
+        let to = (from + BACKFILL_WINDOW_SECS).min(now());
+        let mut points = None;
+        for p in prices.providers.iter() {
+            match p.history(from, to).await {
+                Ok(res) => {
+                    points = Some((p.name(), res));
+                    break;
+                }
+                Err(err) => warn!("{} price history fail: {err}", p.name()),
+            }
+        }
+        let Some((source, points)) = points else {
+            error!("price backfill stopped at {from}, no provider with history");
+            return Ok(());
+        };
+        let candles = build_candles(&points, &[HOUR, DAY], &source, now());
+        let txn = db.begin().await?;
+        save_candles(&txn, candles).await?;
+        price_backfill::Model::set(&txn, BACKFILL_CURSOR, to).await?;
+        txn.commit().await?;
+        info!("price backfill {from} ~ {to}: {} points", points.len());
+        from = to;
+        sleep(Duration::from_secs(2)).await;
+    }
+    Ok(())
+}
+
+pub async fn price_loop(db: DatabaseConnection, prices: Arc<PriceService>) {
+    let secs = var("PRICE_CANDLE_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300);
+    tokio::spawn(async move {
+        if let Err(err) = backfill(&db, &prices).await {
+            error!("price backfill fail: {err}");
+        }
+        loop {
+            record_quote(&db, &prices).await;
+            sleep(Duration::from_secs(secs)).await;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
@@ -44,6 +44,16 @@ impl ApiService {
         }).await
     }
 
+    pub async fn post_json<B: serde::Serialize, S: serde::de::DeserializeOwned + Debug>(
+        url: Url,
+        body: &B,
+    ) -> Result<S, String> {
+        CLIENT.post(url.as_str()).json(body).send().and_then(|res| res.json()).map_err(|e| {
+            error!("{}", url);
+            e.to_string()
+        }).await
+    }
+
     pub async fn get_request_until<T: reqwest::IntoUrl, S: serde::de::DeserializeOwned + Debug>(
         url: T,
         count: u8,
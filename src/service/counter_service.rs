@@ -0,0 +1,85 @@
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
+use sea_orm::*;
+
+use crate::{chain_counter, library::common::now};
+
+pub struct Counter;
+
+impl Counter {
+    pub async fn get<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<i64>, DbErr> {
+        Ok(chain_counter::Entity::find_by_id(name.to_owned())
+            .one(db)
+            .await?
+            .map(|c| c.value))
+    }
+
+    // run in the transaction that inserted the rows
+    pub async fn add<C: ConnectionTrait>(db: &C, name: &str, delta: i64) -> Result<(), DbErr> {
+        if delta == 0 {
+            return Ok(());
+        }
+        chain_counter::Entity::insert(chain_counter::ActiveModel {
+            name: Set(name.to_owned()),
+            value: Set(delta),
+            updated_at: Set(now()),
+        })
+        .on_conflict(
+            OnConflict::column(chain_counter::Column::Name)
+                .value(
+                    chain_counter::Column::Value,
+                    Expr::col((chain_counter::Entity, chain_counter::Column::Value))
+                        .add(Expr::col((Alias::new("excluded"), chain_counter::Column::Value))),
+                )
+                .update_column(chain_counter::Column::UpdatedAt)
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set<C: ConnectionTrait>(db: &C, name: &str, value: i64) -> Result<(), DbErr> {
+        chain_counter::Entity::insert(chain_counter::ActiveModel {
+            name: Set(name.to_owned()),
+            value: Set(value),
+            updated_at: Set(now()),
+        })
+        .on_conflict(
+            OnConflict::column(chain_counter::Column::Name)
+                .update_columns([chain_counter::Column::Value, chain_counter::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    // the counter row is locked before counting, an add that commits meanwhile is either counted or applied after.
+    pub async fn recount<E: EntityTrait>(db: &DatabaseConnection, name: &str) -> Result<(Option<i64>, i64), DbErr>
+    where
+        E::Model: Sync,
+    {
+        let txn = db.begin().await?;
+        let stored = chain_counter::Entity::find_by_id(name.to_owned())
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .map(|c| c.value);
+        if stored.is_none() {
+            chain_counter::Entity::insert(chain_counter::ActiveModel {
+                name: Set(name.to_owned()),
+                value: Set(0),
+                updated_at: Set(now()),
+            })
+            .on_conflict(OnConflict::column(chain_counter::Column::Name).do_nothing().to_owned())
+            .do_nothing()
+            .exec(&txn)
+            .await?;
+            chain_counter::Entity::find_by_id(name.to_owned()).lock_exclusive().one(&txn).await?;
+        }
+        let count = E::find().count(&txn).await? as i64;
+        Self::set(&txn, name, count).await?;
+        txn.commit().await?;
+        Ok((stored, count))
+    }
+}
@@ -0,0 +1,83 @@
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+
+use crate::{agent_cursor, block_state, library::common::now};
+
+pub struct Cursor;
+
+impl Cursor {
+    pub async fn get<C: ConnectionTrait>(db: &C, name: &str) -> Result<i64, DbErr> {
+        Ok(agent_cursor::Entity::find_by_id(name.to_owned())
+            .one(db)
+            .await?
+            .map_or(0, |c| c.block_number))
+    }
+
+    pub async fn set<C: ConnectionTrait>(db: &C, name: &str, block_number: i64) -> Result<(), DbErr> {
+        agent_cursor::Entity::insert(agent_cursor::ActiveModel {
+            name: Set(name.to_owned()),
+            block_number: Set(block_number),
+            updated_at: Set(now()),
+        })
+        .on_conflict(
+            OnConflict::column(agent_cursor::Column::Name)
+                .update_columns([agent_cursor::Column::BlockNumber, agent_cursor::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    // blocks are built concurrently and out of order, only a gapless run above the cursor is final.
+    pub async fn built_height<C: ConnectionTrait>(db: &C, cursor: i64) -> Result<i64, DbErr> {
+        let numbers = block_state::Entity::find()
+            .select_only()
+            .column(block_state::Column::Number)
+            .filter(block_state::Column::IsBuild.eq(true))
+            .filter(block_state::Column::Number.gt(cursor))
+            .order_by_asc(block_state::Column::Number)
+            .limit(10000)
+            .into_tuple::<i64>()
+            .all(db)
+            .await?;
+        Ok(Self::contiguous_height(cursor, &numbers))
+    }
+
+    // unbuilt blocks below the highest built one hold every cursor back until they are rebuilt.
+    pub async fn unbuilt_below_head<C: ConnectionTrait>(
+        db: &C,
+        created_before: i64,
+        limit: u64,
+    ) -> Result<Vec<block_state::Model>, DbErr> {
+        let head = block_state::Entity::find()
+            .select_only()
+            .column_as(block_state::Column::Number.max(), "head")
+            .filter(block_state::Column::IsBuild.eq(true))
+            .into_tuple::<Option<i64>>()
+            .one(db)
+            .await?
+            .flatten();
+        let Some(head) = head else { return Ok(vec![]) };
+        block_state::Entity::find()
+            .filter(block_state::Column::IsBuild.eq(false))
+            .filter(block_state::Column::Number.lt(head))
+            .filter(block_state::Column::CreatedAt.lte(created_before))
+            .order_by_asc(block_state::Column::Number)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    pub fn contiguous_height(cursor: i64, numbers: &[i64]) -> i64 {
+        let mut height = cursor;
+        for &n in numbers {
+            if n == height + 1 {
+                height = n;
+            } else if n > height + 1 {
+                break;
+            }
+        }
+        height
+    }
+}
@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Zero};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use dotenvy::var;
+use log::error;
+
+use super::api_service::ApiService;
+use crate::custody_balance;
+
+// balanceOf(address)
+const BALANCE_OF: &str = "0x70a08231";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BalanceSource {
+    // etherscan compatible tokenbalance endpoint
+    Etherscan { url: String, api_key: String, contract: String },
+    // eth_call balanceOf against a json-rpc node
+    Rpc { url: String, contract: String },
+    Static { balance: BigDecimal },
+}
+
+// an external address holding LM outside the chain, balances are raw token units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustodyAddress {
+    pub name: String,
+    pub chain: String,
+    pub address: String,
+    pub source: BalanceSource,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenBalance {
+    status: String,
+    message: String,
+    result: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<String>,
+    error: Option<serde_json::Value>,
+}
+
+pub fn parse_hex(hex: &str) -> Result<BigDecimal, String> {
+    let hex = hex.trim_start_matches("0x");
+    hex.chars().try_fold(BigDecimal::zero(), |acc, c| {
+        c.to_digit(16)
+            .map(|d| acc * BigDecimal::from(16) + BigDecimal::from(d))
+            .ok_or(format!("invalid hex '{hex}'"))
+    })
+}
+
+pub fn balance_of_data(address: &str) -> String {
+    format!("{BALANCE_OF}{:0>64}", address.trim_start_matches("0x").to_lowercase())
+}
+
+impl CustodyAddress {
+    pub async fn balance(&self) -> Result<BigDecimal, String> {
+        match &self.source {
+            BalanceSource::Etherscan { url, api_key, contract } => {
+                let mut url = Url::parse(url).map_err(|e| e.to_string())?;
+                url.query_pairs_mut()
+                    .append_pair("module", "account")
+                    .append_pair("action", "tokenbalance")
+                    .append_pair("contractaddress", contract)
+                    .append_pair("address", &self.address)
+                    .append_pair("tag", "latest")
+                    .append_pair("apikey", api_key);
+                let tb = ApiService::get_request::<TokenBalance>(url).await?;
+                if tb.status != "1" {
+                    return Err(format!("{}: {}", tb.message, tb.result));
+                }
+                BigDecimal::from_str(&tb.result).map_err(|e| e.to_string())
+            }
+            BalanceSource::Rpc { url, contract } => {
+                let url = Url::parse(url).map_err(|e| e.to_string())?;
+                let body = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "eth_call",
+                    "params": [{"to": contract, "data": balance_of_data(&self.address)}, "latest"],
+                });
+                match ApiService::post_json::<_, RpcResponse>(url, &body).await? {
+                    RpcResponse { result: Some(hex), .. } => parse_hex(&hex),
+                    RpcResponse { error, .. } => Err(format!("eth_call fail: {error:?}")),
+                }
+            }
+            BalanceSource::Static { balance } => Ok(balance.clone()),
+        }
+    }
+
+    pub fn source_name(&self) -> &str {
+        match self.source {
+            BalanceSource::Etherscan { .. } => "etherscan",
+            BalanceSource::Rpc { .. } => "rpc",
+            BalanceSource::Static { .. } => "static",
+        }
+    }
+
+    // a failed fetch keeps the previous balance so one source can't zero the total.
+    pub fn record(
+        &self,
+        res: Result<BigDecimal, String>,
+        prev: Option<custody_balance::Model>,
+        block_number: i64,
+        now: i64,
+    ) -> custody_balance::Model {
+        let (balance, last_error, fetched_at) = match (res, prev) {
+            (Ok(balance), _) => (balance, None, now),
+            (Err(err), Some(p)) => (p.balance, Some(err), p.fetched_at),
+            (Err(err), None) => (BigDecimal::zero(), Some(err), 0),
+        };
+        custody_balance::Model {
+            chain: self.chain.to_lowercase(),
+            address: self.address.to_lowercase(),
+            name: self.name.clone(),
+            source: self.source_name().to_string(),
+            balance,
+            block_number,
+            last_error,
+            fetched_at,
+            updated_at: now,
+        }
+    }
+}
+
+// CUSTODY_SOURCES_FILE holds a json list of custody addresses,
+// without it the legacy LM_ADDR/BAL_ADDR etherscan setup is used.
+pub fn load_custody() -> Vec<CustodyAddress> {
+    if let Ok(path) = var("CUSTODY_SOURCES_FILE").map(|p| p.trim().to_string()) {
+        if !path.is_empty() {
+            return std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+                .unwrap_or_else(|err| {
+                    error!("custody sources {path} load fail: {err}");
+                    vec![]
+                });
+        }
+    }
+    let (Ok(api_key), Ok(lm), Ok(addrs)) = (var("SCAN_API_KEY"), var("LM_ADDR"), var("BAL_ADDR")) else {
+        return vec![];
+    };
+    addrs
+        .split(',')
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|addr| CustodyAddress {
+            name: addr.to_string(),
+            chain: "ethereum".to_string(),
+            address: addr.to_string(),
+            source: BalanceSource::Etherscan {
+                url: "https://api.etherscan.io/api".to_string(),
+                api_key: api_key.clone(),
+                contract: lm.clone(),
+            },
+        })
+        .collect()
+}
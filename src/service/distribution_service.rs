@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use itertools::Itertools;
+use sea_orm::sea_query::{OnConflict, Query};
+use sea_orm::*;
+
+use dotenvy::var;
+use log::error;
+
+use crate::{
+    address_label, balance_change, balance_entity, holder_rank,
+    library::common::{now, parse_from_json_str},
+    nft_owner, token_definition, token_distribution,
+    transaction::{reward_transaction::RewardTx, token_transaction::NftStatus, Transaction, TransactionWithResult},
+    tx_entity, tx_state,
+};
+
+const DEFAULT_RICH_LIST_SIZE: usize = 100;
+
+// balances are sorted largest first
+pub fn gini(sorted_desc: &[BigDecimal]) -> f64 {
+    let n = sorted_desc.len();
+    let total: BigDecimal = sorted_desc.iter().sum();
+    if n == 0 || total.is_zero() {
+        return 0.0;
+    }
+    // ascending rank i (1..n) weighted sum
+    let weighted: BigDecimal = sorted_desc
+        .iter()
+        .enumerate()
+        .map(|(i, v)| v * BigDecimal::from((n - i) as i64))
+        .sum();
+    let g = (weighted * BigDecimal::from(2)) / (total * BigDecimal::from(n as i64));
+    g.to_f64().unwrap_or(0.0) - (n as f64 + 1.0) / n as f64
+}
+
+pub fn nakamoto(sorted_desc: &[BigDecimal]) -> i64 {
+    let total: BigDecimal = sorted_desc.iter().sum();
+    let half = total / BigDecimal::from(2);
+    let mut acc = BigDecimal::zero();
+    for (i, v) in sorted_desc.iter().enumerate() {
+        acc += v;
+        if acc > half {
+            return i as i64 + 1;
+        }
+    }
+    0
+}
+
+pub fn top_share(sorted_desc: &[BigDecimal], n: usize) -> f64 {
+    let total: BigDecimal = sorted_desc.iter().sum();
+    if total.is_zero() {
+        return 0.0;
+    }
+    let top: BigDecimal = sorted_desc.iter().take(n).sum();
+    (top / total).to_f64().unwrap_or(0.0)
+}
+
+// ADDRESS_LABELS_FILE holds a json list of address_label rows.
+pub fn config_labels() -> Vec<address_label::Model> {
+    let Some(path) = var("ADDRESS_LABELS_FILE").ok().filter(|p| !p.trim().is_empty()) else {
+        return vec![];
+    };
+    match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str::<Vec<address_label::Model>>(&s).map_err(|e| e.to_string()))
+    {
+        Ok(v) => v
+            .into_iter()
+            .map(|l| address_label::Model { source: "config".to_string(), updated_at: now(), ..l })
+            .collect(),
+        Err(err) => {
+            error!("address labels {path} load fail: {err}");
+            vec![]
+        }
+    }
+}
+
+pub fn dao_label(tx: &TransactionWithResult) -> Option<address_label::Model> {
+    match &tx.signed_tx.value {
+        Transaction::RewardTx(RewardTx::RegisterDao(t)) => Some(address_label::Model {
+            address: t.dao_account_name.clone(),
+            label: format!("DAO {}", t.group_id),
+            category: "dao".to_string(),
+            exclude_from_supply: true,
+            source: "dao".to_string(),
+            updated_at: now(),
+        }),
+        _ => None,
+    }
+}
+
+async fn save_labels(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let hashes = tx_entity::Entity::find()
+        .select_only()
+        .column(tx_entity::Column::Hash)
+        .filter(tx_entity::Column::SubType.eq("RegisterDao"))
+        .into_tuple::<String>()
+        .all(db)
+        .await?;
+    let mut dao = vec![];
+    for chunk in hashes.chunks(1000) {
+        for state in tx_state::Entity::find().filter(tx_state::Column::Hash.is_in(chunk.to_vec())).all(db).await? {
+            if let Some(label) = parse_from_json_str::<TransactionWithResult>(&state.json).ok().as_ref().and_then(dao_label) {
+                dao.push(label);
+            }
+        }
+    }
+    let all_cols = [
+        address_label::Column::Label,
+        address_label::Column::Category,
+        address_label::Column::ExcludeFromSupply,
+        address_label::Column::Source,
+        address_label::Column::UpdatedAt,
+    ];
+    // configured labels win over the ones derived from dao registration
+    for (labels, on_conflict) in [
+        (dao, OnConflict::column(address_label::Column::Address).do_nothing().to_owned()),
+        (config_labels(), OnConflict::column(address_label::Column::Address).update_columns(all_cols).to_owned()),
+    ] {
+        let labels = labels.into_iter().unique_by(|l| l.address.clone()).collect_vec();
+        for chunk in labels.chunks(1000) {
+            address_label::Entity::insert_many(chunk.iter().cloned().map(|l| address_label::ActiveModel::from(l).reset_all()))
+                .on_conflict(on_conflict.clone())
+                .exec_without_returning(db)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+pub fn rich_list(
+    token: &str,
+    holders: &[(String, BigDecimal, BigDecimal)],
+    labels: &HashMap<String, address_label::Model>,
+    size: usize,
+    now: i64,
+) -> (Vec<holder_rank::Model>, token_distribution::Model) {
+    let mut ranked = holders
+        .iter()
+        .map(|(address, free, locked)| (address, free, locked, free + locked))
+        .filter(|(.., total)| total > &BigDecimal::zero())
+        .collect_vec();
+    ranked.sort_by(|a, b| b.3.cmp(&a.3).then_with(|| a.0.cmp(b.0)));
+    let total: BigDecimal = ranked.iter().map(|r| &r.3).sum();
+    let excluded = |a: &String| labels.get(a).is_some_and(|l| l.exclude_from_supply);
+    let counted = ranked.iter().filter(|r| !excluded(r.0)).map(|r| r.3.clone()).collect_vec();
+    let rows = ranked
+        .iter()
+        .take(size)
+        .enumerate()
+        .map(|(i, (address, free, locked, sum))| holder_rank::Model {
+            token: token.to_string(),
+            rank: i as i32 + 1,
+            address: address.to_string(),
+            free: (*free).clone(),
+            locked: (*locked).clone(),
+            total: sum.clone(),
+            share: if total.is_zero() { BigDecimal::zero() } else { (sum / &total).with_prec(18) },
+            label: labels.get(*address).map(|l| l.label.clone()),
+            updated_at: now,
+        })
+        .collect_vec();
+    let dist = token_distribution::Model {
+        token: token.to_string(),
+        holder_count: ranked.len() as i64,
+        excluded_balance: ranked.iter().filter(|r| excluded(r.0)).map(|r| &r.3).sum(),
+        total_balance: total,
+        top10_share: top_share(&counted, 10),
+        top100_share: top_share(&counted, 100),
+        gini: gini(&counted),
+        nakamoto: nakamoto(&counted),
+        updated_at: now,
+    };
+    (rows, dist)
+}
+
+// (token, [(address, free, locked)]), LM from the balance table, other fungible tokens summed
+// from the balance ledger and nfts as the number of live tokens each owner holds.
+pub async fn token_holders(db: &DatabaseConnection) -> Result<Vec<(String, Vec<(String, BigDecimal, BigDecimal)>)>, DbErr> {
+    let lm = balance_entity::Entity::find()
+        .select_only()
+        .columns([balance_entity::Column::Address, balance_entity::Column::Free, balance_entity::Column::Locked])
+        .into_tuple::<(String, BigDecimal, BigDecimal)>()
+        .all(db)
+        .await?;
+    let fungible: Vec<(String, String, Option<BigDecimal>, Option<BigDecimal>)> = balance_change::Entity::find()
+        .select_only()
+        .column(balance_change::Column::Token)
+        .column(balance_change::Column::Address)
+        .column_as(balance_change::Column::Free.sum(), "free")
+        .column_as(balance_change::Column::Locked.sum(), "locked")
+        .filter(balance_change::Column::Token.ne("LM"))
+        .filter(
+            balance_change::Column::Token.not_in_subquery(
+                Query::select()
+                    .column(token_definition::Column::DefinitionId)
+                    .from(token_definition::Entity)
+                    .and_where(token_definition::Column::IsNft.eq(true))
+                    .to_owned(),
+            ),
+        )
+        .group_by(balance_change::Column::Token)
+        .group_by(balance_change::Column::Address)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let nft: Vec<(String, String, i64)> = nft_owner::Entity::find()
+        .select_only()
+        .column(nft_owner::Column::DefinitionId)
+        .column(nft_owner::Column::Owner)
+        .column_as(nft_owner::Column::TokenId.count(), "items")
+        .filter(nft_owner::Column::Status.ne(NftStatus::Burned.as_str()))
+        .filter(nft_owner::Column::DefinitionId.ne(""))
+        .group_by(nft_owner::Column::DefinitionId)
+        .group_by(nft_owner::Column::Owner)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let mut res = vec![("LM".to_string(), lm)];
+    res.extend(
+        fungible
+            .into_iter()
+            .map(|(token, address, free, locked)| (token, (address, free.unwrap_or_default(), locked.unwrap_or_default())))
+            .chain(nft.into_iter().map(|(def, owner, items)| (def, (owner, BigDecimal::from(items), BigDecimal::zero()))))
+            .into_group_map()
+            .into_iter()
+            .sorted_by(|a, b| a.0.cmp(&b.0)),
+    );
+    Ok(res)
+}
+
+async fn save_distribution(db: &DatabaseConnection, rows: Vec<holder_rank::Model>, dist: &token_distribution::Model) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+    holder_rank::Entity::delete_many()
+        .filter(holder_rank::Column::Token.eq(dist.token.clone()))
+        .exec(&txn)
+        .await?;
+    if !rows.is_empty() {
+        holder_rank::Entity::insert_many(rows.into_iter().map(|r| holder_rank::ActiveModel::from(r).reset_all()))
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    token_distribution::Entity::insert(token_distribution::ActiveModel::from(dist.clone()).reset_all())
+        .on_conflict(
+            OnConflict::column(token_distribution::Column::Token)
+                .update_columns([
+                    token_distribution::Column::HolderCount,
+                    token_distribution::Column::TotalBalance,
+                    token_distribution::Column::ExcludedBalance,
+                    token_distribution::Column::Top10Share,
+                    token_distribution::Column::Top100Share,
+                    token_distribution::Column::Gini,
+                    token_distribution::Column::Nakamoto,
+                    token_distribution::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+    txn.commit().await
+}
+
+pub async fn update_distribution(db: &DatabaseConnection) -> Result<Vec<token_distribution::Model>, DbErr> {
+    save_labels(db).await?;
+    let labels: HashMap<String, address_label::Model> = address_label::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|l| (l.address.clone(), l))
+        .collect();
+    let size = var("RICH_LIST_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_RICH_LIST_SIZE);
+    let mut res = vec![];
+    for (token, holders) in token_holders(db).await? {
+        let (rows, dist) = rich_list(&token, &holders, &labels, size, now());
+        save_distribution(db, rows, &dist).await?;
+        res.push(dist);
+    }
+    Ok(res)
+}
@@ -0,0 +1,176 @@
+use async_stream::stream;
+use futures::Stream;
+use lazy_static::lazy_static;
+use sea_orm::sea_query::{Expr, SimpleExpr};
+use sea_orm::*;
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{chain_event, service::cursor_service::Cursor};
+
+const BACKLOG_PAGE: u64 = 500;
+// block height events are sequenced up to
+const CURSOR: &str = "stream";
+
+lazy_static! {
+    static ref HUB: broadcast::Sender<chain_event::Model> = broadcast::channel(4096).0;
+}
+
+// topic filters of a stream client, empty means everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventFilter {
+    // comma separated kinds
+    pub topics: Option<String>,
+    pub address: Option<String>,
+    pub token: Option<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, e: &chain_event::Model) -> bool {
+        let topic = self
+            .topics
+            .as_ref()
+            .is_none_or(|t| t.split(',').any(|k| k.trim() == e.kind));
+        let address = self.address.as_ref().is_none_or(|a| e.addresses().contains(&a.as_str()));
+        let token = self.token.as_ref().is_none_or(|t| e.token.as_ref() == Some(t));
+        topic && address && token
+    }
+}
+
+pub struct EventHub;
+
+impl EventHub {
+    pub fn publish(events: Vec<chain_event::Model>) {
+        for e in events {
+            // no receivers is fine
+            let _ = HUB.send(e);
+        }
+    }
+
+    // numbers the pending rows in chain order, only up to the gapless built height since
+    // blocks are saved newest first and concurrently.
+    pub async fn sequence(db: &DatabaseConnection) -> Result<Vec<chain_event::Model>, DbErr> {
+        let txn = db.begin().await?;
+        let last = chain_event::Entity::find()
+            .select_only()
+            .column_as(chain_event::Column::Seq.max(), "seq")
+            .into_tuple::<Option<i64>>()
+            .one(&txn)
+            .await?
+            .flatten()
+            .unwrap_or(0);
+        let mut cursor = Cursor::get(&txn, CURSOR).await?;
+        if cursor == 0 {
+            // start right below the oldest pending block instead of walking up from genesis
+            cursor = chain_event::Entity::find()
+                .select_only()
+                .column_as(chain_event::Column::BlockNumber.min(), "block_number")
+                .filter(chain_event::Column::Seq.is_null())
+                .into_tuple::<Option<i64>>()
+                .one(&txn)
+                .await?
+                .flatten()
+                .map_or(0, |b| b - 1);
+        }
+        let built = Cursor::built_height(&txn, cursor).await?;
+        let pending = chain_event::Entity::find()
+            .filter(chain_event::Column::Seq.is_null())
+            .filter(chain_event::Column::BlockNumber.lte(built))
+            .order_by_asc(chain_event::Column::BlockNumber)
+            // balances follow the txs of their block whichever was stored first
+            .order_by(
+                SimpleExpr::Case(Box::new(
+                    Expr::case(chain_event::Column::Kind.eq(chain_event::BALANCE), 1).finally(0),
+                )),
+                Order::Asc,
+            )
+            .order_by_asc(chain_event::Column::Id)
+            .limit(1000)
+            .all(&txn)
+            .await?;
+        if built > cursor {
+            Cursor::set(&txn, CURSOR, built).await?;
+        }
+        let mut events = vec![];
+        for (i, e) in pending.into_iter().enumerate() {
+            let seq = last + 1 + i as i64;
+            chain_event::Entity::update_many()
+                .col_expr(chain_event::Column::Seq, Expr::value(seq))
+                .filter(chain_event::Column::Id.eq(e.id))
+                .exec(&txn)
+                .await?;
+            events.push(chain_event::Model { seq: Some(seq), ..e });
+        }
+        txn.commit().await?;
+        Ok(events)
+    }
+
+    // Some(oldest stored seq) when events right after since were already pruned.
+    pub async fn pruned_since<C: ConnectionTrait>(db: &C, since: i64) -> Result<Option<i64>, DbErr> {
+        let oldest = chain_event::Entity::find()
+            .select_only()
+            .column_as(chain_event::Column::Seq.min(), "seq")
+            .into_tuple::<Option<i64>>()
+            .one(db)
+            .await?
+            .flatten();
+        Ok(oldest.filter(|oldest| since + 1 < *oldest))
+    }
+
+    pub async fn backlog<C: ConnectionTrait>(db: &C, since: i64, limit: u64) -> Result<Vec<chain_event::Model>, DbErr> {
+        chain_event::Entity::find()
+            .filter(chain_event::Column::Seq.gt(since))
+            .order_by_asc(chain_event::Column::Seq)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    // stored events after since, then live ones; a lagging client catches up from the table.
+    pub fn subscribe(
+        db: DatabaseConnection,
+        filter: EventFilter,
+        since: Option<i64>,
+    ) -> impl Stream<Item = chain_event::Model> {
+        let mut rx = HUB.subscribe();
+        stream! {
+            let mut last = since;
+            loop {
+                if let Some(since) = last {
+                    loop {
+                        let Ok(page) = Self::backlog(&db, last.unwrap_or(since), BACKLOG_PAGE).await else { return };
+                        let done = (page.len() as u64) < BACKLOG_PAGE;
+                        for e in page {
+                            last = e.seq;
+                            if filter.matches(&e) {
+                                yield e;
+                            }
+                        }
+                        if done {
+                            break;
+                        }
+                    }
+                }
+                loop {
+                    match rx.recv().await {
+                        Ok(e) if e.seq <= last => continue,
+                        Ok(e) => {
+                            last = e.seq;
+                            if filter.matches(&e) {
+                                yield e;
+                            }
+                        }
+                        Err(RecvError::Lagged(_)) => {
+                            // without a position there is nothing to catch up from
+                            if last.is_none() {
+                                continue;
+                            }
+                            break;
+                        }
+                        Err(RecvError::Closed) => return,
+                    }
+                }
+            }
+        }
+    }
+}
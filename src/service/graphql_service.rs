@@ -0,0 +1,246 @@
+use async_graphql::{Context, EmptyMutation, EmptySubscription, InputObject, Object, Result, Schema, SimpleObject};
+use sea_orm::*;
+
+use dotenvy::var;
+
+use crate::{account_entity, account_mapper, api_app::parse_cursor, block_entity, tx_entity};
+
+const DEFAULT_FIRST: u64 = 20;
+const MAX_FIRST: u64 = 100;
+
+pub type LmSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+fn first(first: Option<u64>) -> u64 {
+    first.unwrap_or(DEFAULT_FIRST).clamp(1, MAX_FIRST)
+}
+
+// a page costs its size times what is asked per item
+fn page_cost(first: Option<u64>, child: usize) -> usize {
+    self::first(first) as usize * child
+}
+
+#[derive(Debug, Default, InputObject)]
+pub struct TxFilter {
+    pub tx_type: Option<String>,
+    pub sub_type: Option<String>,
+    // unix seconds, inclusive
+    pub from_time: Option<i64>,
+    pub to_time: Option<i64>,
+}
+
+#[derive(SimpleObject)]
+#[graphql(concrete(name = "TxPage", params(Tx)), concrete(name = "BlockPage", params(Block)))]
+pub struct Page<T: async_graphql::OutputType> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+pub struct Account(account_entity::Model);
+pub struct Tx(tx_entity::Model);
+pub struct Block(block_entity::Model);
+
+// txs newest first, cursor is "{event_time}:{hash}" like the rest api
+async fn find_txs(
+    db: &DatabaseConnection,
+    query: Select<tx_entity::Entity>,
+    filter: Option<TxFilter>,
+    first: Option<u64>,
+    after: Option<String>,
+) -> Result<Page<Tx>> {
+    let limit = self::first(first);
+    let filter = filter.unwrap_or_default();
+    let mut query = query
+        .apply_if(filter.tx_type, |q, v| q.filter(tx_entity::Column::TxType.eq(v)))
+        .apply_if(filter.sub_type, |q, v| q.filter(tx_entity::Column::SubType.eq(v)))
+        .apply_if(filter.from_time, |q, v| q.filter(tx_entity::Column::EventTime.gte(v)))
+        .apply_if(filter.to_time, |q, v| q.filter(tx_entity::Column::EventTime.lte(v)))
+        .order_by_desc(tx_entity::Column::EventTime)
+        .order_by_desc(tx_entity::Column::Hash)
+        .limit(limit + 1);
+    if let Some(after) = after {
+        let (time, hash) = parse_cursor(&after).ok_or("invalid cursor")?;
+        query = query.filter(
+            Condition::any().add(tx_entity::Column::EventTime.lt(time)).add(
+                Condition::all()
+                    .add(tx_entity::Column::EventTime.eq(time))
+                    .add(tx_entity::Column::Hash.lt(hash)),
+            ),
+        );
+    }
+    let mut rows = query.all(db).await?;
+    let next_cursor = if rows.len() as u64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|r| format!("{}:{}", r.event_time, r.hash))
+    } else {
+        None
+    };
+    Ok(Page { items: rows.into_iter().map(Tx).collect(), next_cursor })
+}
+
+#[Object]
+impl Account {
+    async fn address(&self) -> &str {
+        &self.0.address
+    }
+    async fn eth_address(&self) -> Option<&str> {
+        self.0.eth_address.as_deref()
+    }
+    async fn guardian(&self) -> Option<&str> {
+        self.0.guardian.as_deref()
+    }
+    async fn event_time(&self) -> i64 {
+        self.0.event_time
+    }
+
+    #[graphql(complexity = "page_cost(first, child_complexity)")]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TxFilter>,
+        first: Option<u64>,
+        after: Option<String>,
+    ) -> Result<Page<Tx>> {
+        let query = tx_entity::Entity::find()
+            .join(JoinType::InnerJoin, account_mapper::Relation::Hash.def().rev())
+            .filter(account_mapper::Column::Address.eq(self.0.address.clone()));
+        find_txs(ctx.data::<DatabaseConnection>()?, query, filter, first, after).await
+    }
+}
+
+#[Object]
+impl Tx {
+    async fn hash(&self) -> &str {
+        &self.0.hash
+    }
+    async fn signer(&self) -> &str {
+        &self.0.signer
+    }
+    async fn token_type(&self) -> &str {
+        &self.0.token_type
+    }
+    async fn tx_type(&self) -> &str {
+        &self.0.tx_type
+    }
+    async fn sub_type(&self) -> &str {
+        &self.0.sub_type
+    }
+    async fn block_hash(&self) -> &str {
+        &self.0.block_hash
+    }
+    async fn block_number(&self) -> i64 {
+        self.0.block_number
+    }
+    async fn event_time(&self) -> i64 {
+        self.0.event_time
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        let db = ctx.data::<DatabaseConnection>()?;
+        Ok(block_entity::Entity::find_by_id(self.0.block_hash.clone()).one(db).await?.map(Block))
+    }
+
+    async fn signer_account(&self, ctx: &Context<'_>) -> Result<Option<Account>> {
+        let db = ctx.data::<DatabaseConnection>()?;
+        Ok(account_entity::Entity::find_by_id(self.0.signer.clone()).one(db).await?.map(Account))
+    }
+}
+
+#[Object]
+impl Block {
+    async fn hash(&self) -> &str {
+        &self.0.hash
+    }
+    async fn number(&self) -> i64 {
+        self.0.number
+    }
+    async fn parent_hash(&self) -> &str {
+        &self.0.parent_hash
+    }
+    async fn tx_count(&self) -> i64 {
+        self.0.tx_count
+    }
+    async fn event_time(&self) -> i64 {
+        self.0.event_time
+    }
+
+    #[graphql(complexity = "page_cost(first, child_complexity)")]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TxFilter>,
+        first: Option<u64>,
+        after: Option<String>,
+    ) -> Result<Page<Tx>> {
+        let query = tx_entity::Entity::find().filter(tx_entity::Column::BlockHash.eq(self.0.hash.clone()));
+        find_txs(ctx.data::<DatabaseConnection>()?, query, filter, first, after).await
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn account(&self, ctx: &Context<'_>, address: String) -> Result<Option<Account>> {
+        let db = ctx.data::<DatabaseConnection>()?;
+        Ok(account_entity::Entity::find_by_id(address).one(db).await?.map(Account))
+    }
+
+    async fn tx(&self, ctx: &Context<'_>, hash: String) -> Result<Option<Tx>> {
+        let db = ctx.data::<DatabaseConnection>()?;
+        Ok(tx_entity::Entity::find_by_id(hash).one(db).await?.map(Tx))
+    }
+
+    #[graphql(complexity = "page_cost(first, child_complexity)")]
+    async fn txs(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TxFilter>,
+        first: Option<u64>,
+        after: Option<String>,
+    ) -> Result<Page<Tx>> {
+        find_txs(ctx.data::<DatabaseConnection>()?, tx_entity::Entity::find(), filter, first, after).await
+    }
+
+    async fn block(&self, ctx: &Context<'_>, number: Option<i64>, hash: Option<String>) -> Result<Option<Block>> {
+        let db = ctx.data::<DatabaseConnection>()?;
+        let query = match (number, hash) {
+            (Some(n), _) => block_entity::Entity::find().filter(block_entity::Column::Number.eq(n)),
+            (None, Some(h)) => block_entity::Entity::find().filter(block_entity::Column::Hash.eq(h)),
+            (None, None) => return Err("number or hash is required".into()),
+        };
+        Ok(query.one(db).await?.map(Block))
+    }
+
+    // newest first, cursor is the last block number
+    #[graphql(complexity = "page_cost(first, child_complexity)")]
+    async fn blocks(&self, ctx: &Context<'_>, first: Option<u64>, after: Option<String>) -> Result<Page<Block>> {
+        let db = ctx.data::<DatabaseConnection>()?;
+        let limit = self::first(first);
+        let mut query = block_entity::Entity::find()
+            .order_by_desc(block_entity::Column::Number)
+            .limit(limit + 1);
+        if let Some(after) = after {
+            let number = after.parse::<i64>().map_err(|_| "invalid cursor")?;
+            query = query.filter(block_entity::Column::Number.lt(number));
+        }
+        let mut rows = query.all(db).await?;
+        let next_cursor = if rows.len() as u64 > limit {
+            rows.truncate(limit as usize);
+            rows.last().map(|b| b.number.to_string())
+        } else {
+            None
+        };
+        Ok(Page { items: rows.into_iter().map(Block).collect(), next_cursor })
+    }
+}
+
+// GRAPHQL_MAX_DEPTH and GRAPHQL_MAX_COMPLEXITY bound what a public query may ask for.
+pub fn schema(db: DatabaseConnection) -> LmSchema {
+    let depth = var("GRAPHQL_MAX_DEPTH").ok().and_then(|v| v.parse().ok()).unwrap_or(8);
+    let complexity = var("GRAPHQL_MAX_COMPLEXITY").ok().and_then(|v| v.parse().ok()).unwrap_or(2000);
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(depth)
+        .limit_complexity(complexity)
+        .finish()
+}
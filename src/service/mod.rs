@@ -1,2 +1,11 @@
 pub mod api_service;
 pub mod finder_service;
+pub mod precision_service;
+pub mod url_resolver;
+pub mod cursor_service;
+pub mod price_service;
+pub mod custody_service;
+pub mod counter_service;
+pub mod distribution_service;
+pub mod graphql_service;
+pub mod event_service;
@@ -0,0 +1,35 @@
+use bigdecimal::BigDecimal;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use sea_orm::{DatabaseConnection, EntityTrait};
+
+use crate::token_definition;
+
+// precision of LM when the definition carries none.
+pub const DEFAULT_PRECISION: i32 = 18;
+
+lazy_static! {
+    static ref PRECISION: DashMap<String, i32> = DashMap::new();
+}
+
+pub struct Precision;
+
+impl Precision {
+    pub async fn of(db: &DatabaseConnection, definition_id: &str) -> i32 {
+        if let Some(p) = PRECISION.get(definition_id) {
+            return *p;
+        }
+        match token_definition::Entity::find_by_id(definition_id.to_owned()).one(db).await {
+            Ok(Some(def)) => {
+                let p = def.precision.unwrap_or(DEFAULT_PRECISION);
+                PRECISION.insert(definition_id.to_owned(), p);
+                p
+            }
+            _ => DEFAULT_PRECISION,
+        }
+    }
+
+    pub fn scale(raw: &BigDecimal, precision: i32) -> BigDecimal {
+        raw * BigDecimal::new(1.into(), precision as i64)
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bigdecimal::BigDecimal;
+use chrono::DateTime;
+use futures::future::join_all;
+use reqwest::Url;
+use sea_orm::prelude::async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use dotenvy::var;
+use log::warn;
+
+use super::api_service::ApiService;
+use crate::{library::common::now, model::lm_price::LmPrice};
+
+// CoinMarketCap id of LM
+pub const CMC_LM_ID: i32 = 20315;
+const DEFAULT_MAX_AGE_SECS: i64 = 60 * 60;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_CACHE_SECS: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceQuote {
+    #[serde(default)]
+    pub source: String,
+    pub price: BigDecimal,
+    pub market_cap: Option<BigDecimal>,
+    pub circulating_supply: Option<BigDecimal>,
+    // unix seconds the vendor last updated the price
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    fn name(&self) -> String;
+    async fn quote(&self) -> Result<PriceQuote, String>;
+
+    // (unix seconds, price) points between from and to, oldest first.
+    async fn history(&self, _from: i64, _to: i64) -> Result<Vec<(i64, BigDecimal)>, String> {
+        Err(format!("{} has no history", self.name()))
+    }
+}
+
+pub struct CoinMarketCap {
+    pub api_key: String,
+    pub id: i32,
+}
+
+#[async_trait]
+impl PriceProvider for CoinMarketCap {
+    fn name(&self) -> String {
+        "coinmarketcap".to_string()
+    }
+
+    async fn quote(&self) -> Result<PriceQuote, String> {
+        let url = Url::parse(&format!(
+            "https://pro-api.coinmarketcap.com/v2/cryptocurrency/quotes/latest?id={}",
+            self.id
+        ))
+        .map_err(|e| e.to_string())?;
+        let lm = ApiService::get_request_header_always::<LmPrice>(url, &self.api_key).await?;
+        let d = lm.data.get(&self.id).ok_or(format!("no quote for {}", self.id))?;
+        Ok(PriceQuote {
+            source: self.name(),
+            price: d.quote.usd.price.clone(),
+            market_cap: Some(d.quote.usd.market_cap.clone()),
+            circulating_supply: Some(d.circulating_supply.clone()),
+            updated_at: DateTime::parse_from_rfc3339(&d.quote.usd.last_updated).map_or(now(), |t| t.timestamp()),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GeckoChart {
+    prices: Vec<(f64, BigDecimal)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeckoQuote {
+    usd: BigDecimal,
+    usd_market_cap: Option<BigDecimal>,
+    last_updated_at: Option<i64>,
+}
+
+// CoinGecko /simple/price, or any vendor serving the same shape.
+pub struct CoinGecko {
+    pub base_url: String,
+    pub coin_id: String,
+    pub api_key: Option<String>,
+}
+
+#[async_trait]
+impl PriceProvider for CoinGecko {
+    fn name(&self) -> String {
+        "coingecko".to_string()
+    }
+
+    async fn quote(&self) -> Result<PriceQuote, String> {
+        let mut url = Url::parse(&format!("{}/simple/price", self.base_url.trim_end_matches('/')))
+            .map_err(|e| e.to_string())?;
+        url.query_pairs_mut()
+            .append_pair("ids", &self.coin_id)
+            .append_pair("vs_currencies", "usd")
+            .append_pair("include_market_cap", "true")
+            .append_pair("include_last_updated_at", "true");
+        if let Some(key) = &self.api_key {
+            url.query_pairs_mut().append_pair("x_cg_demo_api_key", key);
+        }
+        let mut res = ApiService::get_request::<HashMap<String, GeckoQuote>>(url).await?;
+        let q = res.remove(&self.coin_id).ok_or(format!("no quote for {}", self.coin_id))?;
+        Ok(PriceQuote {
+            source: self.name(),
+            price: q.usd,
+            market_cap: q.usd_market_cap,
+            circulating_supply: None,
+            updated_at: q.last_updated_at.unwrap_or(now()),
+        })
+    }
+
+    // hourly points for ranges up to 90 days, daily beyond.
+    async fn history(&self, from: i64, to: i64) -> Result<Vec<(i64, BigDecimal)>, String> {
+        let mut url = Url::parse(&format!(
+            "{}/coins/{}/market_chart/range",
+            self.base_url.trim_end_matches('/'),
+            self.coin_id
+        ))
+        .map_err(|e| e.to_string())?;
+        url.query_pairs_mut()
+            .append_pair("vs_currency", "usd")
+            .append_pair("from", &from.to_string())
+            .append_pair("to", &to.to_string());
+        if let Some(key) = &self.api_key {
+            url.query_pairs_mut().append_pair("x_cg_demo_api_key", key);
+        }
+        let chart = ApiService::get_request::<GeckoChart>(url).await?;
+        Ok(chart.prices.into_iter().map(|(ms, p)| (ms as i64 / 1000, p)).collect())
+    }
+}
+
+// fixed quote, or one read from a json file on every call when a path is given.
+pub struct StaticPrice {
+    pub quote: Option<PriceQuote>,
+    pub path: Option<String>,
+}
+
+#[async_trait]
+impl PriceProvider for StaticPrice {
+    fn name(&self) -> String {
+        "static".to_string()
+    }
+
+    async fn quote(&self) -> Result<PriceQuote, String> {
+        let mut quote = match (&self.quote, &self.path) {
+            (Some(q), _) => q.clone(),
+            (None, Some(path)) => {
+                let s = tokio::fs::read_to_string(path).await.map_err(|e| format!("{path} {e}"))?;
+                serde_json::from_str::<PriceQuote>(&s).map_err(|e| e.to_string())?
+            }
+            (None, None) => return Err("no static price".to_string()),
+        };
+        quote.source = self.name();
+        if quote.updated_at == 0 {
+            quote.updated_at = now();
+        }
+        Ok(quote)
+    }
+}
+
+pub fn median(mut values: Vec<BigDecimal>) -> Option<BigDecimal> {
+    values.sort();
+    let n = values.len();
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(values[n / 2].clone()),
+        _ => Some((&values[n / 2 - 1] + &values[n / 2]) / BigDecimal::from(2)),
+    }
+}
+
+// median of the quotes no older than max_age, market cap derived from supply when missing.
+pub fn aggregate(quotes: Vec<PriceQuote>, now: i64, max_age_secs: i64) -> Option<PriceQuote> {
+    let fresh: Vec<PriceQuote> = quotes
+        .into_iter()
+        .filter(|q| {
+            let fresh = now - q.updated_at <= max_age_secs;
+            if !fresh {
+                warn!("{} price is stale, updated at {}", q.source, q.updated_at);
+            }
+            fresh
+        })
+        .collect();
+    let price = median(fresh.iter().map(|q| q.price.clone()).collect())?;
+    let circulating_supply = median(fresh.iter().filter_map(|q| q.circulating_supply.clone()).collect());
+    let market_cap = median(fresh.iter().filter_map(|q| q.market_cap.clone()).collect())
+        .or_else(|| circulating_supply.as_ref().map(|s| &price * s));
+    Some(PriceQuote {
+        source: fresh.iter().map(|q| q.source.clone()).collect::<Vec<_>>().join(","),
+        price,
+        market_cap,
+        circulating_supply,
+        updated_at: fresh.iter().map(|q| q.updated_at).min().unwrap_or(now),
+    })
+}
+
+pub struct PriceService {
+    pub providers: Vec<Box<dyn PriceProvider>>,
+    pub max_age_secs: i64,
+    // a provider slower than this is left out of the quote
+    pub timeout: Duration,
+    pub cache_secs: i64,
+    // (fetched at, aggregate) shared by every loop holding this service
+    last: tokio::sync::Mutex<Option<(i64, Option<PriceQuote>)>>,
+}
+
+impl PriceService {
+    pub fn new(providers: Vec<Box<dyn PriceProvider>>, max_age_secs: i64, timeout: Duration, cache_secs: i64) -> PriceService {
+        PriceService { providers, max_age_secs, timeout, cache_secs, last: tokio::sync::Mutex::new(None) }
+    }
+
+    // PRICE_PROVIDERS lists the providers to ask, coinmarketcap when unset.
+    pub fn from_env(cmc_api_key: String) -> PriceService {
+        let names = var("PRICE_PROVIDERS").unwrap_or("coinmarketcap".to_string());
+        let providers = names
+            .split(',')
+            .map(|n| n.trim())
+            .filter_map(|n| -> Option<Box<dyn PriceProvider>> {
+                match n {
+                    "coinmarketcap" => Some(Box::new(CoinMarketCap { api_key: cmc_api_key.clone(), id: CMC_LM_ID })),
+                    "coingecko" => Some(Box::new(CoinGecko {
+                        base_url: var("COINGECKO_API_URL").unwrap_or("https://api.coingecko.com/api/v3".to_string()),
+                        coin_id: var("COINGECKO_ID").unwrap_or("leisuremeta".to_string()),
+                        api_key: var("COINGECKO_API_KEY").ok().filter(|k| !k.is_empty()),
+                    })),
+                    "static" => Some(Box::new(StaticPrice {
+                        quote: var("PRICE_STATIC").ok().and_then(|p| BigDecimal::from_str(&p).ok()).map(|price| PriceQuote {
+                            source: String::new(),
+                            price,
+                            market_cap: None,
+                            circulating_supply: None,
+                            updated_at: 0,
+                        }),
+                        path: var("PRICE_FILE").ok().filter(|p| !p.is_empty()),
+                    })),
+                    "" => None,
+                    _ => {
+                        warn!("unknown price provider '{n}'");
+                        None
+                    }
+                }
+            })
+            .collect();
+        PriceService::new(
+            providers,
+            var("PRICE_MAX_AGE_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_MAX_AGE_SECS),
+            Duration::from_secs(var("PRICE_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_TIMEOUT_SECS)),
+            var("PRICE_CACHE_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_CACHE_SECS),
+        )
+    }
+
+    // callers within cache_secs of the last fetch get its result, concurrent callers wait for one fetch.
+    pub async fn quote(&self) -> Option<PriceQuote> {
+        let mut last = self.last.lock().await;
+        if let Some((at, q)) = last.as_ref() {
+            if now() - at < self.cache_secs {
+                return q.clone();
+            }
+        }
+        let q = self.fetch().await;
+        *last = Some((now(), q.clone()));
+        q
+    }
+
+    async fn fetch(&self) -> Option<PriceQuote> {
+        let quotes = join_all(self.providers.iter().map(|p| tokio::time::timeout(self.timeout, p.quote())))
+            .await
+            .into_iter()
+            .zip(self.providers.iter())
+            .filter_map(|(res, p)| match res {
+                Ok(Ok(q)) => Some(q),
+                Ok(Err(err)) => {
+                    warn!("{} price fail: {err}", p.name());
+                    None
+                }
+                Err(_) => {
+                    warn!("{} price timed out after {:?}", p.name(), self.timeout);
+                    None
+                }
+            })
+            .collect();
+        aggregate(quotes, now(), self.max_age_secs)
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
+use dotenvy::var;
+
+lazy_static! {
+    static ref RESOLVER: UrlResolver = UrlResolver::from_env();
+}
+
+const DEFAULT_IPFS_GATEWAYS: &str = "https://ipfs.io/ipfs/,https://cloudflare-ipfs.com/ipfs/";
+const DEFAULT_AR_GATEWAYS: &str = "https://arweave.net/";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Mirror(PathBuf),
+    Http(String),
+}
+
+// resolves nft data urls, ipfs:// and ar:// go through the gateways in order.
+pub struct UrlResolver {
+    pub ipfs_gateways: Vec<String>,
+    pub ar_gateways: Vec<String>,
+    pub mirror_dir: Option<PathBuf>,
+    pub max_bytes: usize,
+    client: reqwest::Client,
+}
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|g| g.trim())
+        .filter(|g| !g.is_empty())
+        .map(|g| if g.ends_with('/') { g.to_owned() } else { format!("{g}/") })
+        .collect()
+}
+
+impl UrlResolver {
+    pub fn new(
+        ipfs_gateways: &str,
+        ar_gateways: &str,
+        mirror_dir: Option<PathBuf>,
+        max_bytes: usize,
+        timeout_secs: u64,
+    ) -> UrlResolver {
+        UrlResolver {
+            ipfs_gateways: list(ipfs_gateways),
+            ar_gateways: list(ar_gateways),
+            mirror_dir,
+            max_bytes,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(timeout_secs))
+                .build()
+                .unwrap(),
+        }
+    }
+
+    fn from_env() -> UrlResolver {
+        UrlResolver::new(
+            &var("NFT_IPFS_GATEWAYS").unwrap_or(DEFAULT_IPFS_GATEWAYS.to_owned()),
+            &var("NFT_AR_GATEWAYS").unwrap_or(DEFAULT_AR_GATEWAYS.to_owned()),
+            var("NFT_MIRROR_DIR").ok().filter(|d| !d.is_empty()).map(PathBuf::from),
+            var("NFT_MAX_BYTES").ok().and_then(|v| v.parse().ok()).unwrap_or(20 * 1024 * 1024),
+            var("NFT_FETCH_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30),
+        )
+    }
+
+    pub fn global() -> &'static UrlResolver {
+        &RESOLVER
+    }
+
+    // (scheme, path) for content addressed urls, gateway urls are recognised by their /ipfs/ path.
+    fn content_path(url: &str) -> Option<(&'static str, String)> {
+        if let Some(rest) = url.strip_prefix("ipfs://") {
+            let rest = rest.strip_prefix("ipfs/").unwrap_or(rest);
+            return Some(("ipfs", rest.to_owned()));
+        }
+        if let Some(rest) = url.strip_prefix("ar://") {
+            return Some(("ar", rest.to_owned()));
+        }
+        let parsed = Url::parse(url).ok()?;
+        parsed
+            .path()
+            .split_once("/ipfs/")
+            .filter(|(prefix, _)| prefix.is_empty())
+            .map(|(_, rest)| ("ipfs", rest.to_owned()))
+    }
+
+    // on-chain paths are untrusted, anything that could leave the mirror dir gets no mirror source.
+    fn mirror_path(dir: &Path, parts: &[&str]) -> Option<PathBuf> {
+        let mut path = dir.to_path_buf();
+        for part in parts {
+            for c in Path::new(part).components() {
+                match c {
+                    Component::Normal(p) => path.push(p),
+                    Component::CurDir => (),
+                    Component::RootDir | Component::ParentDir | Component::Prefix(_) => return None,
+                }
+            }
+        }
+        Some(path)
+    }
+
+    pub fn sources(&self, url: &str) -> Vec<Source> {
+        let url = url.trim();
+        let mut sources = vec![];
+        match Self::content_path(url) {
+            Some((scheme, path)) => {
+                if let Some(path) = self.mirror_dir.as_ref().and_then(|dir| Self::mirror_path(dir, &[scheme, &path])) {
+                    sources.push(Source::Mirror(path));
+                }
+                let gateways = if scheme == "ipfs" { &self.ipfs_gateways } else { &self.ar_gateways };
+                // an http gateway url is tried as given before the configured ones
+                if url.starts_with("http") {
+                    sources.push(Source::Http(url.to_owned()));
+                }
+                sources.extend(gateways.iter().map(|g| Source::Http(format!("{g}{path}"))).filter(|s| *s != Source::Http(url.to_owned())));
+            }
+            None => {
+                if let (Some(dir), Ok(parsed)) = (&self.mirror_dir, Url::parse(url)) {
+                    let host = parsed.host_str().unwrap_or_default();
+                    if let Some(path) = Self::mirror_path(dir, &["http", host, parsed.path().trim_start_matches('/')]) {
+                        sources.push(Source::Mirror(path));
+                    }
+                }
+                sources.push(Source::Http(url.to_owned()));
+            }
+        }
+        sources
+    }
+
+    // host the first network request for this url goes to, for per-host limits.
+    pub fn host(&self, url: &str) -> String {
+        self.sources(url)
+            .into_iter()
+            .find_map(|s| match s {
+                Source::Http(u) => Url::parse(&u).ok().and_then(|u| u.host_str().map(|h| h.to_owned())),
+                Source::Mirror(_) => None,
+            })
+            .unwrap_or_default()
+    }
+
+    async fn read_mirror(&self, path: &Path) -> Result<Vec<u8>, String> {
+        let meta = tokio::fs::metadata(path).await.map_err(|e| format!("{} {e}", path.display()))?;
+        if meta.len() as usize > self.max_bytes {
+            return Err(format!("{} exceeds {} bytes", path.display(), self.max_bytes));
+        }
+        tokio::fs::read(path).await.map_err(|e| e.to_string())
+    }
+
+    async fn read_http(&self, url: &str) -> Result<Vec<u8>, String> {
+        let mut res = self.client.get(url).send().await.map_err(|e| e.to_string())?;
+        if !res.status().is_success() {
+            return Err(format!("{} - {url}", res.status()));
+        }
+        if res.content_length().is_some_and(|len| len as usize > self.max_bytes) {
+            return Err(format!("{url} exceeds {} bytes", self.max_bytes));
+        }
+        let mut bytes = vec![];
+        while let Some(chunk) = res.chunk().await.map_err(|e| e.to_string())? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > self.max_bytes {
+                return Err(format!("{url} exceeds {} bytes", self.max_bytes));
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub async fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+        let mut errors = vec![];
+        for source in self.sources(url) {
+            let res = match &source {
+                Source::Mirror(path) => self.read_mirror(path).await,
+                Source::Http(url) => self.read_http(url).await,
+            };
+            match res {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) => errors.push(err),
+            }
+        }
+        Err(errors.join("; "))
+    }
+
+    pub async fn fetch_json<S: DeserializeOwned>(&self, url: &str) -> Result<S, String> {
+        let bytes = self.fetch(url).await?;
+        serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+    }
+}
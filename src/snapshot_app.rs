@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::service::cursor_service::Cursor;
+use crate::{balance_app, entity::*, nft_app, transaction::token_transaction::NftStatus};
+use bigdecimal::{BigDecimal, Zero};
+use itertools::Itertools;
+use log::{error, info};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+use tokio::time::sleep;
+
+// snapshots are taken from the balance ledger and nft history, both must have reached the snapshot block.
+pub async fn captured_height(db: &DatabaseConnection) -> Result<i64, DbErr> {
+    let balance = Cursor::get(db, balance_app::CURSOR).await?;
+    let nft = Cursor::get(db, nft_app::CURSOR).await?;
+    Ok(balance.min(nft))
+}
+
+// strictly before the tx at (block_number, tx_index)
+fn before<B: ColumnTrait, I: ColumnTrait>(block: B, index: I, block_number: i64, tx_index: i32) -> Condition {
+    Condition::any()
+        .add(block.lt(block_number))
+        .add(Condition::all().add(block.eq(block_number)).add(index.lt(tx_index)))
+}
+
+// LM balances as they were just before the snapshot tx, summed from the balance ledger.
+pub async fn balances_at(
+    db: &DatabaseConnection,
+    block_number: i64,
+    tx_index: i32,
+) -> Result<Vec<(String, BigDecimal, BigDecimal)>, DbErr> {
+    let rows: Vec<(String, Option<BigDecimal>, Option<BigDecimal>)> = balance_change::Entity::find()
+        .select_only()
+        .column(balance_change::Column::Address)
+        .column_as(balance_change::Column::Free.sum(), "free")
+        .column_as(balance_change::Column::Locked.sum(), "locked")
+        .filter(balance_change::Column::Token.eq("LM"))
+        .filter(before(balance_change::Column::BlockNumber, balance_change::Column::TxIndex, block_number, tx_index))
+        .group_by(balance_change::Column::Address)
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(address, free, locked)| (address, free.unwrap_or_default(), locked.unwrap_or_default()))
+        .filter(|(_, free, locked)| !free.is_zero() || !locked.is_zero())
+        .collect())
+}
+
+// the last state of every nft just before the snapshot tx, burned ones left out.
+pub async fn owners_at(db: &DatabaseConnection, block_number: i64, tx_index: i32) -> Result<Vec<nft_history::Model>, DbErr> {
+    let mut latest: HashMap<String, nft_history::Model> = HashMap::new();
+    for h in nft_history::Entity::find()
+        .filter(before(nft_history::Column::BlockNumber, nft_history::Column::TxIndex, block_number, tx_index))
+        .order_by_asc(nft_history::Column::BlockNumber)
+        .order_by_asc(nft_history::Column::TxIndex)
+        .all(db)
+        .await?
+    {
+        latest.insert(h.token_id.clone(), h);
+    }
+    Ok(latest
+        .into_values()
+        .filter(|h| h.status != NftStatus::Burned.as_str())
+        .sorted_by(|a, b| a.token_id.cmp(&b.token_id))
+        .collect())
+}
+
+pub async fn capture_snapshot(db: &DatabaseConnection, snapshot: snapshot::Model) -> Result<(), DbErr> {
+    let hash = snapshot.hash.clone();
+    let balances = balances_at(db, snapshot.block_number, snapshot.tx_index).await?
+        .into_iter()
+        .map(|(address, free, locked)| snapshot_balance::Model::from(&hash, address, free, locked))
+        .collect_vec();
+    let owners = owners_at(db, snapshot.block_number, snapshot.tx_index).await?
+        .into_iter()
+        .map(|o| snapshot_nft_owner::Model::from(&hash, o))
+        .collect_vec();
+
+    db.transaction::<_, (), DbErr>(|txn| {
+        Box::pin(async move {
+            for chunk in balances.chunks(1000) {
+                snapshot_balance::Entity::insert_many(chunk.to_vec())
+                    .on_conflict(
+                        OnConflict::columns([snapshot_balance::Column::SnapshotHash, snapshot_balance::Column::Address])
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .do_nothing()
+                    .exec_without_returning(txn)
+                    .await?;
+            }
+            for chunk in owners.chunks(1000) {
+                snapshot_nft_owner::Entity::insert_many(chunk.to_vec())
+                    .on_conflict(
+                        OnConflict::columns([snapshot_nft_owner::Column::SnapshotHash, snapshot_nft_owner::Column::TokenId])
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .do_nothing()
+                    .exec_without_returning(txn)
+                    .await?;
+            }
+            snapshot::Entity::update_many()
+                .col_expr(snapshot::Column::IsCaptured, Expr::value(true))
+                .filter(snapshot::Column::Hash.eq(hash))
+                .exec(txn)
+                .await?;
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|err| DbErr::Custom(err.to_string()))
+}
+
+pub async fn capture_pending_snapshots(db: &DatabaseConnection) {
+    let height = match captured_height(db).await {
+        Ok(height) => height,
+        Err(err) => {
+            error!("snapshot height read failed {err}");
+            return;
+        }
+    };
+    let pending = snapshot::Entity::find()
+        .filter(snapshot::Column::IsCaptured.eq(false))
+        .filter(snapshot::Column::BlockNumber.lte(height))
+        .order_by_asc(snapshot::Column::BlockNumber)
+        .all(db)
+        .await
+        .unwrap_or_default();
+
+    for snapshot in pending {
+        let (hash, number) = (snapshot.hash.clone(), snapshot.block_number);
+        match capture_snapshot(db, snapshot).await {
+            Ok(_) => info!("snapshot captured hash: {hash}, block number: {number}"),
+            Err(err) => error!("snapshot capture failed {hash} - {err}"),
+        }
+    }
+}
+
+pub async fn snapshot_loop(db: DatabaseConnection) {
+    info!("snapshot loop start");
+    tokio::spawn(async move {
+        loop {
+            capture_pending_snapshots(&db).await;
+            sleep(Duration::from_secs(60)).await;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
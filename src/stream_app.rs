@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+
+use dotenvy::var;
+use log::{error, info};
+use tokio::time::sleep;
+
+use crate::{chain_event, library::common::now, service::event_service::EventHub};
+
+// published events are kept this long for clients resuming by seq
+const DEFAULT_RETENTION_SECS: i64 = 60 * 60 * 24;
+
+// the newest event is always kept, its seq tells resuming clients what was pruned.
+async fn prune(db: &DatabaseConnection, retention_secs: i64) -> Result<u64, DbErr> {
+    let Some(newest) = chain_event::Entity::find()
+        .select_only()
+        .column_as(chain_event::Column::Seq.max(), "seq")
+        .into_tuple::<Option<i64>>()
+        .one(db)
+        .await?
+        .flatten()
+    else {
+        return Ok(0);
+    };
+    Ok(chain_event::Entity::delete_many()
+        .filter(chain_event::Column::Seq.lt(newest))
+        .filter(chain_event::Column::CreatedAt.lt(now() - retention_secs))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+pub async fn stream_loop(db: DatabaseConnection) {
+    info!("stream loop start");
+    let retention_secs = var("STREAM_RETENTION_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_RETENTION_SECS);
+    tokio::spawn(async move {
+        let mut last_prune = 0;
+        loop {
+            match EventHub::sequence(&db).await {
+                Ok(events) if !events.is_empty() => {
+                    EventHub::publish(events);
+                    continue;
+                }
+                Ok(_) => {}
+                Err(err) => error!("event sequence fail: {err}"),
+            }
+            if now() - last_prune > 60 * 10 {
+                last_prune = now();
+                if let Err(err) = prune(&db, retention_secs).await {
+                    error!("event prune fail: {err}");
+                }
+            }
+            sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
@@ -1,53 +1,73 @@
-use crate::{entity::*, model::lm_price::LmPrice, service::api_service::ApiService};
+use crate::{entity::*, library::common::now, service::{counter_service::Counter, custody_service::{load_custody, CustodyAddress}, distribution_service::update_distribution, precision_service::Precision, price_service::PriceService}};
 use bigdecimal::{BigDecimal, Zero};
-use log::error;
-use reqwest::Url;
-use rust_decimal::prelude::FromPrimitive;
+use log::{error, warn};
 use sea_orm::DatabaseConnection;
 use sea_orm::*;
-use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 
-extern crate dotenvy;
 use dotenvy::var;
 
-pub async fn summary_loop(db: DatabaseConnection, api_key: String) {
+pub async fn summary_loop(db: DatabaseConnection, prices: Arc<PriceService>) {
+    let custody = load_custody();
+    let recount_secs = var("SUMMARY_RECOUNT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60 * 60 * 24);
     tokio::spawn(async move {
+        let mut last_recount = 0;
         loop {
+            let recount = now() - last_recount >= recount_secs;
+            if recount {
+                last_recount = now();
+            }
             match (
                 get_last_built_block(&db).await,
-                get_lm_price(&db, api_key.clone()).await,
-                get_total_accounts(&db).await,
-                get_tx_size(&db).await,
-                get_total_balance().await,
-                get_total_nft(&db).await,
+                get_lm_price(&db, &prices).await,
+                get_counter::<account_entity::Entity>(&db, chain_counter::ACCOUNT, recount).await,
+                get_counter::<tx_entity::Entity>(&db, chain_counter::TX, recount).await,
+                get_counter::<nft_file::Entity>(&db, chain_counter::NFT_FILE, recount).await,
             ) {
                 (
                     Some(last_built_block),
                     Some((price, cap, supply)),
                     Some(total_accounts),
                     Some(total_tx_size),
-                    total_balance,
                     Some(total_nft),
                 ) => {
-                    let summary = summary::Model::from(
+                    let precision = Precision::of(&db, "LM").await;
+                    let total_balance = get_total_balance(&db, &custody, last_built_block.number).await;
+                    let mut summary = summary::Model::from(
                         last_built_block.number,
                         price,
                         cap,
-                        supply,
+                        supply.clone(),
                         total_accounts as i64,
                         total_tx_size as i64,
-                        total_balance.unwrap(),
+                        total_balance.clone(),
                         total_nft,
                     );
+                    summary.total_balance_scaled = Set(Precision::scale(&total_balance, precision));
+                    summary.cir_supply_raw = Set(Precision::scale(&supply, -precision));
+                    match get_chain_supply(&db).await {
+                        Ok(chain) => {
+                            let chain_cir_supply = Precision::scale(&chain.circulating(), precision);
+                            summary.cir_supply_diff = Set(&supply - &chain_cir_supply);
+                            summary.chain_cir_supply = Set(chain_cir_supply);
+                            summary.minted = Set(chain.minted);
+                            summary.burned = Set(chain.burned);
+                            summary.locked = Set(chain.locked);
+                            summary.treasury = Set(chain.treasury);
+                        }
+                        Err(err) => error!("chain supply fail: {err}"),
+                    }
                     if let Err(err) = summary::Entity::insert(summary).exec(&db).await {
                         error!("summary loop failed {}", err);
                     }
                 }
                 _ => error!("summary loop is skiped.")
             }
+            if let Err(err) = update_distribution(&db).await {
+                error!("token distribution update fail: {err}");
+            }
             sleep(Duration::from_secs(60 * 10)).await;
         }
     })
@@ -55,54 +75,122 @@ pub async fn summary_loop(db: DatabaseConnection, api_key: String) {
     .unwrap()
 }
 
-async fn get_total_accounts(db: &DatabaseConnection) -> Option<u64> {
-    account_entity::Entity::find().count(db).await.ok()
+// counters are kept by the indexer, a full count seeds them and corrects drift on recount.
+async fn get_counter<E: EntityTrait>(db: &DatabaseConnection, name: &str, recount: bool) -> Option<u64>
+where
+    E::Model: Sync,
+{
+    let stored = match Counter::get(db, name).await {
+        Ok(v) => v,
+        Err(err) => {
+            error!("counter {name} read fail: {err}");
+            return None;
+        }
+    };
+    if let (Some(v), false) = (stored, recount) {
+        return Some(v as u64);
+    }
+    match Counter::recount::<E>(db, name).await {
+        Ok((stored, count)) => {
+            if stored.is_some_and(|v| v != count) {
+                warn!("counter {name} drifted: {stored:?} -> {count}");
+            }
+            Some(count as u64)
+        }
+        Err(err) => {
+            error!("counter {name} recount fail: {err}");
+            None
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TokenBalance {
-    pub status: String,
-    pub message: String,
-    pub result: String,
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChainSupply {
+    pub minted: BigDecimal,
+    pub burned: BigDecimal,
+    pub locked: BigDecimal,
+    pub treasury: BigDecimal,
 }
 
-async fn get_total_balance() -> Option<BigDecimal> {
-    let api_key = var("SCAN_API_KEY").expect("SCAN_API_KEY must be set");
-    let lm = var("LM_ADDR").expect("LM_ADDR must be set");
-    let addrs: Vec<Url> = var("BAL_ADDR").expect("BAL_ADDR must be set")
-        .split(",")
-        .into_iter()
-        .map(|addr| Url::parse(format!("https://api.etherscan.io/api?module=account&action=tokenbalance&contractaddress={lm}&address={addr}&tag=latest&apikey={api_key}"
-        ).as_str()).unwrap())
-        .collect();
-    futures::future::join_all(addrs.into_iter().map(|url| ApiService::get_request(url)))
-        .await
-        .into_iter()
-        .fold(
-            BigDecimal::from_i32(0),
-            |acc, res: Result<TokenBalance, String>| match (acc, res.ok()) {
-                (Some(x), Some(tb)) => Some(x + BigDecimal::from_str(&tb.result).unwrap()),
-                _ => BigDecimal::from_i32(0),
-            },
-        )
+impl ChainSupply {
+    pub fn circulating(&self) -> BigDecimal {
+        &self.minted - &self.burned - &self.locked - &self.treasury
+    }
 }
 
-async fn get_lm_price(db: &DatabaseConnection, api_key: String) -> Option<(BigDecimal, BigDecimal, BigDecimal)> {
-    let lm_token_id = 20315;
-    match ApiService::get_request_header_always::<LmPrice>(
-    Url::parse(format!(
-            "https://pro-api.coinmarketcap.com/v2/cryptocurrency/quotes/latest?id={lm_token_id}"
-        ).as_str()).unwrap(),
-        &api_key,
-    ).await
-    .map(|lm| 
-        lm.data.get(&lm_token_id)
-        .and_then(|d| 
-            Some((d.quote.usd.price.clone(), d.quote.usd.market_cap.clone(), d.circulating_supply.clone()))
+// treasury is the free LM of addresses labeled exclude_from_supply, their locked part is already in locked.
+pub async fn get_chain_supply(db: &DatabaseConnection) -> Result<ChainSupply, DbErr> {
+    let def = token_definition::Entity::find_by_id("LM".to_owned()).one(db).await?;
+    let treasury: Option<BigDecimal> = balance_entity::Entity::find()
+        .select_only()
+        .column_as(balance_entity::Column::Free.sum(), "treasury")
+        .filter(
+            balance_entity::Column::Address.in_subquery(
+                sea_query::Query::select()
+                    .column(address_label::Column::Address)
+                    .from(address_label::Entity)
+                    .and_where(address_label::Column::ExcludeFromSupply.eq(true))
+                    .to_owned(),
+            ),
         )
-    ).ok() {
-        Some(x) => x,
-        _ => get_last_saved_lm_price(db).await
+        .into_tuple::<Option<BigDecimal>>()
+        .one(db)
+        .await?
+        .flatten();
+    Ok(ChainSupply {
+        minted: def.as_ref().map(|d| d.total_minted.clone()).unwrap_or_default(),
+        burned: def.as_ref().map(|d| d.total_burned.clone()).unwrap_or_default(),
+        locked: def.map(|d| d.total_locked).unwrap_or_default(),
+        treasury: treasury.unwrap_or_default(),
+    })
+}
+
+async fn get_total_balance(db: &DatabaseConnection, custody: &[CustodyAddress], block_number: i64) -> BigDecimal {
+    let results = futures::future::join_all(custody.iter().map(|c| c.balance())).await;
+    let mut total = BigDecimal::zero();
+    for (c, res) in custody.iter().zip(results) {
+        if let Err(err) = &res {
+            error!("custody balance {} {} fail: {err}", c.chain, c.address);
+        }
+        let prev = custody_balance::Entity::find_by_id((c.chain.to_lowercase(), c.address.to_lowercase()))
+            .one(db)
+            .await
+            .unwrap_or_default();
+        let row = c.record(res, prev, block_number, now());
+        total += &row.balance;
+        let res = custody_balance::Entity::insert(custody_balance::ActiveModel::from(row).reset_all())
+            .on_conflict(
+                sea_query::OnConflict::columns([custody_balance::Column::Chain, custody_balance::Column::Address])
+                    .update_columns([
+                        custody_balance::Column::Name,
+                        custody_balance::Column::Source,
+                        custody_balance::Column::Balance,
+                        custody_balance::Column::BlockNumber,
+                        custody_balance::Column::LastError,
+                        custody_balance::Column::FetchedAt,
+                        custody_balance::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await;
+        if let Err(err) = res {
+            error!("custody balance save fail: {err}");
+        }
+    }
+    total
+}
+
+async fn get_lm_price(db: &DatabaseConnection, prices: &PriceService) -> Option<(BigDecimal, BigDecimal, BigDecimal)> {
+    match prices.quote().await {
+        Some(q) => {
+            let (_, last_cap, last_supply) = get_last_saved_lm_price(db).await.unwrap_or_default();
+            Some((q.price, q.market_cap.unwrap_or(last_cap), q.circulating_supply.unwrap_or(last_supply)))
+        }
+        None => {
+            error!("no fresh lm price, keeping the last saved one");
+            get_last_saved_lm_price(db).await
+        }
     }
 }
 
@@ -116,14 +204,6 @@ async fn get_last_saved_lm_price(db: &DatabaseConnection) -> Option<(BigDecimal,
         ).unwrap_or(Some((BigDecimal::zero(), BigDecimal::zero(), BigDecimal::zero())))
 }
 
-async fn get_tx_size(db: &DatabaseConnection) -> Option<u64> {
-    tx_entity::Entity::find().count(db).await.ok()
-}
-
-async fn get_total_nft(db: &DatabaseConnection) -> Option<u64> {
-    nft_file::Entity::find().count(db).await.ok()
-}
-
 async fn get_last_built_block(db: &DatabaseConnection) -> Option<block_state::Model> {
     block_state::Entity::find()
         .filter(block_state::Column::IsBuild.eq(true))
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{
+    agent_cursor, chain_event, library::common::now, service::cursor_service::Cursor,
+    webhook_delivery, webhook_watch,
+};
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use sea_orm::sea_query::Expr;
+use sea_orm::DatabaseConnection;
+use sea_orm::*;
+use sha2::Sha256;
+
+use dotenvy::var;
+use log::{error, info, warn};
+use tokio::time::sleep;
+
+// position in the chain_event seq, not a block number
+const CURSOR: &str = "webhook";
+const EVENT_BATCH: u64 = 500;
+const BATCH_SIZE: u64 = 100;
+const MAX_BACKOFF_SECS: i64 = 60 * 60 * 6;
+
+lazy_static! {
+    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(
+            var("WEBHOOK_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(10)
+        ))
+        .build()
+        .unwrap();
+}
+
+fn max_attempts() -> i32 {
+    var("WEBHOOK_MAX_ATTEMPTS").ok().and_then(|v| v.parse().ok()).unwrap_or(8)
+}
+
+// 30s, 1m, 2m ... capped at 6h.
+pub fn backoff_secs(attempts: i32) -> i64 {
+    (30_i64 << attempts.clamp(0, 20)).min(MAX_BACKOFF_SECS)
+}
+
+// hex hmac-sha256 of "{timestamp}.{body}", sent as X-Lmscan-Signature: sha256=...
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes any key size");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn deliveries(watches: &[webhook_watch::Model], events: &[chain_event::Model]) -> Vec<webhook_delivery::ActiveModel> {
+    events
+        .iter()
+        .flat_map(|e| watches.iter().filter(|w| w.matches(e)).map(|w| webhook_delivery::Model::pending(w.id, e)))
+        .collect_vec()
+}
+
+async fn active_watches<C: ConnectionTrait>(db: &C, watch_id: Option<i64>) -> Result<Vec<webhook_watch::Model>, DbErr> {
+    let mut q = webhook_watch::Entity::find().filter(webhook_watch::Column::Active.eq(true));
+    if let Some(id) = watch_id {
+        q = q.filter(webhook_watch::Column::Id.eq(id));
+    }
+    q.all(db).await
+}
+
+async fn head_seq<C: ConnectionTrait>(db: &C) -> Result<i64, DbErr> {
+    Ok(chain_event::Entity::find()
+        .select_only()
+        .column_as(chain_event::Column::Seq.max(), "seq")
+        .into_tuple::<Option<i64>>()
+        .one(db)
+        .await?
+        .flatten()
+        .unwrap_or(0))
+}
+
+// matches published events past the cursor against the watch list.
+pub async fn enqueue(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    // a fresh install starts at the head instead of flooding receivers with the retained backlog
+    if agent_cursor::Entity::find_by_id(CURSOR.to_owned()).one(db).await?.is_none() {
+        Cursor::set(db, CURSOR, head_seq(db).await?).await?;
+        return Ok(0);
+    }
+    let cursor = Cursor::get(db, CURSOR).await?;
+    let events = chain_event::Entity::find()
+        .filter(chain_event::Column::Seq.gt(cursor))
+        .order_by_asc(chain_event::Column::Seq)
+        .limit(EVENT_BATCH)
+        .all(db)
+        .await?;
+    let Some(last) = events.last().and_then(|e| e.seq) else {
+        return Ok(0);
+    };
+    let rows = deliveries(&active_watches(db, None).await?, &events);
+    let count = rows.len();
+    let txn = db.begin().await?;
+    if !rows.is_empty() {
+        webhook_delivery::Entity::insert_many(rows).exec_without_returning(&txn).await?;
+    }
+    Cursor::set(&txn, CURSOR, last).await?;
+    txn.commit().await?;
+    Ok(count)
+}
+
+async fn deliver(db: &DatabaseConnection, job: webhook_delivery::Model, watch: Option<&webhook_watch::Model>, now: i64) {
+    let res = match watch {
+        Some(watch) => {
+            let body = job.body.to_string();
+            CLIENT
+                .post(&watch.url)
+                .header("Content-Type", "application/json")
+                .header("X-Lmscan-Event", job.kind.as_str())
+                .header("X-Lmscan-Delivery", job.id)
+                .header("X-Lmscan-Timestamp", now)
+                .header("X-Lmscan-Signature", format!("sha256={}", sign(&watch.secret, now, &body)))
+                .body(body)
+                .send()
+                .await
+                .map_err(|err| (None, err.to_string()))
+                .and_then(|res| match res.status() {
+                    s if s.is_success() => Ok(s.as_u16() as i32),
+                    s => Err((Some(s.as_u16() as i32), format!("receiver answered {s}"))),
+                })
+        }
+        None => Err((None, "watch removed or inactive".to_string())),
+    };
+    let attempts = job.attempts + 1;
+    let next = match res {
+        Ok(status) => webhook_delivery::ActiveModel {
+            status: Set("delivered".to_string()),
+            attempts: Set(attempts),
+            response_status: Set(Some(status)),
+            last_error: Set(None),
+            delivered_at: Set(Some(now)),
+            updated_at: Set(now),
+            ..Default::default()
+        },
+        Err((status, err)) => {
+            warn!("webhook delivery {} fail ({attempts} attempts): {err}", job.id);
+            let gave_up = watch.is_none() || attempts >= max_attempts();
+            webhook_delivery::ActiveModel {
+                status: Set(if gave_up { "failed" } else { "pending" }.to_string()),
+                attempts: Set(attempts),
+                next_attempt_at: Set(now + backoff_secs(job.attempts)),
+                response_status: Set(status),
+                last_error: Set(Some(err)),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+        }
+    };
+    let res = webhook_delivery::Entity::update_many()
+        .set(next)
+        .filter(webhook_delivery::Column::Id.eq(job.id))
+        .exec(db)
+        .await;
+    if let Err(err) = res {
+        error!("webhook delivery {} update fail: {err}", job.id);
+    }
+}
+
+// sends the pending deliveries that are due at now.
+pub async fn dispatch(db: &DatabaseConnection, now: i64) -> Result<usize, DbErr> {
+    let jobs = webhook_delivery::Entity::find()
+        .filter(webhook_delivery::Column::Status.eq("pending"))
+        .filter(webhook_delivery::Column::NextAttemptAt.lte(now))
+        .order_by_asc(webhook_delivery::Column::Id)
+        .limit(BATCH_SIZE)
+        .all(db)
+        .await?;
+    let count = jobs.len();
+    let watches: HashMap<i64, webhook_watch::Model> = active_watches(db, None)
+        .await?
+        .into_iter()
+        .map(|w| (w.id, w))
+        .collect();
+    join_all(jobs.into_iter().map(|job| {
+        let watch = watches.get(&job.watch_id);
+        deliver(db, job, watch, now)
+    }))
+    .await;
+    Ok(count)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replay {
+    Deliveries(Vec<i64>),
+    Failed { watch_id: Option<i64> },
+    // re-match already published events, the cursor is left alone
+    Since { seq: i64, watch_id: Option<i64> },
+}
+
+impl Replay {
+    // webhook-replay <delivery id>... | failed [watch id] | since <seq> [watch id]
+    pub fn parse(args: &[String]) -> Result<Replay, String> {
+        let num = |s: &String| s.parse::<i64>().map_err(|_| format!("not a number: {s}"));
+        let watch = |rest: &[String]| rest.first().map(num).transpose();
+        match args {
+            [cmd, rest @ ..] if cmd == "failed" => Ok(Replay::Failed { watch_id: watch(rest)? }),
+            [cmd, seq, rest @ ..] if cmd == "since" => Ok(Replay::Since { seq: num(seq)?, watch_id: watch(rest)? }),
+            [] => Err("usage: webhook-replay <delivery id>... | failed [watch id] | since <seq> [watch id]".to_string()),
+            ids => Ok(Replay::Deliveries(ids.iter().map(num).try_collect()?)),
+        }
+    }
+}
+
+// queues deliveries again, returns how many.
+pub async fn replay(db: &DatabaseConnection, target: Replay) -> Result<u64, DbErr> {
+    let retry = webhook_delivery::Entity::update_many()
+        .col_expr(webhook_delivery::Column::Status, Expr::value("pending"))
+        .col_expr(webhook_delivery::Column::Attempts, Expr::value(0))
+        .col_expr(webhook_delivery::Column::NextAttemptAt, Expr::value(0))
+        .col_expr(webhook_delivery::Column::UpdatedAt, Expr::value(now()));
+    match target {
+        Replay::Deliveries(ids) => Ok(retry.filter(webhook_delivery::Column::Id.is_in(ids)).exec(db).await?.rows_affected),
+        Replay::Failed { watch_id } => {
+            let mut q = retry.filter(webhook_delivery::Column::Status.eq("failed"));
+            if let Some(id) = watch_id {
+                q = q.filter(webhook_delivery::Column::WatchId.eq(id));
+            }
+            Ok(q.exec(db).await?.rows_affected)
+        }
+        Replay::Since { seq, watch_id } => {
+            let watches = active_watches(db, watch_id).await?;
+            let mut count = 0;
+            let mut since = seq;
+            loop {
+                let events = chain_event::Entity::find()
+                    .filter(chain_event::Column::Seq.gt(since))
+                    .order_by_asc(chain_event::Column::Seq)
+                    .limit(EVENT_BATCH)
+                    .all(db)
+                    .await?;
+                let Some(last) = events.last().and_then(|e| e.seq) else { break };
+                let rows = deliveries(&watches, &events);
+                count += rows.len() as u64;
+                if !rows.is_empty() {
+                    webhook_delivery::Entity::insert_many(rows).exec_without_returning(db).await?;
+                }
+                since = last;
+            }
+            Ok(count)
+        }
+    }
+}
+
+pub async fn webhook_loop(db: DatabaseConnection) {
+    info!("webhook loop start");
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = enqueue(&db).await {
+                error!("webhook enqueue err: {err}");
+            }
+            match dispatch(&db, now()).await {
+                // keep draining while a full batch was due
+                Ok(count) if count as u64 == BATCH_SIZE => continue,
+                Ok(_) => (),
+                Err(err) => error!("webhook dispatch err: {err}"),
+            }
+            sleep(Duration::from_secs(5)).await;
+        }
+    })
+    .await
+    .map_err(|err| error!("{err}"))
+    .unwrap()
+}
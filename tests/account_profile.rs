@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use lmscan_agent::library::common::parse_from_json_str;
+    use lmscan_agent::transaction::TransactionWithResult;
+    use sea_orm::ActiveValue;
+
+    #[test]
+    fn external_chain_addresses() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"AccountTx":{"UpdateAccountWithExternalChainAddresses":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","account":"alice","guardian":"bob","externalChainAddresses":{"eth":"0xAbC123"},"memo":null}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let history = tx.get_account_history("hash", 10).unwrap();
+        assert_eq!(history.address, "alice");
+        assert_eq!(history.tx_type, "UpdateAccountWithExternalChainAddresses");
+
+        let profile = history.profile().unwrap();
+        assert_eq!(profile.guardian, ActiveValue::Set(Some("bob".to_string())));
+        assert_eq!(profile.block_number, ActiveValue::Set(10));
+        // eth address is only replaced by CreateAccount / UpdateAccount
+        assert_eq!(profile.eth_address, ActiveValue::NotSet);
+
+        let addrs = history.external_addresses().unwrap();
+        assert_eq!(addrs.len(), 1);
+        assert_eq!(addrs[0].chain, "eth");
+        assert_eq!(addrs[0].external_address, "0xabc123");
+        assert_eq!(addrs[0].address, "alice");
+    }
+
+    #[test]
+    fn update_account_clears_eth_address() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"AccountTx":{"UpdateAccount":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","account":"alice","ethAddress":null,"guardian":null}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let history = tx.get_account_history("hash", 10).unwrap();
+        assert_eq!(history.profile().unwrap().eth_address, ActiveValue::Set(None));
+        assert!(history.external_addresses().unwrap().is_empty());
+    }
+
+    #[test]
+    fn public_key_summaries_with_removed() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"AccountTx":{"AddPublicKeySummaries":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","account":"alice","summaries":{"new-key":"phone"}}}}},"result":{"AddPublicKeySummariesResult":{"removed":{"old-key":"laptop"}}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let history = tx.get_account_history("hash", 10).unwrap();
+        assert!(history.profile().is_none());
+        assert!(history.external_addresses().is_none());
+        assert_eq!(history.public_key_summaries, Some(serde_json::json!({"new-key": "phone"})));
+        assert_eq!(history.removed_summaries, Some(serde_json::json!({"old-key": "laptop"})));
+    }
+
+    async fn sqlite() -> sea_orm::DatabaseConnection {
+        use sea_orm::{ConnectionTrait, Database, DbBackend, Schema};
+        use lmscan_agent::{account_entity, account_history, account_public_key, external_address};
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(account_entity::Entity),
+            schema.create_table_from_entity(account_history::Entity),
+            schema.create_table_from_entity(account_public_key::Entity),
+            schema.create_table_from_entity(external_address::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        db
+    }
+
+    fn history(hash: &str, block_number: i64, tx: &str) -> lmscan_agent::account_history::Model {
+        let json = format!(r#"{{"signedTx":{{"sig":{{"sig":{{"v":27,"r":"a3","s":"39"}},"account":"alice"}},"value":{{"AccountTx":{tx}}}}},"result":null}}"#);
+        let tx = parse_from_json_str::<TransactionWithResult>(&json).unwrap();
+        tx.get_account_history(hash, block_number).unwrap()
+    }
+
+    #[tokio::test]
+    async fn eth_and_external_updates_keep_each_other() {
+        use lmscan_agent::check_app::save_account_profiles;
+        use lmscan_agent::external_address;
+        use sea_orm::{EntityTrait, TransactionTrait};
+
+        let db = sqlite().await;
+        let external = |chain: &str, addr: &str| {
+            format!(r#"{{"UpdateAccountWithExternalChainAddresses":{{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","account":"alice","guardian":null,"externalChainAddresses":{{"{chain}":"{addr}"}},"memo":null}}}}"#)
+        };
+        let eth = r#"{"UpdateAccount":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","account":"alice","ethAddress":"0xE1","guardian":null}}"#;
+        for (hash, block_number, tx) in [
+            ("h1", 10, external("sol", "S1")),
+            ("h2", 11, eth.to_string()),
+            ("h3", 12, external("btc", "B1")),
+        ] {
+            let txn = db.begin().await.unwrap();
+            save_account_profiles(&txn, vec![history(hash, block_number, &tx)]).await.unwrap();
+            txn.commit().await.unwrap();
+        }
+
+        let active = |chain: &str, addr: &str| (chain.to_string(), addr.to_string());
+        let rows = external_address::Entity::find().all(&db).await.unwrap();
+        let mut on = rows.iter().filter(|m| m.is_active).map(|m| active(&m.chain, &m.external_address)).collect::<Vec<_>>();
+        on.sort();
+        // the eth update didn't drop sol, the later external update drops sol but keeps eth.
+        assert_eq!(on, vec![active("btc", "b1"), active("eth", "0xe1")]);
+        let sol = rows.iter().find(|m| m.chain == "sol").unwrap();
+        assert!(!sol.is_active);
+        assert_eq!(sol.source, external_address::EXTERNAL_CHAIN);
+    }
+}
+
@@ -0,0 +1,262 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::export_app::{export, Dataset, ExportArgs, Format, Range};
+    use lmscan_agent::{agent_cursor, balance_tx, block_entity, block_state, nft_tx, summary, tx_entity, tx_state};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use sea_orm::*;
+    use serde_json::Value;
+
+    const DAY: i64 = 60 * 60 * 24;
+    const MINT: &str = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"minter"},"value":{"TokenTx":{"MintFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","outputs":{"a":100}}}}},"result":null}"#;
+
+    async fn create<E: EntityTrait>(db: &DatabaseConnection, entity: E) {
+        let stmt = Schema::new(DbBackend::Sqlite).create_table_from_entity(entity);
+        db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+    }
+
+    // blocks 1 and 2 on the first day, 3 on the next, one tx each
+    async fn sqlite() -> (DatabaseConnection, DatabaseConnection) {
+        chain(&[(1, 10), (2, 20), (3, DAY + 10)]).await
+    }
+
+    // (number, event_time) blocks with one tx each
+    async fn chain(blocks: &[(i64, i64)]) -> (DatabaseConnection, DatabaseConnection) {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        create(&db, block_entity::Entity).await;
+        create(&db, block_state::Entity).await;
+        create(&db, tx_entity::Entity).await;
+        create(&db, tx_state::Entity).await;
+        create(&db, nft_tx::Entity).await;
+        create(&db, summary::Entity).await;
+        create(&db, agent_cursor::Entity).await;
+        let local = Database::connect("sqlite::memory:").await.unwrap();
+        create(&local, balance_tx::Entity).await;
+        for &(n, time) in blocks {
+            block_entity::ActiveModel::from(block_entity::Model {
+                hash: format!("b{n}"),
+                number: n,
+                parent_hash: format!("b{}", n - 1),
+                tx_count: 1,
+                event_time: time,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            tx_entity::ActiveModel::from(tx_entity::Model {
+                hash: format!("t{n}"),
+                signer: "minter".to_string(),
+                token_type: "LM".to_string(),
+                tx_type: "Token".to_string(),
+                sub_type: "MintFungibleToken".to_string(),
+                block_hash: format!("b{n}"),
+                block_number: n,
+                event_time: time,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            tx_state::ActiveModel::from(tx_state::Model {
+                hash: format!("t{n}"),
+                block_hash: format!("b{n}"),
+                json: MINT.to_string(),
+                event_time: time,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            balance_tx::ActiveModel::from(balance_tx::Model {
+                hash: format!("t{n}"),
+                address: "a".to_string(),
+                free: BigDecimal::from(100),
+                lock: BigDecimal::from(0),
+                spend: false,
+                token: "LM".to_string(),
+            })
+            .reset_all()
+            .insert(&local)
+            .await
+            .unwrap();
+        }
+        (db, local)
+    }
+
+    async fn built(db: &DatabaseConnection, n: i64) {
+        block_state::ActiveModel::from(block_state::Model {
+            hash: format!("b{n}"),
+            number: n,
+            is_build: true,
+            json: String::new(),
+            event_time: 0,
+            created_at: 0,
+        })
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    fn out(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lmscan-export-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn args(format: Format, out: &Path, range: Range) -> ExportArgs {
+        ExportArgs { format, out: out.to_path_buf(), datasets: Dataset::ALL.to_vec(), range }
+    }
+
+    fn lines(path: PathBuf) -> Vec<Value> {
+        fs::read_to_string(path).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_args() {
+        let parse = |s: &str| ExportArgs::parse(&s.split_whitespace().map(String::from).collect::<Vec<_>>());
+        let a = parse("--from-block 5 --format parquet --out /x --datasets block,tx").unwrap();
+        assert_eq!(a.range, Range::Blocks { from: 5, to: None });
+        assert_eq!(a.format, Format::Parquet);
+        assert_eq!(a.out, PathBuf::from("/x"));
+        assert_eq!(a.datasets, vec![Dataset::Block, Dataset::Tx]);
+        assert_eq!(parse("--from-time 1 --to-time 9").unwrap().range, Range::Time { from: 1, to: Some(9) });
+        assert_eq!(parse("--incremental").unwrap().range, Range::Incremental);
+        assert_eq!(parse("--incremental").unwrap().format, Format::Jsonl);
+        assert!(parse("--incremental --to-block 3").is_err());
+        assert!(parse("--from-block 1 --format xml").is_err());
+        assert!(parse("--from-block 1 --datasets block,nope").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[tokio::test]
+    async fn jsonl_partitioned_by_day() {
+        let (db, local) = sqlite().await;
+        let dir = out("jsonl");
+        let report = export(&db, Some(&local), args(Format::Jsonl, &dir, Range::Blocks { from: 1, to: None })).await.unwrap();
+        assert_eq!((report.from, report.to), (1, 3));
+        assert_eq!(report.rows["block"], 3);
+        assert!(!report.rows.contains_key("nft"));
+
+        let blocks = lines(dir.join("block/day=1970-01-01/block_1_3.jsonl"));
+        assert_eq!(blocks.iter().map(|b| b["number"].as_i64().unwrap()).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(lines(dir.join("block/day=1970-01-02/block_1_3.jsonl")).len(), 1);
+
+        let states = lines(dir.join("tx_state/day=1970-01-01/tx_state_1_3.jsonl"));
+        assert_eq!(states[0]["block_number"], 1);
+        assert_eq!(states[0]["transaction"]["signedTx"]["sig"]["account"], "minter");
+        let balances = lines(dir.join("balance_tx/day=1970-01-02/balance_tx_1_3.jsonl"));
+        assert_eq!(balances[0]["hash"], "t3");
+        assert_eq!(balances[0]["block_number"], 3);
+
+        // only files renamed into place remain
+        let tmp = fs::read_dir(dir.join("block/day=1970-01-01")).unwrap().filter(|f| {
+            f.as_ref().unwrap().path().extension().is_some_and(|e| e == "tmp")
+        });
+        assert_eq!(tmp.count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn csv_and_parquet_by_time_range() {
+        let (db, local) = sqlite().await;
+        let dir = out("columnar");
+        let range = Range::Time { from: 0, to: Some(DAY) };
+        let report = export(&db, Some(&local), args(Format::Csv, &dir, range.clone())).await.unwrap();
+        assert_eq!((report.from, report.to), (1, 2));
+        let csv = fs::read_to_string(dir.join("tx/day=1970-01-01/tx_1_2.csv")).unwrap();
+        let mut rows = csv.lines();
+        assert_eq!(rows.next().unwrap(), "hash,signer,token_type,tx_type,sub_type,block_hash,block_number,event_time,created_at");
+        assert_eq!(rows.next().unwrap(), "t1,minter,LM,Token,MintFungibleToken,b1,1,10,0");
+        assert_eq!(rows.count(), 1);
+
+        export(&db, Some(&local), args(Format::Parquet, &dir, range)).await.unwrap();
+        for (dataset, count) in [("block", 2), ("tx_state", 2), ("balance_tx", 2)] {
+            let path = dir.join(format!("{dataset}/day=1970-01-01/{dataset}_1_2.parquet"));
+            let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+            assert_eq!(reader.metadata().file_metadata().num_rows(), count);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn incremental_resumes_from_last_height() {
+        let (db, local) = sqlite().await;
+        let dir = out("incremental");
+        built(&db, 1).await;
+        built(&db, 2).await;
+        let run = || export(&db, Some(&local), args(Format::Jsonl, &dir, Range::Incremental));
+        let first = run().await.unwrap();
+        assert_eq!((first.from, first.to), (1, 2));
+
+        built(&db, 3).await;
+        let second = run().await.unwrap();
+        assert_eq!((second.from, second.to), (3, 3));
+        assert_eq!(second.rows["tx"], 1);
+        assert_eq!(lines(dir.join("tx/day=1970-01-02/tx_3_3.jsonl"))[0]["hash"], "t3");
+
+        let third = run().await.unwrap();
+        assert!(third.files.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn late_signed_tx_after_chunk_boundary() {
+        // block 1001 opens the second chunk, its tx was signed the day before
+        let (db, local) = chain(&(1..=1001).map(|n| (n, if n < 1000 { n } else { DAY + n })).collect::<Vec<_>>()).await;
+        let signed = DAY - 1;
+        tx_entity::Entity::update_many()
+            .col_expr(tx_entity::Column::EventTime, signed.into())
+            .filter(tx_entity::Column::Hash.eq("t1001"))
+            .exec(&db)
+            .await
+            .unwrap();
+        tx_state::Entity::update_many()
+            .col_expr(tx_state::Column::EventTime, signed.into())
+            .filter(tx_state::Column::Hash.eq("t1001"))
+            .exec(&db)
+            .await
+            .unwrap();
+        let dir = out("late-signed");
+        let report = export(&db, Some(&local), args(Format::Jsonl, &dir, Range::Blocks { from: 1, to: None })).await.unwrap();
+        assert_eq!(report.rows["tx"], 1001);
+
+        for dataset in ["tx", "tx_state", "balance_tx"] {
+            let rows = lines(dir.join(format!("{dataset}/day=1970-01-02/{dataset}_1_1001.jsonl")));
+            assert_eq!(rows.iter().map(|r| r["block_number"].as_i64().unwrap()).collect::<Vec<_>>(), vec![1000, 1001]);
+            assert_eq!(rows[1]["event_time"], signed);
+        }
+        assert!(report.files.iter().all(|f| f.exists()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn days_beyond_open_file_limit() {
+        let days = 200;
+        let (db, local) = chain(&(1..=days).map(|n| (n, n * DAY)).collect::<Vec<_>>()).await;
+        let dir = out("many-days");
+
+        // allow far fewer files than the export writes
+        let open = fs::read_dir("/proc/self/fd").unwrap().count() as u64;
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+        let lowered = libc::rlimit { rlim_cur: open + 64, ..limit };
+        unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &lowered) };
+        let report = export(&db, Some(&local), args(Format::Parquet, &dir, Range::Blocks { from: 1, to: None })).await;
+        unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) };
+
+        let report = report.unwrap();
+        assert_eq!(report.rows["block"], days as usize);
+        assert_eq!(report.files.len(), 4 * days as usize);
+        assert!(report.files.iter().all(|f| f.exists()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use lmscan_agent::service::counter_service::Counter;
+    use lmscan_agent::{chain_counter, tx_entity};
+    use sea_orm::sea_query::OnConflict;
+    use sea_orm::*;
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [schema.create_table_from_entity(chain_counter::Entity), schema.create_table_from_entity(tx_entity::Entity)] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        db
+    }
+
+    fn tx(hash: &str) -> tx_entity::ActiveModel {
+        tx_entity::ActiveModel {
+            hash: Set(hash.to_string()),
+            signer: Set("a".to_string()),
+            token_type: Set("LM".to_string()),
+            tx_type: Set("Token".to_string()),
+            sub_type: Set("TransferFungibleToken".to_string()),
+            block_hash: Set("b".to_string()),
+            block_number: Set(1),
+            event_time: Set(0),
+            created_at: Set(0),
+        }
+    }
+
+    #[tokio::test]
+    async fn counts_only_new_rows() {
+        let db = sqlite().await;
+        assert_eq!(Counter::get(&db, chain_counter::TX).await.unwrap(), None);
+        // a block replayed after a restart must not count its txs twice
+        for batch in [vec![tx("t1"), tx("t2")], vec![tx("t2"), tx("t3")]] {
+            let txn = db.begin().await.unwrap();
+            let inserted = tx_entity::Entity::insert_many(batch)
+                .on_conflict(OnConflict::column(tx_entity::Column::Hash).do_nothing().to_owned())
+                .exec_without_returning(&txn)
+                .await
+                .unwrap();
+            Counter::add(&txn, chain_counter::TX, inserted as i64).await.unwrap();
+            txn.commit().await.unwrap();
+        }
+        assert_eq!(Counter::get(&db, chain_counter::TX).await.unwrap(), Some(3));
+        assert_eq!(tx_entity::Entity::find().count(&db).await.unwrap(), 3);
+
+        Counter::set(&db, chain_counter::TX, 10).await.unwrap();
+        Counter::add(&db, chain_counter::TX, 0).await.unwrap();
+        assert_eq!(Counter::get(&db, chain_counter::TX).await.unwrap(), Some(10));
+    }
+
+    #[tokio::test]
+    async fn recount_locks_and_resets() {
+        let db = sqlite().await;
+        tx_entity::Entity::insert_many([tx("t1"), tx("t2")]).exec(&db).await.unwrap();
+        assert_eq!(Counter::recount::<tx_entity::Entity>(&db, chain_counter::TX).await.unwrap(), (None, 2));
+
+        // drifted counter is repaired, later adds apply on top of the recount
+        Counter::add(&db, chain_counter::TX, 5).await.unwrap();
+        assert_eq!(Counter::recount::<tx_entity::Entity>(&db, chain_counter::TX).await.unwrap(), (Some(7), 2));
+        Counter::add(&db, chain_counter::TX, 1).await.unwrap();
+        assert_eq!(Counter::get(&db, chain_counter::TX).await.unwrap(), Some(3));
+    }
+}
+
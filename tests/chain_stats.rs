@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::analytics_app::{active_rows, update_stats, Stats};
+    use lmscan_agent::{agent_cursor, block_entity, block_state, chain_stat, chain_stat_active, tx_entity, tx_state};
+    use sea_orm::*;
+
+    const TRANSFER: &str = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"TokenTx":{"TransferFungibleToken":{"networkId":1000,"createdAt":"2023-05-09T01:50:13Z","tokenDefinitionId":"LM","inputs":["i1"],"outputs":{"bob":30,"alice":70}}}}},"result":null}"#;
+
+    fn block(number: i64, event_time: i64) -> block_entity::Model {
+        block_entity::Model {
+            hash: format!("b{number}"),
+            number,
+            parent_hash: format!("b{}", number - 1),
+            tx_count: 0,
+            event_time,
+            created_at: 0,
+        }
+    }
+
+    fn tx(hash: &str, signer: &str, tx_type: &str, sub_type: &str, block_number: i64, event_time: i64) -> tx_entity::Model {
+        tx_entity::Model {
+            hash: hash.to_string(),
+            signer: signer.to_string(),
+            token_type: "LM".to_string(),
+            tx_type: tx_type.to_string(),
+            sub_type: sub_type.to_string(),
+            block_hash: format!("b{block_number}"),
+            block_number,
+            event_time,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn stats_by_bucket() {
+        let mut stats = Stats::default();
+        stats.add_blocks(&[block(2, 3_610), block(3, 3_630), block(4, 7_200)], Some(3_600));
+        stats.add_txs(&[
+            tx("t1", "alice", "Account", "CreateAccount", 2, 3_610),
+            tx("t2", "alice", "Token", "MintNft", 3, 3_630),
+            tx("t3", "bob", "Token", "MintNft", 4, 7_200),
+        ]);
+        let one = BigDecimal::from(1);
+        assert_eq!(stats.get("hour", 3_600, chain_stat::BLOCKS, ""), Some(&BigDecimal::from(2)));
+        assert_eq!(stats.get("hour", 3_600, chain_stat::BLOCK_TIME_SUM, ""), Some(&BigDecimal::from(30)));
+        assert_eq!(stats.get("hour", 7_200, chain_stat::BLOCK_TIME_SUM, ""), Some(&BigDecimal::from(3_570)));
+        assert_eq!(stats.get("day", 0, chain_stat::NFT_MINT, ""), Some(&BigDecimal::from(2)));
+        assert_eq!(stats.get("hour", 3_600, chain_stat::NEW_ACCOUNTS, ""), Some(&one));
+        assert_eq!(stats.get("hour", 3_600, chain_stat::TX, "Token/MintNft"), Some(&one));
+
+        let active = active_rows(&[tx("a", "alice", "", "", 1, 10), tx("b", "alice", "", "", 1, 20)]);
+        assert_eq!(active.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn stats_on_sqlite() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(block_state::Entity),
+            schema.create_table_from_entity(block_entity::Entity),
+            schema.create_table_from_entity(tx_entity::Entity),
+            schema.create_table_from_entity(tx_state::Entity),
+            schema.create_table_from_entity(agent_cursor::Entity),
+            schema.create_table_from_entity(chain_stat::Entity),
+            schema.create_table_from_entity(chain_stat_active::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        for n in 1..=3 {
+            block_state::ActiveModel {
+                hash: Set(format!("b{n}")),
+                number: Set(n),
+                is_build: Set(true),
+                json: Set("{}".to_string()),
+                event_time: Set(0),
+                created_at: Set(0),
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            block_entity::ActiveModel::from(block(n, n * 10)).reset_all().insert(&db).await.unwrap();
+        }
+        let txs = [
+            tx("t1", "alice", "Token", "TransferFungibleToken", 2, 20),
+            tx("t2", "bob", "Token", "TransferNft", 3, 30),
+        ];
+        for t in txs {
+            tx_entity::ActiveModel::from(t).reset_all().insert(&db).await.unwrap();
+        }
+        tx_state::ActiveModel {
+            hash: Set("t1".to_string()),
+            block_hash: Set("b2".to_string()),
+            json: Set(TRANSFER.to_string()),
+            event_time: Set(20),
+            created_at: Set(0),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        assert_eq!(update_stats(0, &db).await.unwrap(), 3);
+        let stat = |metric: &str, key: &str| {
+            let db = db.clone();
+            let (metric, key) = (metric.to_string(), key.to_string());
+            async move {
+                chain_stat::Entity::find_by_id(("day".to_string(), 0, metric, key))
+                    .one(&db)
+                    .await
+                    .unwrap()
+                    .map(|s| s.value)
+            }
+        };
+        assert_eq!(stat(chain_stat::BLOCKS, "").await, Some(BigDecimal::from(3)));
+        assert_eq!(stat(chain_stat::BLOCK_TIME_SUM, "").await, Some(BigDecimal::from(20)));
+        assert_eq!(stat(chain_stat::TRANSFER_VOLUME, "LM").await, Some(BigDecimal::from_str("30").unwrap()));
+        assert_eq!(stat(chain_stat::ACTIVE_ACCOUNTS, "").await, Some(BigDecimal::from(2)));
+        assert_eq!(stat(chain_stat::NFT_TRANSFER, "").await, Some(BigDecimal::from(1)));
+        // nothing new above the cursor
+        assert_eq!(update_stats(3, &db).await.unwrap(), 3);
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::summary_app::{get_chain_supply, ChainSupply};
+    use lmscan_agent::{address_label, balance_entity, token_definition};
+    use sea_orm::*;
+
+    fn d(v: i64) -> BigDecimal {
+        BigDecimal::from(v)
+    }
+
+    fn balance(address: &str, free: i64, locked: i64) -> balance_entity::ActiveModel {
+        balance_entity::Model {
+            address: address.to_string(),
+            free: d(free),
+            locked: d(locked),
+            free_scaled: d(0),
+            locked_scaled: d(0),
+            created_at: 0,
+            updated_at: 0,
+        }
+        .into_active_model()
+        .reset_all()
+    }
+
+    #[tokio::test]
+    async fn supply_from_chain() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(token_definition::Entity),
+            schema.create_table_from_entity(balance_entity::Entity),
+            schema.create_table_from_entity(address_label::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        assert_eq!(get_chain_supply(&db).await.unwrap(), ChainSupply::default());
+
+        let def = token_definition::Model {
+            total_minted: d(1000),
+            total_burned: d(100),
+            total_locked: d(50),
+            ..token_definition::Model::new("LM".to_string())
+        };
+        token_definition::ActiveModel::from(def).reset_all().insert(&db).await.unwrap();
+        balance_entity::Entity::insert_many([balance("treasury", 300, 20), balance("user", 500, 30)])
+            .exec(&db)
+            .await
+            .unwrap();
+        address_label::ActiveModel::from(address_label::Model {
+            address: "treasury".to_string(),
+            label: "Treasury".to_string(),
+            category: "treasury".to_string(),
+            exclude_from_supply: true,
+            source: "config".to_string(),
+            updated_at: 0,
+        })
+        .reset_all()
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let supply = get_chain_supply(&db).await.unwrap();
+        assert_eq!(supply.treasury, d(300));
+        assert_eq!(supply.circulating(), d(550));
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::service::custody_service::{balance_of_data, parse_hex, BalanceSource, CustodyAddress};
+
+    fn custody(source: BalanceSource) -> CustodyAddress {
+        CustodyAddress {
+            name: "bridge".to_string(),
+            chain: "Ethereum".to_string(),
+            address: "0xABCdef".to_string(),
+            source,
+        }
+    }
+
+    #[test]
+    fn sources_from_json() {
+        let json = r#"[
+            {"name":"a","chain":"ethereum","address":"0x1","source":{"type":"etherscan","url":"https://api.etherscan.io/api","api_key":"k","contract":"0xc"}},
+            {"name":"b","chain":"bsc","address":"0x2","source":{"type":"rpc","url":"http://node","contract":"0xc"}},
+            {"name":"c","chain":"off","address":"cold","source":{"type":"static","balance":"100"}}
+        ]"#;
+        let list: Vec<CustodyAddress> = serde_json::from_str(json).unwrap();
+        assert_eq!(list.iter().map(|c| c.source_name()).collect::<Vec<_>>(), vec!["etherscan", "rpc", "static"]);
+        assert_eq!(list[2].source, BalanceSource::Static { balance: BigDecimal::from(100) });
+    }
+
+    #[test]
+    fn balance_of_call() {
+        assert_eq!(
+            balance_of_data("0xABCdef"),
+            format!("0x70a08231{}abcdef", "0".repeat(58))
+        );
+        assert_eq!(parse_hex("0x0de0b6b3a7640000").unwrap(), BigDecimal::from_str("1000000000000000000").unwrap());
+        assert!(parse_hex("0xzz").is_err());
+    }
+
+    #[tokio::test]
+    async fn failed_source_keeps_last_balance() {
+        let c = custody(BalanceSource::Static { balance: BigDecimal::from(7) });
+        let ok = c.record(c.balance().await, None, 10, 100);
+        assert_eq!((ok.chain.as_str(), ok.address.as_str()), ("ethereum", "0xabcdef"));
+        assert_eq!((ok.balance.clone(), ok.fetched_at, ok.last_error.clone()), (BigDecimal::from(7), 100, None));
+
+        let failed = c.record(Err("timeout".to_string()), Some(ok), 11, 200);
+        assert_eq!((failed.balance, failed.fetched_at, failed.block_number), (BigDecimal::from(7), 100, 11));
+        assert_eq!(failed.last_error.as_deref(), Some("timeout"));
+
+        let first = c.record(Err("timeout".to_string()), None, 11, 200);
+        assert_eq!(first.balance, BigDecimal::from(0));
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use futures::{pin_mut, StreamExt};
+    use lmscan_agent::{agent_cursor, block_state, chain_event};
+    use lmscan_agent::service::event_service::{EventFilter, EventHub};
+    use sea_orm::*;
+    use serde_json::json;
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(chain_event::Entity),
+            schema.create_table_from_entity(block_state::Entity),
+            schema.create_table_from_entity(agent_cursor::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        build(&db, 1).await;
+        db
+    }
+
+    async fn build(db: &DatabaseConnection, number: i64) {
+        block_state::ActiveModel {
+            hash: Set(format!("h{number}")),
+            number: Set(number),
+            is_build: Set(true),
+            json: Set("{}".to_string()),
+            event_time: Set(0),
+            created_at: Set(0),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    fn transfer(from: &str, to: &str, token: &str) -> chain_event::ActiveModel {
+        transfer_at(from, to, token, 1)
+    }
+
+    fn transfer_at(from: &str, to: &str, token: &str, block_number: i64) -> chain_event::ActiveModel {
+        chain_event::Model::event(
+            chain_event::TX,
+            vec![from.to_string(), to.to_string()],
+            Some(token.to_string()),
+            block_number,
+            json!({ "from": from, "to": to }),
+        )
+    }
+
+    #[test]
+    fn filter_by_topic_address_token() {
+        let e = chain_event::Model {
+            id: 1,
+            seq: Some(1),
+            kind: chain_event::TX.to_string(),
+            addresses: json!(["alice", "bob"]),
+            token: Some("LM".to_string()),
+            block_number: 1,
+            payload: json!({}),
+            created_at: 0,
+        };
+        assert!(EventFilter::default().matches(&e));
+        let topics = |t: &str| EventFilter { topics: Some(t.to_string()), ..Default::default() };
+        assert!(topics("block, tx").matches(&e));
+        assert!(!topics("block").matches(&e));
+        let address = |a: &str| EventFilter { address: Some(a.to_string()), ..Default::default() };
+        assert!(address("bob").matches(&e));
+        assert!(!address("carol").matches(&e));
+        let token = |t: &str| EventFilter { token: Some(t.to_string()), ..Default::default() };
+        assert!(token("LM").matches(&e));
+        assert!(!token("NFT").matches(&e));
+    }
+
+    #[tokio::test]
+    async fn sequence_numbers_pending_rows_in_order() {
+        let db = sqlite().await;
+        for to in ["a", "b"] {
+            transfer("x", to, "LM").insert(&db).await.unwrap();
+        }
+        let first = EventHub::sequence(&db).await.unwrap();
+        assert_eq!(first.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+        assert!(EventHub::sequence(&db).await.unwrap().is_empty());
+
+        transfer("x", "c", "LM").insert(&db).await.unwrap();
+        let next = EventHub::sequence(&db).await.unwrap();
+        assert_eq!(next[0].seq, Some(3));
+        assert_eq!(EventHub::backlog(&db, 1, 10).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn resume_from_backlog_then_live() {
+        let db = sqlite().await;
+        for to in ["a", "b", "c"] {
+            transfer("resume", to, "RESUME").insert(&db).await.unwrap();
+        }
+        transfer("other", "d", "OTHER").insert(&db).await.unwrap();
+        EventHub::sequence(&db).await.unwrap();
+
+        let filter = EventFilter { token: Some("RESUME".to_string()), ..Default::default() };
+        let events = EventHub::subscribe(db.clone(), filter, Some(1));
+        pin_mut!(events);
+        assert_eq!(events.next().await.unwrap().seq, Some(2));
+        assert_eq!(events.next().await.unwrap().seq, Some(3));
+
+        transfer("resume", "e", "RESUME").insert(&db).await.unwrap();
+        let live = EventHub::sequence(&db).await.unwrap();
+        // already delivered from the backlog, must not repeat
+        let mut replayed = live.clone();
+        replayed.insert(0, chain_event::Model { seq: Some(3), ..live[0].clone() });
+        EventHub::publish(replayed);
+        let e = events.next().await.unwrap();
+        assert_eq!(e.seq, Some(5));
+        assert_eq!(e.addresses(), vec!["resume", "e"]);
+    }
+
+    #[tokio::test]
+    async fn sequence_follows_chain_order_up_to_built_height() {
+        let db = sqlite().await;
+        // blocks are saved newest first
+        for (to, block_number) in [("d", 4), ("c", 3), ("b", 2), ("a", 1)] {
+            transfer_at("x", to, "LM", block_number).insert(&db).await.unwrap();
+        }
+        build(&db, 3).await;
+        build(&db, 4).await;
+        // block 2 isn't built yet, nothing above it may get a seq
+        let first = EventHub::sequence(&db).await.unwrap();
+        assert_eq!(first.iter().map(|e| (e.block_number, e.seq)).collect::<Vec<_>>(), vec![(1, Some(1))]);
+
+        build(&db, 2).await;
+        let next = EventHub::sequence(&db).await.unwrap();
+        assert_eq!(
+            next.iter().map(|e| (e.block_number, e.seq)).collect::<Vec<_>>(),
+            vec![(2, Some(2)), (3, Some(3)), (4, Some(4))]
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_events_follow_the_block_of_their_change() {
+        let db = sqlite().await;
+        // the balance loop can store its event before the tx of an earlier block is indexed
+        chain_event::Model::event(chain_event::BALANCE, vec!["a".to_string()], Some("LM".to_string()), 2, json!({}))
+            .insert(&db)
+            .await
+            .unwrap();
+        transfer_at("x", "a", "LM", 2).insert(&db).await.unwrap();
+        transfer_at("x", "b", "LM", 1).insert(&db).await.unwrap();
+        assert_eq!(EventHub::sequence(&db).await.unwrap().len(), 1);
+
+        build(&db, 2).await;
+        let kinds = EventHub::sequence(&db).await.unwrap().into_iter().map(|e| (e.block_number, e.kind)).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![(2, chain_event::TX.to_string()), (2, chain_event::BALANCE.to_string())]);
+    }
+
+    #[tokio::test]
+    async fn since_before_pruned_events_is_a_gap() {
+        let db = sqlite().await;
+        for to in ["a", "b", "c", "d"] {
+            transfer("x", to, "LM").insert(&db).await.unwrap();
+        }
+        EventHub::sequence(&db).await.unwrap();
+        assert_eq!(EventHub::pruned_since(&db, 0).await.unwrap(), None);
+        chain_event::Entity::delete_many().filter(chain_event::Column::Seq.lte(2)).exec(&db).await.unwrap();
+        assert_eq!(EventHub::pruned_since(&db, 0).await.unwrap(), Some(3));
+        assert_eq!(EventHub::pruned_since(&db, 1).await.unwrap(), Some(3));
+        assert_eq!(EventHub::pruned_since(&db, 2).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use lmscan_agent::service::graphql_service::schema;
+    use lmscan_agent::{account_entity, account_mapper, block_entity, tx_entity};
+    use sea_orm::*;
+    use serde_json::{json, Value};
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(block_entity::Entity),
+            schema.create_table_from_entity(tx_entity::Entity),
+            schema.create_table_from_entity(account_entity::Entity),
+            schema.create_table_from_entity(account_mapper::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        account_entity::Model::from_address("alice", 0).insert(&db).await.unwrap();
+        for (n, sub_type) in [(1, "CreateAccount"), (2, "TransferFungibleToken"), (3, "TransferFungibleToken")] {
+            block_entity::ActiveModel::from(block_entity::Model {
+                hash: format!("b{n}"),
+                number: n,
+                parent_hash: format!("b{}", n - 1),
+                tx_count: 1,
+                event_time: n * 10,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            tx_entity::ActiveModel::from(tx_entity::Model {
+                hash: format!("t{n}"),
+                signer: "alice".to_string(),
+                token_type: "LM".to_string(),
+                tx_type: if n == 1 { "Account" } else { "Token" }.to_string(),
+                sub_type: sub_type.to_string(),
+                block_hash: format!("b{n}"),
+                block_number: n,
+                event_time: n * 10,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            account_mapper::ActiveModel::from(account_mapper::Model {
+                address: "alice".to_string(),
+                hash: format!("t{n}"),
+                event_time: n * 10,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    async fn run(db: &DatabaseConnection, query: &str) -> (Value, bool) {
+        let res = schema(db.clone()).execute(query).await;
+        (res.data.into_json().unwrap(), !res.errors.is_empty())
+    }
+
+    #[tokio::test]
+    async fn nested_account_txs_block() {
+        let db = sqlite().await;
+        let (data, err) = run(
+            &db,
+            r#"{ account(address: "alice") {
+                transactions(first: 1, filter: { txType: "Token", fromTime: 20 }) {
+                    items { hash block { number } }
+                    nextCursor
+                }
+            } }"#,
+        )
+        .await;
+        assert!(!err);
+        let page = &data["account"]["transactions"];
+        assert_eq!(page["items"], json!([{ "hash": "t3", "block": { "number": 3 } }]));
+        assert_eq!(page["nextCursor"], "30:t3");
+
+        let (data, _) = run(&db, r#"{ txs(after: "30:t3", filter: { subType: "TransferFungibleToken" }) { items { hash } nextCursor } }"#).await;
+        assert_eq!(data["txs"], json!({ "items": [{ "hash": "t2" }], "nextCursor": null }));
+
+        let (data, _) = run(&db, r#"{ blocks(first: 2) { items { number } nextCursor } }"#).await;
+        assert_eq!(data["blocks"]["nextCursor"], "2");
+    }
+
+    #[tokio::test]
+    async fn limits_reject_heavy_queries() {
+        let db = sqlite().await;
+        let deep = r#"{ tx(hash: "t1") { signerAccount { transactions { items { signerAccount { transactions { items { signerAccount { address } } } } } } } } }"#;
+        assert!(run(&db, deep).await.1);
+        let wide = r#"{ blocks(first: 100) { items { transactions(first: 100) { items { hash } } } } }"#;
+        assert!(run(&db, wide).await.1);
+        assert!(!run(&db, r#"{ tx(hash: "t1") { hash } }"#).await.1);
+    }
+}
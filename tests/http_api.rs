@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use lmscan_agent::api_app::{parse_cursor, router};
+    use lmscan_agent::{account_entity, account_mapper, block_entity, tx_entity, tx_state};
+    use sea_orm::*;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(block_entity::Entity),
+            schema.create_table_from_entity(tx_entity::Entity),
+            schema.create_table_from_entity(tx_state::Entity),
+            schema.create_table_from_entity(account_entity::Entity),
+            schema.create_table_from_entity(account_mapper::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        account_entity::Model::from_address("alice", 0).insert(&db).await.unwrap();
+        for n in 1..=3 {
+            block_entity::ActiveModel::from(block_entity::Model {
+                hash: format!("b{n}"),
+                number: n,
+                parent_hash: format!("b{}", n - 1),
+                tx_count: 1,
+                event_time: n * 10,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            tx_entity::ActiveModel::from(tx_entity::Model {
+                hash: format!("t{n}"),
+                signer: "alice".to_string(),
+                token_type: "LM".to_string(),
+                tx_type: "Token".to_string(),
+                sub_type: "TransferFungibleToken".to_string(),
+                block_hash: format!("b{n}"),
+                block_number: n,
+                event_time: n * 10,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+            account_mapper::ActiveModel::from(account_mapper::Model {
+                address: "alice".to_string(),
+                hash: format!("t{n}"),
+                event_time: n * 10,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    async fn get(db: &DatabaseConnection, uri: &str) -> (StatusCode, Value) {
+        let res = router(db.clone())
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = res.status();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn cursor_format() {
+        assert_eq!(parse_cursor("30:t3"), Some((30, "t3".to_string())));
+        assert_eq!(parse_cursor("t3"), None);
+    }
+
+    #[tokio::test]
+    async fn blocks_and_txs() {
+        let db = sqlite().await;
+        let (status, body) = get(&db, "/v1/blocks?limit=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["number"], 3);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        assert_eq!(get(&db, "/v1/blocks/2").await.1["hash"], "b2");
+        assert_eq!(get(&db, "/v1/blocks/b1").await.1["number"], 1);
+        let (status, body) = get(&db, "/v1/blocks/9").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "block not found");
+
+        let (_, body) = get(&db, "/v1/txs/t1").await;
+        assert_eq!(body["tx"]["block_number"], 1);
+        assert!(body["transaction"].is_null());
+    }
+
+    #[tokio::test]
+    async fn account_txs_paginate() {
+        let db = sqlite().await;
+        let (_, page) = get(&db, "/v1/accounts/alice/txs?limit=2").await;
+        let hashes = |p: &Value| p["items"].as_array().unwrap().iter().map(|t| t["hash"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(hashes(&page), vec!["t3", "t2"]);
+        assert_eq!(page["next_cursor"], "20:t2");
+
+        let (_, page) = get(&db, "/v1/accounts/alice/txs?limit=2&cursor=20:t2").await;
+        assert_eq!(hashes(&page), vec!["t1"]);
+        assert!(page["next_cursor"].is_null());
+
+        assert_eq!(get(&db, "/v1/accounts/alice/txs?cursor=bad").await.0, StatusCode::BAD_REQUEST);
+    }
+}
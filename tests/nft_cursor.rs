@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use lmscan_agent::library::common::now;
+    use lmscan_agent::service::cursor_service::Cursor;
+    use lmscan_agent::nft_app::get_nft_tx;
+    use lmscan_agent::{agent_cursor, block_state, nft_tx, tx_state};
+    use sea_orm::*;
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        let stmt = schema.create_table_from_entity(block_state::Entity);
+        let stmt2 = schema.create_table_from_entity(agent_cursor::Entity);
+        db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        db.execute(db.get_database_backend().build(&stmt2)).await.unwrap();
+        for stmt in [schema.create_table_from_entity(nft_tx::Entity), schema.create_table_from_entity(tx_state::Entity)] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        db
+    }
+
+    fn block(number: i64, is_build: bool) -> block_state::ActiveModel {
+        block_state::ActiveModel {
+            hash: Set(format!("h{number}")),
+            number: Set(number),
+            is_build: Set(is_build),
+            json: Set("{}".to_string()),
+            event_time: Set(0),
+            created_at: Set(now()),
+        }
+    }
+
+    #[test]
+    fn contiguous_height() {
+        assert_eq!(Cursor::contiguous_height(0, &[1, 2, 4]), 2);
+        assert_eq!(Cursor::contiguous_height(5, &[6, 6, 7]), 7);
+        assert_eq!(Cursor::contiguous_height(5, &[7]), 5);
+    }
+
+    #[tokio::test]
+    async fn cursor_on_sqlite() {
+        let db = sqlite().await;
+        block_state::Entity::insert_many([block(1, true), block(2, true), block(3, false), block(4, true)])
+            .exec(&db)
+            .await
+            .unwrap();
+        assert_eq!(Cursor::built_height(&db, 0).await.unwrap(), 2);
+
+        assert_eq!(Cursor::get(&db, "nft").await.unwrap(), 0);
+        Cursor::set(&db, "nft", 2).await.unwrap();
+        Cursor::set(&db, "nft", 4).await.unwrap();
+        assert_eq!(Cursor::get(&db, "nft").await.unwrap(), 4);
+    }
+
+    fn nft(number: i64) -> (nft_tx::ActiveModel, tx_state::ActiveModel) {
+        let nft = nft_tx::Model {
+            tx_hash: format!("t{number}"),
+            token_id: "1".to_string(),
+            action: "TransferNft".to_string(),
+            from_addr: "alice".to_string(),
+            to_addr: "bob".to_string(),
+            block_number: number,
+            tx_index: 0,
+            event_time: 0,
+            created_at: 0,
+        };
+        let state = tx_state::Model {
+            hash: format!("t{number}"),
+            block_hash: format!("h{number}"),
+            json: "{}".to_string(),
+            event_time: 0,
+            created_at: 0,
+        };
+        (nft.into_active_model(), state.into_active_model())
+    }
+
+    #[tokio::test]
+    async fn nft_cursor_waits_for_a_rebuilt_gap() {
+        let db = sqlite().await;
+        let mut old = block(3, false);
+        old.created_at = Set(100);
+        block_state::Entity::insert_many([block(1, true), block(2, true), old, block(4, true)])
+            .exec(&db)
+            .await
+            .unwrap();
+        for n in [2, 3, 4] {
+            let (t, state) = nft(n);
+            nft_tx::Entity::insert(t).exec(&db).await.unwrap();
+            tx_state::Entity::insert(state).exec(&db).await.unwrap();
+        }
+
+        // block 3 failed to build, the cursor stops right below it
+        let (upper, txs) = get_nft_tx(0, &db).await.unwrap();
+        assert_eq!(upper, 2);
+        assert_eq!(txs.iter().map(|(r, _)| r.block_number).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(get_nft_tx(upper, &db).await.unwrap(), (2, vec![]));
+
+        // the gap below the head is found for rebuilding, a fresh one is left to its build task
+        let stalled = Cursor::unbuilt_below_head(&db, 200, 10).await.unwrap();
+        assert_eq!(stalled.iter().map(|b| b.number).collect::<Vec<_>>(), vec![3]);
+        assert!(Cursor::unbuilt_below_head(&db, 50, 10).await.unwrap().is_empty());
+
+        block_state::Entity::update_many()
+            .col_expr(block_state::Column::IsBuild, sea_query::Expr::value(true))
+            .filter(block_state::Column::Number.eq(3))
+            .exec(&db)
+            .await
+            .unwrap();
+        let (upper, txs) = get_nft_tx(upper, &db).await.unwrap();
+        assert_eq!(upper, 4);
+        assert_eq!(txs.iter().map(|(r, _)| r.block_number).collect::<Vec<_>>(), vec![3, 4]);
+        assert!(Cursor::unbuilt_below_head(&db, 200, 10).await.unwrap().is_empty());
+    }
+}
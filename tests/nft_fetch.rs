@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use lmscan_agent::nft_app::NftMetaInfo;
+    use lmscan_agent::nft_fetch_app::{backoff_secs, claim, complete, enqueue_missing};
+    use lmscan_agent::{nft_fetch, nft_file};
+    use sea_orm::*;
+
+    #[test]
+    fn backoff_doubles_until_a_day() {
+        assert_eq!(backoff_secs(0), 60);
+        assert_eq!(backoff_secs(1), 120);
+        assert_eq!(backoff_secs(5), 60 * 32);
+        assert_eq!(backoff_secs(20), 60 * 60 * 24);
+        assert_eq!(backoff_secs(1000), 60 * 60 * 24);
+    }
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [schema.create_table_from_entity(nft_fetch::Entity), schema.create_table_from_entity(nft_file::Entity)] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        db
+    }
+
+    fn file(token_id: &str, nft_name: &str) -> nft_file::ActiveModel {
+        nft_file::ActiveModel::from(nft_file::Model {
+            token_id: token_id.to_string(),
+            token_def_id: "def".to_string(),
+            collection_name: String::new(),
+            nft_name: nft_name.to_string(),
+            nft_uri: String::new(),
+            creator_description: String::new(),
+            data_url: format!("https://meta.example/{token_id}.json"),
+            content_hash: String::new(),
+            rarity: String::new(),
+            creator: String::new(),
+            event_time: 0,
+            created_at: 0,
+        })
+        .reset_all()
+    }
+
+    async fn job(db: &DatabaseConnection, token_id: &str) -> nft_fetch::Model {
+        nft_fetch::Entity::find_by_id(token_id.to_string()).one(db).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn enqueue_claim_and_succeed() {
+        let db = sqlite().await;
+        nft_file::Entity::insert_many([file("1", ""), file("2", "named")]).exec(&db).await.unwrap();
+        enqueue_missing(&db).await.unwrap();
+        // only the row written without metadata is queued
+        let queued = nft_fetch::Entity::find().all(&db).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].status, "pending");
+
+        let jobs = claim(&db, 1000, 10).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(job(&db, "1").await.status, "running");
+        // a leased job isn't handed out twice
+        assert!(claim(&db, 1000, 10).await.unwrap().is_empty());
+
+        let info = NftMetaInfo { nft_name: "first".to_string(), ..Default::default() };
+        complete(&db, &jobs[0], Ok(info), 1000).await.unwrap();
+        let done = job(&db, "1").await;
+        assert_eq!(done.status, "done");
+        assert_eq!(done.fetched_at, Some(1000));
+        assert!(done.next_attempt_at > 1000);
+        let f = nft_file::Entity::find_by_id("1".to_string()).one(&db).await.unwrap().unwrap();
+        assert_eq!(f.nft_name, "first");
+    }
+
+    #[tokio::test]
+    async fn retry_with_backoff_then_give_up() {
+        let db = sqlite().await;
+        nft_fetch::Entity::insert(nft_fetch::Model::pending("1".to_string(), "https://meta.example/1.json".to_string()))
+            .exec(&db)
+            .await
+            .unwrap();
+
+        let mut now = 1000;
+        for attempt in 0..10 {
+            let jobs = claim(&db, now, 10).await.unwrap();
+            assert_eq!(jobs.len(), 1, "attempt {attempt}");
+            complete(&db, &jobs[0], Err("timeout".to_string()), now).await.unwrap();
+            let j = job(&db, "1").await;
+            assert_eq!(j.attempts, attempt + 1);
+            assert_eq!(j.last_error.as_deref(), Some("timeout"));
+            if attempt < 9 {
+                assert_eq!(j.status, "failed");
+                assert_eq!(j.next_attempt_at, now + backoff_secs(attempt));
+                // not due before the backoff ends
+                assert!(claim(&db, j.next_attempt_at - 1, 10).await.unwrap().is_empty());
+                now = j.next_attempt_at;
+            }
+        }
+        // NFT_FETCH_MAX_ATTEMPTS defaults to 10
+        assert_eq!(job(&db, "1").await.status, "gave_up");
+        assert!(claim(&db, i64::MAX, 10).await.unwrap().is_empty());
+    }
+}
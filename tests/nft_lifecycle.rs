@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use lmscan_agent::library::common::parse_from_json_str;
+    use lmscan_agent::library::common::now;
+    use lmscan_agent::balance_app::nft_holder_counts;
+    use lmscan_agent::{nft_collection, nft_owner};
+    use sea_orm::*;
+    use lmscan_agent::transaction::token_transaction::{NftStatus, TokenTx};
+    use lmscan_agent::transaction::{Transaction, TransactionWithResult};
+
+    fn token_tx(signer: &str, tx: &str) -> TokenTx {
+        let json = format!(r#"{{"signedTx":{{"sig":{{"sig":{{"v":27,"r":"a3","s":"39"}},"account":"{signer}"}},"value":{{"TokenTx":{tx}}}}},"result":null}}"#);
+        match parse_from_json_str::<TransactionWithResult>(&json).unwrap().signed_tx.value {
+            Transaction::TokenTx(t) => t,
+            _ => panic!("not a token tx"),
+        }
+    }
+
+    #[test]
+    fn entrust_and_dispose() {
+        let entrust = token_tx("alice", r#"{"EntrustNFT":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"nft-1","tokenId":"1","input":"h1","to":"custody"}}"#);
+        let t = entrust.get_nft_transition("alice").unwrap();
+        assert_eq!(t.status, NftStatus::Entrusted);
+        assert_eq!(t.owner, Some("alice".to_string()));
+        assert_eq!(t.custodian, Some("custody".to_string()));
+
+        // no output returns the nft to its owner
+        let dispose = token_tx("custody", r#"{"DisposeEntrustedNFT":{"networkId":1000,"createdAt":"2023-11-07T05:55:27.867Z","definitionId":"nft-1","tokenId":"1","input":"h2","output":null}}"#);
+        let t = dispose.get_nft_transition("custody").unwrap();
+        assert_eq!(t.status, NftStatus::Owned);
+        assert_eq!(t.owner, None);
+        assert_eq!(t.custodian, None);
+    }
+
+    #[test]
+    fn burn_and_update() {
+        let burn = token_tx("alice", r#"{"BurnNFT":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"nft-1","input":"h1"}}"#);
+        let t = burn.get_nft_transition("alice").unwrap();
+        assert_eq!(t.status, NftStatus::Burned);
+        assert_eq!(t.token_id, "");
+        assert_eq!(burn.sub_type(), "BurnNft");
+
+        let update = token_tx("minter", r#"{"UpdateNFT":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","tokenDefinitionId":"nft-1","tokenId":"1","rarity":"LGDY","dataUrl":"https://example.com/1.json","contentHash":"abc","output":"alice","memo":null}}"#);
+        let t = update.get_nft_transition("minter").unwrap();
+        assert_eq!(t.status, NftStatus::Updated);
+        assert_eq!(t.token_id, "1");
+        assert_eq!(t.definition_id, "nft-1");
+        assert_eq!(t.rarity, Some("LGDY".to_string()));
+        assert_eq!(t.owner, Some("alice".to_string()));
+    }
+
+    #[test]
+    fn collection_rarity_stats() {
+        let weights = Some(serde_json::json!({"LGDY": "100", "UNIQ": "50", "EPIC": null}));
+        let counts = vec![
+            (Some("LGDY".to_string()), 2),
+            (Some("UNIQ".to_string()), 3),
+            (Some("EPIC".to_string()), 1),
+            (None, 1),
+        ];
+        let (rarity_counts, unranked) = nft_collection::Model::rarity_stats(&counts, &weights);
+        assert_eq!(rarity_counts, serde_json::json!({"": 1, "EPIC": 1, "LGDY": 2, "UNIQ": 3}));
+        assert_eq!(unranked, 2);
+
+        let (_, unranked) = nft_collection::Model::rarity_stats(&counts, &None);
+        assert_eq!(unranked, 0);
+    }
+
+    fn owner(owner: &str, block_number: i64, tx_index: i32) -> nft_owner::ActiveModel {
+        nft_owner::Model {
+            token_id: "1".to_string(),
+            definition_id: "nft-1".to_string(),
+            owner: owner.to_string(),
+            status: "owned".to_string(),
+            custodian: None,
+            rarity: None,
+            block_number,
+            tx_index,
+            // skewed client clock, must not matter
+            event_time: 100 - block_number,
+            created_at: now(),
+        }
+        .into_active_model()
+    }
+
+    #[tokio::test]
+    async fn owner_follows_chain_order() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let stmt = Schema::new(DbBackend::Sqlite).create_table_from_entity(nft_owner::Entity);
+        db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+
+        for (name, block_number, tx_index) in [("bob", 5, 2), ("alice", 5, 1), ("carol", 4, 9), ("dave", 5, 3)] {
+            nft_owner::Entity::insert(owner(name, block_number, tx_index))
+                .on_conflict(nft_owner::Model::on_conflict_newer())
+                .exec_without_returning(&db)
+                .await
+                .unwrap();
+            let current = nft_owner::Entity::find_by_id("1".to_string()).one(&db).await.unwrap().unwrap();
+            let expected = if name == "dave" { "dave" } else { "bob" };
+            assert_eq!(current.owner, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn nft_holders_per_definition() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let stmt = Schema::new(DbBackend::Sqlite).create_table_from_entity(nft_owner::Entity);
+        db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+
+        for (token_id, definition_id, name, status) in [
+            ("1", "nft-1", "alice", "owned"),
+            ("2", "nft-1", "alice", "minted"),
+            ("3", "nft-1", "bob", "entrusted"),
+            ("4", "nft-1", "carol", "burned"),
+            ("5", "nft-2", "carol", "owned"),
+        ] {
+            let mut m = owner(name, 1, 0);
+            m.token_id = Set(token_id.to_string());
+            m.definition_id = Set(definition_id.to_string());
+            m.status = Set(status.to_string());
+            m.insert(&db).await.unwrap();
+        }
+        let mut counts = nft_holder_counts(&db).await.unwrap();
+        counts.sort();
+        assert_eq!(counts, vec![("nft-1".to_string(), 2), ("nft-2".to_string(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn collection_deltas_match_recount() {
+        use std::collections::HashMap;
+        use lmscan_agent::nft_app::{apply_collection_deltas, collection_deltas, recount_collection};
+        use lmscan_agent::{nft_file, nft_history, token_definition};
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(nft_owner::Entity),
+            schema.create_table_from_entity(nft_history::Entity),
+            schema.create_table_from_entity(nft_collection::Entity),
+            schema.create_table_from_entity(token_definition::Entity),
+            schema.create_table_from_entity(nft_file::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        let definition = token_definition::Model {
+            rarity: Some(serde_json::json!({"LGDY": "100", "UNIQ": "50"})),
+            ..token_definition::Model::new("nft-1".to_string())
+        };
+        token_definition::Entity::insert(definition.into_active_model()).exec(&db).await.unwrap();
+
+        // (token, owner, status, rarity, action, event time) per batch, one tx per row
+        let batches = [
+            vec![
+                ("1", "alice", "minted", Some("LGDY"), "MintNft", 10),
+                ("2", "alice", "minted", Some("UNIQ"), "MintNft", 11),
+                ("3", "bob", "minted", None, "MintNftWithMemo", 12),
+            ],
+            vec![
+                ("1", "bob", "owned", Some("LGDY"), "TransferNft", 20),
+                ("2", "carol", "owned", Some("UNIQ"), "TransferNft", 21),
+                ("2", "alice", "owned", Some("UNIQ"), "TransferNft", 22),
+            ],
+            vec![
+                ("3", "bob", "burned", None, "BurnNft", 30),
+                ("4", "dave", "minted", Some("EPIC"), "MintNft", 5),
+                ("1", "bob", "entrusted", Some("LGDY"), "EntrustNft", 31),
+            ],
+        ];
+        for (block_number, batch) in batches.into_iter().enumerate() {
+            let ids = batch.iter().map(|b| b.0.to_string()).collect::<Vec<_>>();
+            let before: HashMap<String, nft_owner::Model> = nft_owner::Entity::find()
+                .filter(nft_owner::Column::TokenId.is_in(ids))
+                .all(&db)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|o| (o.token_id.clone(), o))
+                .collect();
+            let mut after: HashMap<String, nft_owner::Model> = HashMap::new();
+            let mut history = vec![];
+            for (tx_index, (token_id, name, status, rarity, action, event_time)) in batch.into_iter().enumerate() {
+                let mut o = owner(name, block_number as i64, tx_index as i32).try_into_model().unwrap();
+                o.token_id = token_id.to_string();
+                o.status = status.to_string();
+                o.rarity = rarity.map(|r| r.to_string());
+                after.insert(o.token_id.clone(), o.clone());
+                history.push(nft_history::Model {
+                    tx_hash: format!("h{block_number}-{tx_index}"),
+                    token_id: o.token_id.clone(),
+                    definition_id: o.definition_id.clone(),
+                    action: action.to_string(),
+                    status: o.status.clone(),
+                    owner: o.owner.clone(),
+                    custodian: None,
+                    signer: o.owner.clone(),
+                    block_number: o.block_number,
+                    tx_index: o.tx_index,
+                    event_time,
+                    created_at: 0,
+                });
+            }
+            let after = after.into_values().collect::<Vec<_>>();
+            let deltas = collection_deltas(&before, &after, &history, &db).await.unwrap();
+            for o in after {
+                nft_owner::Entity::insert(o.into_active_model())
+                    .on_conflict(nft_owner::Model::on_conflict_newer())
+                    .exec_without_returning(&db)
+                    .await
+                    .unwrap();
+            }
+            nft_history::Entity::insert_many(history.into_iter().map(|h| h.into_active_model()))
+                .exec(&db)
+                .await
+                .unwrap();
+            apply_collection_deltas(deltas, &db).await.unwrap();
+
+            let kept = nft_collection::Entity::find_by_id("nft-1".to_string()).one(&db).await.unwrap().unwrap();
+            let recount = recount_collection("nft-1", &db).await.unwrap().try_into_model().unwrap();
+            assert_eq!(nft_collection::Model { updated_at: 0, ..kept }, nft_collection::Model { updated_at: 0, ..recount }, "batch {block_number}");
+        }
+    }
+}
+
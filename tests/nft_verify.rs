@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use lmscan_agent::nft_file;
+    use lmscan_agent::nft_verify_app::{cache_path, hash_matches, sha256_hex, verify};
+    use lmscan_agent::service::url_resolver::UrlResolver;
+    use sea_orm::ActiveValue::Set;
+
+    #[test]
+    fn content_hash_match() {
+        let hash = sha256_hex(b"hello");
+        assert_eq!(hash, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert!(hash_matches("0x2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824", &hash));
+        assert!(!hash_matches("", &hash));
+        assert!(!hash_matches("abc", &hash));
+    }
+
+    #[test]
+    fn content_addressed_path() {
+        let path = cache_path(Path::new("/cache"), "2cf24d");
+        assert_eq!(path, Path::new("/cache/2c/2cf24d"));
+    }
+
+    fn file(token_id: &str, data_url: &str, content_hash: &str) -> nft_file::Model {
+        nft_file::Model {
+            token_id: token_id.to_string(),
+            token_def_id: "nft-1".to_string(),
+            collection_name: String::new(),
+            nft_name: String::new(),
+            nft_uri: String::new(),
+            creator_description: String::new(),
+            data_url: data_url.to_string(),
+            content_hash: content_hash.to_string(),
+            rarity: String::new(),
+            creator: String::new(),
+            event_time: 0,
+            created_at: 0,
+        }
+    }
+
+    fn metadata(nft_uri: &str, checksum: &str) -> Vec<u8> {
+        serde_json::json!({
+            "Creator_description": "", "Collection_description": "", "Rarity": "LGDY", "NFT_checksum": checksum,
+            "Collection_name": "c", "Creator": "alice", "NFT_name": "n", "NFT_URI": nft_uri,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    // ipfs://<name> is served from <mirror>/ipfs/<name>, no gateways so nothing leaves the disk
+    fn mirror(dir: &Path, name: &str, bytes: &[u8]) -> String {
+        fs::create_dir_all(dir.join("ipfs")).unwrap();
+        fs::write(dir.join("ipfs").join(name), bytes).unwrap();
+        format!("ipfs://{name}")
+    }
+
+    #[tokio::test]
+    async fn verify_statuses() {
+        let dir = std::env::temp_dir().join(format!("lmscan-verify-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (mirror_dir, cache_dir) = (dir.join("mirror"), dir.join("cache"));
+        let resolver = UrlResolver::new("", "", Some(mirror_dir.clone()), 1024 * 1024, 1);
+        let media = b"media bytes";
+        let media_uri = mirror(&mirror_dir, "media", media);
+        let status = |am: &lmscan_agent::nft_verification::ActiveModel| am.status.clone().unwrap();
+
+        let meta = metadata(&media_uri, &sha256_hex(media));
+        let url = mirror(&mirror_dir, "verified", &meta);
+        let am = verify(&resolver, &file("1", &url, &format!("0x{}", sha256_hex(&meta))), Some(&cache_dir)).await;
+        assert_eq!(status(&am), "verified");
+        assert_eq!((am.content_hash_match.clone(), am.checksum_match.clone()), (Set(Some(true)), Set(Some(true))));
+        assert_eq!(am.media_size, Set(Some(media.len() as i64)));
+        let cached = PathBuf::from(am.media_path.clone().unwrap().unwrap());
+        assert_eq!(cached, cache_path(&cache_dir, &sha256_hex(media)));
+        assert_eq!(fs::read(cached).unwrap(), media);
+
+        // the media no longer matches its checksum
+        let meta = metadata(&media_uri, &sha256_hex(b"other media"));
+        let url = mirror(&mirror_dir, "tampered", &meta);
+        let am = verify(&resolver, &file("2", &url, &sha256_hex(&meta)), None).await;
+        assert_eq!(status(&am), "tampered");
+        assert_eq!((am.content_hash_match.clone(), am.checksum_match.clone()), (Set(Some(true)), Set(Some(false))));
+        assert_eq!(am.media_path, sea_orm::ActiveValue::NotSet);
+
+        let url = mirror(&mirror_dir, "invalid", b"not json");
+        let am = verify(&resolver, &file("3", &url, &sha256_hex(b"not json")), None).await;
+        assert_eq!(status(&am), "invalid");
+        assert!(am.last_error.clone().unwrap().is_some());
+
+        let am = verify(&resolver, &file("4", "ipfs://missing", ""), None).await;
+        assert_eq!(status(&am), "dead");
+        let meta = metadata("ipfs://missing-media", "");
+        let url = mirror(&mirror_dir, "dead-media", &meta);
+        let am = verify(&resolver, &file("5", &url, ""), None).await;
+        assert_eq!(status(&am), "dead");
+        assert_eq!(am.content_hash_match, Set(Some(false)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::library::common::{bucket, DAY, HOUR, MINUTE};
+    use lmscan_agent::price_app::{build_candles, is_new_sample, price_at, save_candles};
+    use lmscan_agent::service::price_service::PriceQuote;
+    use lmscan_agent::{price_backfill, price_candle};
+    use sea_orm::*;
+
+    fn d(s: &str) -> BigDecimal {
+        BigDecimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn candles_per_bucket() {
+        assert_eq!(bucket(3_661, 3_600), 3_600);
+        let points = vec![(3_700, d("2")), (3_610, d("1")), (3_900, d("5")), (7_300, d("3"))];
+        let candles = build_candles(&points, &[HOUR], "test", 0);
+        assert_eq!(candles.len(), 2);
+        let c = &candles[0];
+        assert_eq!((c.open_time, c.sample_count), (3_600, 3));
+        assert_eq!((c.open.clone(), c.high.clone(), c.low.clone(), c.close.clone()), (d("1"), d("5"), d("1"), d("5")));
+        assert_eq!(candles[1].open, d("3"));
+    }
+
+    #[tokio::test]
+    async fn candles_merge_on_sqlite() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let stmt = Schema::new(DbBackend::Sqlite).create_table_from_entity(price_candle::Entity);
+        db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+
+        save_candles(&db, build_candles(&[(120, d("2")), (130, d("4"))], &[MINUTE, DAY], "a", 0)).await.unwrap();
+        // a late quote older than the stored close keeps the close
+        save_candles(&db, build_candles(&[(125, d("1"))], &[MINUTE, DAY], "a", 0)).await.unwrap();
+        save_candles(&db, build_candles(&[(170, d("6"))], &[MINUTE, DAY], "a", 0)).await.unwrap();
+
+        let m = price_candle::Entity::find_by_id(("minute".to_string(), 120)).one(&db).await.unwrap().unwrap();
+        assert_eq!((m.open, m.high, m.low, m.close, m.sample_count), (d("2"), d("6"), d("1"), d("6"), 4));
+        let day = price_candle::Entity::find_by_id(("day".to_string(), 0)).one(&db).await.unwrap().unwrap();
+        assert_eq!(day.close_time, 170);
+
+        assert_eq!(price_at(&db, 125).await.unwrap(), Some(d("6")));
+        assert_eq!(price_at(&db, 86_400 * 3).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn repeated_vendor_quote_is_skipped() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [schema.create_table_from_entity(price_candle::Entity), schema.create_table_from_entity(price_backfill::Entity)] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        let q = |updated_at: i64| PriceQuote {
+            source: "a".to_string(),
+            price: d("2"),
+            market_cap: None,
+            circulating_supply: None,
+            updated_at,
+        };
+        assert!(is_new_sample(&db, &q(130)).await.unwrap());
+        save_candles(&db, build_candles(&[(130, d("2"))], &[MINUTE], "a", 0)).await.unwrap();
+        // the same vendor update polled again
+        assert!(!is_new_sample(&db, &q(130)).await.unwrap());
+        assert!(is_new_sample(&db, &q(150)).await.unwrap());
+
+        // backfill progress is a timestamp of its own, not a block cursor
+        assert_eq!(price_backfill::Model::get(&db, "price_backfill").await.unwrap(), None);
+        price_backfill::Model::set(&db, "price_backfill", 1_700_000_000).await.unwrap();
+        price_backfill::Model::set(&db, "price_backfill", 1_700_086_400).await.unwrap();
+        assert_eq!(price_backfill::Model::get(&db, "price_backfill").await.unwrap(), Some(1_700_086_400));
+    }
+}
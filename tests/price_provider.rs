@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::service::price_service::{aggregate, median, PriceProvider, PriceQuote, PriceService, StaticPrice};
+    use sea_orm::prelude::async_trait::async_trait;
+
+    fn quote(source: &str, price: &str, updated_at: i64) -> PriceQuote {
+        PriceQuote {
+            source: source.to_string(),
+            price: BigDecimal::from_str(price).unwrap(),
+            market_cap: None,
+            circulating_supply: None,
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn median_of_prices() {
+        let d = |s: &str| BigDecimal::from_str(s).unwrap();
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![d("3"), d("1"), d("2")]), Some(d("2")));
+        assert_eq!(median(vec![d("4"), d("1"), d("2"), d("3")]), Some(d("2.5")));
+    }
+
+    #[test]
+    fn stale_quotes_are_ignored() {
+        let now = 10_000;
+        let quotes = vec![quote("a", "1", now - 10), quote("b", "100", now - 7200), quote("c", "3", now)];
+        let q = aggregate(quotes, now, 3600).unwrap();
+        assert_eq!(q.price, BigDecimal::from(2));
+        assert_eq!(q.source, "a,c");
+
+        assert!(aggregate(vec![quote("b", "100", now - 7200)], now, 3600).is_none());
+    }
+
+    #[test]
+    fn market_cap_from_supply() {
+        let mut q = quote("a", "0.5", 100);
+        q.circulating_supply = Some(BigDecimal::from(1000));
+        let q = aggregate(vec![q], 100, 60).unwrap();
+        assert_eq!(q.market_cap, Some(BigDecimal::from(500)));
+    }
+
+    #[tokio::test]
+    async fn static_price_from_file() {
+        let path = std::env::temp_dir().join(format!("price_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"price":"0.0123","market_cap":null,"circulating_supply":"1000"}"#).unwrap();
+        let provider = StaticPrice { quote: None, path: Some(path.to_string_lossy().to_string()) };
+        let q = provider.quote().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(q.price, BigDecimal::from_str("0.0123").unwrap());
+        assert_eq!(q.source, "static");
+        assert!(q.updated_at > 0);
+    }
+
+    struct Hanging;
+
+    #[async_trait]
+    impl PriceProvider for Hanging {
+        fn name(&self) -> String {
+            "hanging".to_string()
+        }
+
+        async fn quote(&self) -> Result<PriceQuote, String> {
+            tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+            Ok(quote("hanging", "100", lmscan_agent::library::common::now()))
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_provider_times_out() {
+        let fast = StaticPrice { quote: Some(quote("", "0.5", 0)), path: None };
+        let service = PriceService::new(vec![Box::new(Hanging), Box::new(fast)], 3600, std::time::Duration::from_millis(100), 0);
+        let q = tokio::time::timeout(std::time::Duration::from_secs(5), service.quote()).await.unwrap().unwrap();
+        assert_eq!(q.source, "static");
+        assert_eq!(q.price, BigDecimal::from_str("0.5").unwrap());
+
+        let hanging = PriceService::new(vec![Box::new(Hanging)], 3600, std::time::Duration::from_millis(100), 0);
+        assert!(hanging.quote().await.is_none());
+    }
+
+    struct Counting(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    #[async_trait]
+    impl PriceProvider for Counting {
+        fn name(&self) -> String {
+            "counting".to_string()
+        }
+
+        async fn quote(&self) -> Result<PriceQuote, String> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(quote("counting", "1", lmscan_agent::library::common::now()))
+        }
+    }
+
+    #[tokio::test]
+    async fn quote_is_shared_within_cache() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let service = PriceService::new(vec![Box::new(Counting(calls.clone()))], 3600, std::time::Duration::from_secs(5), 60);
+        let (a, b) = tokio::join!(service.quote(), service.quote());
+        assert_eq!(a, b);
+        assert!(service.quote().await.is_some());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let uncached = PriceService::new(vec![Box::new(Counting(calls.clone()))], 3600, std::time::Duration::from_secs(5), 0);
+        uncached.quote().await;
+        uncached.quote().await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::library::common::parse_from_json_str;
+    use lmscan_agent::transaction::TransactionWithResult;
+
+    #[test]
+    fn record_activity_points_per_day() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"reward-activity"},"value":{"RewardTx":{"RecordActivity":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","timestamp":"2023-11-06T00:00:00.000Z","userActivity":{"alice":[{"point":3,"description":"like"},{"point":2,"description":"comment"}]},"tokenReceived":{"alice":[{"point":5,"description":"like"}],"bob":[{"point":1,"description":"like"}]}}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let activities = tx.signed_tx.value.get_dao_activities();
+        assert_eq!(activities.len(), 2);
+        let alice = activities.iter().find(|m| m.address == "alice").unwrap();
+        assert_eq!((alice.activity_point, alice.received_point), (5, 5));
+        assert_eq!(alice.day, 1699228800);
+        let bob = activities.iter().find(|m| m.address == "bob").unwrap();
+        assert_eq!((bob.activity_point, bob.received_point), (0, 1));
+    }
+
+    #[test]
+    fn execute_reward_payouts() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"reward-activity"},"value":{"RewardTx":{"ExecuteReward":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","daoAccount":"dao"}}}},"result":{"ExecuteRewardResult":{"outputs":{"alice":1000,"bob":2000}}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let payouts = tx.get_reward_payouts("hash", 10);
+        assert_eq!(payouts.len(), 2);
+        let bob = payouts.iter().find(|m| m.address == "bob").unwrap();
+        assert_eq!(bob.amount, BigDecimal::from(2000));
+        assert_eq!(bob.dao_account, Some("dao".to_string()));
+        assert_eq!(bob.reward_type, "ExecuteReward");
+    }
+
+    async fn sqlite() -> sea_orm::DatabaseConnection {
+        use lmscan_agent::{dao_activity, reward_payout, reward_total, snapshot};
+        use sea_orm::{ConnectionTrait, Database, DbBackend, Schema};
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(dao_activity::Entity),
+            schema.create_table_from_entity(reward_payout::Entity),
+            schema.create_table_from_entity(reward_total::Entity),
+            schema.create_table_from_entity(snapshot::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        db
+    }
+
+    fn payout(hash: &str, address: &str, amount: i64, block_number: i64) -> lmscan_agent::reward_payout::Model {
+        lmscan_agent::reward_payout::Model {
+            hash: hash.to_string(),
+            address: address.to_string(),
+            amount: BigDecimal::from(amount),
+            reward_type: "ExecuteReward".to_string(),
+            dao_account: None,
+            definition_id: None,
+            snapshot_hash: None,
+            snapshot_number: None,
+            block_number,
+            event_time: 0,
+            created_at: 0,
+        }
+    }
+
+    fn activity(address: &str, point: i64) -> lmscan_agent::dao_activity::Model {
+        lmscan_agent::dao_activity::Model {
+            address: address.to_string(),
+            day: 0,
+            activity_point: point,
+            received_point: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn build_snapshot(hash: &str, block_number: i64) -> lmscan_agent::snapshot::Model {
+        lmscan_agent::snapshot::Model {
+            hash: hash.to_string(),
+            snapshot_type: "BuildSnapshot".to_string(),
+            definition_id: None,
+            memo: None,
+            account_amount: None,
+            token_amount: None,
+            ownership_amount: None,
+            block_number,
+            tx_index: 0,
+            is_captured: false,
+            event_time: 0,
+            created_at: 0,
+        }
+    }
+
+    // stores a block the way check_app does: snapshots first, then the rewards
+    async fn save_block(
+        db: &sea_orm::DatabaseConnection,
+        block_number: i64,
+        snapshots: Vec<lmscan_agent::snapshot::Model>,
+        payouts: Vec<lmscan_agent::reward_payout::Model>,
+        activities: Vec<lmscan_agent::dao_activity::Model>,
+        is_new_block: bool,
+    ) {
+        use lmscan_agent::check_app::save_rewards;
+        use lmscan_agent::snapshot;
+        use sea_orm::{EntityTrait, IntoActiveModel, TransactionTrait};
+        let txn = db.begin().await.unwrap();
+        for m in snapshots.iter().cloned() {
+            snapshot::Entity::insert(m.into_active_model()).exec(&txn).await.unwrap();
+        }
+        save_rewards(&txn, block_number, &snapshots, payouts, activities, is_new_block).await.unwrap();
+        txn.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn ledger_totals() {
+        use lmscan_agent::{dao_activity, reward_total};
+        use sea_orm::EntityTrait;
+        let db = sqlite().await;
+        save_block(&db, 10, vec![], vec![payout("t1", "alice", 100, 10), payout("t1", "bob", 50, 10)], vec![activity("alice", 3), activity("alice", 2)], true).await;
+        save_block(&db, 11, vec![], vec![payout("t2", "alice", 20, 11)], vec![activity("alice", 1)], true).await;
+
+        let alice = reward_total::Entity::find_by_id("alice").one(&db).await.unwrap().unwrap();
+        assert_eq!((alice.total, alice.payout_count), (BigDecimal::from(120), 2));
+        let bob = reward_total::Entity::find_by_id("bob").one(&db).await.unwrap().unwrap();
+        assert_eq!((bob.total, bob.payout_count), (BigDecimal::from(50), 1));
+        let points = dao_activity::Entity::find_by_id(("alice".to_string(), 0)).one(&db).await.unwrap().unwrap();
+        assert_eq!(points.activity_point, 6);
+    }
+
+    #[tokio::test]
+    async fn late_build_snapshot_relinks_payouts() {
+        use lmscan_agent::reward_payout;
+        use sea_orm::EntityTrait;
+        let db = sqlite().await;
+        let linked = |hash: &'static str| {
+            let db = db.clone();
+            async move {
+                let m = reward_payout::Entity::find_by_id((hash.to_string(), "alice".to_string())).one(&db).await.unwrap().unwrap();
+                (m.snapshot_hash, m.snapshot_number)
+            }
+        };
+        // blocks are indexed from the head down, so payouts come before their snapshot
+        save_block(&db, 10, vec![], vec![payout("t10", "alice", 1, 10)], vec![], true).await;
+        save_block(&db, 6, vec![], vec![payout("t6", "alice", 1, 6)], vec![], true).await;
+        assert_eq!(linked("t10").await, (None, None));
+
+        save_block(&db, 8, vec![build_snapshot("s8", 8)], vec![], vec![], true).await;
+        assert_eq!(linked("t10").await, (Some("s8".to_string()), Some(8)));
+        assert_eq!(linked("t6").await, (None, None));
+
+        // a newer snapshot below the payout takes over, one above it does not
+        save_block(&db, 9, vec![build_snapshot("s9", 9)], vec![], vec![], true).await;
+        save_block(&db, 12, vec![build_snapshot("s12", 12)], vec![], vec![], true).await;
+        assert_eq!(linked("t10").await, (Some("s9".to_string()), Some(9)));
+
+        // payouts stored after their snapshot are linked right away
+        save_block(&db, 11, vec![], vec![payout("t11", "alice", 1, 11)], vec![], true).await;
+        assert_eq!(linked("t11").await, (Some("s9".to_string()), Some(9)));
+    }
+
+    #[tokio::test]
+    async fn rebuilt_block_is_not_counted_twice() {
+        use lmscan_agent::{dao_activity, reward_payout, reward_total};
+        use sea_orm::{EntityTrait, PaginatorTrait};
+        let db = sqlite().await;
+        let block = || (vec![payout("t1", "alice", 100, 10)], vec![activity("alice", 3)]);
+        let (payouts, activities) = block();
+        save_block(&db, 10, vec![], payouts, activities, true).await;
+        let (payouts, activities) = block();
+        save_block(&db, 10, vec![], payouts, activities, false).await;
+
+        assert_eq!(reward_payout::Entity::find().count(&db).await.unwrap(), 1);
+        let alice = reward_total::Entity::find_by_id("alice").one(&db).await.unwrap().unwrap();
+        assert_eq!((alice.total, alice.payout_count), (BigDecimal::from(100), 1));
+        let points = dao_activity::Entity::find_by_id(("alice".to_string(), 0)).one(&db).await.unwrap().unwrap();
+        assert_eq!(points.activity_point, 3);
+    }
+}
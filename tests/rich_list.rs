@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::address_label;
+    use lmscan_agent::service::distribution_service::{gini, nakamoto, rich_list, top_share};
+
+    fn d(v: i64) -> BigDecimal {
+        BigDecimal::from(v)
+    }
+
+    #[test]
+    fn concentration() {
+        assert!(gini(&[d(5), d(5), d(5)]).abs() < 1e-9);
+        let g = gini(&[d(100), d(0), d(0), d(0)]);
+        assert!((g - 0.75).abs() < 1e-9);
+        assert_eq!(nakamoto(&[d(40), d(30), d(20), d(10)]), 2);
+        assert_eq!(nakamoto(&[d(50), d(50)]), 2);
+        assert!((top_share(&[d(60), d(30), d(10)], 1) - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ranks_with_labels() {
+        let holders = vec![
+            ("a".to_string(), d(10), d(0)),
+            ("exchange".to_string(), d(50), d(20)),
+            ("b".to_string(), d(5), d(15)),
+            ("empty".to_string(), d(0), d(0)),
+        ];
+        let labels = HashMap::from([(
+            "exchange".to_string(),
+            address_label::Model {
+                address: "exchange".to_string(),
+                label: "Exchange".to_string(),
+                category: "exchange".to_string(),
+                exclude_from_supply: true,
+                source: "config".to_string(),
+                updated_at: 0,
+            },
+        )]);
+        let (rows, dist) = rich_list("LM", &holders, &labels, 2, 0);
+        assert_eq!(rows.iter().map(|r| r.address.as_str()).collect::<Vec<_>>(), vec!["exchange", "b"]);
+        assert_eq!(rows[0].label.as_deref(), Some("Exchange"));
+        assert_eq!(rows[0].share, "0.7".parse::<BigDecimal>().unwrap());
+        assert_eq!((dist.holder_count, dist.total_balance, dist.excluded_balance), (3, d(100), d(70)));
+        // the labeled exchange is left out of the concentration metrics
+        assert_eq!(dist.nakamoto, 1);
+        assert!((dist.top10_share - 1.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn distribution_per_token() {
+        use lmscan_agent::service::distribution_service::update_distribution;
+        use lmscan_agent::{balance_change, balance_entity, holder_rank, nft_owner, token_definition, token_distribution, tx_entity, tx_state};
+        use sea_orm::*;
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(address_label::Entity),
+            schema.create_table_from_entity(balance_entity::Entity),
+            schema.create_table_from_entity(balance_change::Entity),
+            schema.create_table_from_entity(token_definition::Entity),
+            schema.create_table_from_entity(nft_owner::Entity),
+            schema.create_table_from_entity(tx_entity::Entity),
+            schema.create_table_from_entity(tx_state::Entity),
+            schema.create_table_from_entity(holder_rank::Entity),
+            schema.create_table_from_entity(token_distribution::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        balance_entity::ActiveModel::from(balance_entity::Model {
+            address: "a".to_string(),
+            free: d(10),
+            locked: d(0),
+            free_scaled: d(0),
+            locked_scaled: d(0),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .reset_all()
+        .insert(&db)
+        .await
+        .unwrap();
+        for (address, token, free) in [("a", "X", 30), ("b", "X", 10), ("a", "X", -20), ("b", "LM", 99)] {
+            balance_change::Model::change("t", address, token, d(free), d(0), 1, 0).insert(&db).await.unwrap();
+        }
+        let nft = token_definition::Model { is_nft: true, ..token_definition::Model::new("N".to_string()) };
+        token_definition::ActiveModel::from(nft).reset_all().insert(&db).await.unwrap();
+        for (token_id, owner, status) in [("1", "a", "owned"), ("2", "a", "owned"), ("3", "b", "owned"), ("4", "b", "burned")] {
+            nft_owner::ActiveModel::from(nft_owner::Model {
+                token_id: token_id.to_string(),
+                definition_id: "N".to_string(),
+                owner: owner.to_string(),
+                status: status.to_string(),
+                custodian: None,
+                rarity: None,
+                block_number: 1,
+                tx_index: 0,
+                event_time: 0,
+                created_at: 0,
+            })
+            .reset_all()
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        let dists = update_distribution(&db).await.unwrap();
+        // LM is taken from the balance table, not the ledger
+        assert_eq!(
+            dists.iter().map(|d| (d.token.as_str(), d.holder_count, d.total_balance.clone())).collect::<Vec<_>>(),
+            vec![("LM", 1, d(10)), ("N", 2, d(3)), ("X", 2, d(20))]
+        );
+        let ranks = |token: &'static str| {
+            let db = db.clone();
+            async move {
+                holder_rank::Entity::find()
+                    .filter(holder_rank::Column::Token.eq(token))
+                    .order_by_asc(holder_rank::Column::Rank)
+                    .all(&db)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| (r.address, r.total))
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(ranks("X").await, vec![("a".to_string(), d(10)), ("b".to_string(), d(10))]);
+        assert_eq!(ranks("N").await, vec![("a".to_string(), d(2)), ("b".to_string(), d(1))]);
+        assert_eq!(token_distribution::Entity::find().count(&db).await.unwrap(), 3);
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::service::cursor_service::Cursor;
+    use lmscan_agent::snapshot_app::{capture_pending_snapshots, capture_snapshot};
+    use lmscan_agent::{balance_app, nft_app};
+    use lmscan_agent::{agent_cursor, balance_change, nft_history, snapshot, snapshot_balance, snapshot_nft_owner};
+    use sea_orm::*;
+
+    fn d(v: i64) -> BigDecimal {
+        BigDecimal::from(v)
+    }
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(snapshot::Entity),
+            schema.create_table_from_entity(snapshot_balance::Entity),
+            schema.create_table_from_entity(snapshot_nft_owner::Entity),
+            schema.create_table_from_entity(balance_change::Entity),
+            schema.create_table_from_entity(nft_history::Entity),
+            schema.create_table_from_entity(agent_cursor::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        db
+    }
+
+    fn history(tx_hash: &str, token_id: &str, owner: &str, status: &str, block_number: i64, tx_index: i32) -> nft_history::ActiveModel {
+        nft_history::ActiveModel::from(nft_history::Model {
+            tx_hash: tx_hash.to_string(),
+            token_id: token_id.to_string(),
+            definition_id: "nft-1".to_string(),
+            action: "TransferNft".to_string(),
+            status: status.to_string(),
+            owner: owner.to_string(),
+            custodian: None,
+            signer: owner.to_string(),
+            block_number,
+            tx_index,
+            event_time: 0,
+            created_at: 0,
+        })
+        .reset_all()
+    }
+
+    fn build_snapshot(block_number: i64, tx_index: i32) -> snapshot::Model {
+        snapshot::Model {
+            hash: "snap".to_string(),
+            snapshot_type: "BuildSnapshot".to_string(),
+            definition_id: None,
+            memo: None,
+            account_amount: None,
+            token_amount: None,
+            ownership_amount: None,
+            block_number,
+            tx_index,
+            is_captured: false,
+            event_time: 0,
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn state_at_snapshot_height() {
+        let db = sqlite().await;
+        for (hash, address, token, free, locked, block_number, tx_index) in [
+            ("t1", "alice", "LM", 100, 0, 5, 0),
+            ("t2", "bob", "LM", 50, 20, 6, 1),
+            ("t2", "bob", "OTHER", 7, 0, 6, 1),
+            // same block, after the snapshot tx
+            ("t3", "alice", "LM", -40, 0, 6, 3),
+            ("t4", "bob", "LM", -50, -20, 7, 0),
+            ("t5", "carol", "LM", 1000, 0, 9, 0),
+        ] {
+            balance_change::Model::change(hash, address, token, d(free), d(locked), block_number, tx_index)
+                .insert(&db)
+                .await
+                .unwrap();
+        }
+        for h in [
+            history("n1", "1", "alice", "minted", 5, 1),
+            history("n2", "1", "bob", "owned", 8, 0),
+            history("n3", "2", "alice", "minted", 3, 0),
+            history("n4", "2", "alice", "burned", 4, 0),
+        ] {
+            h.insert(&db).await.unwrap();
+        }
+        let snapshot = build_snapshot(6, 2);
+        snapshot::ActiveModel::from(snapshot.clone()).reset_all().insert(&db).await.unwrap();
+
+        capture_snapshot(&db, snapshot).await.unwrap();
+
+        let balances = snapshot_balance::Entity::find()
+            .order_by_asc(snapshot_balance::Column::Address)
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.address, b.free, b.locked))
+            .collect::<Vec<_>>();
+        assert_eq!(balances, vec![("alice".to_string(), d(100), d(0)), ("bob".to_string(), d(50), d(20))]);
+        let owners = snapshot_nft_owner::Entity::find().all(&db).await.unwrap();
+        assert_eq!(owners.len(), 1);
+        assert_eq!((owners[0].token_id.as_str(), owners[0].owner.as_str()), ("1", "alice"));
+        assert!(snapshot::Entity::find_by_id("snap".to_string()).one(&db).await.unwrap().unwrap().is_captured);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_balance_ledger() {
+        let db = sqlite().await;
+        balance_change::Model::change("t1", "alice", "LM", d(100), d(0), 5, 0).insert(&db).await.unwrap();
+        snapshot::ActiveModel::from(build_snapshot(6, 2)).reset_all().insert(&db).await.unwrap();
+        Cursor::set(&db, nft_app::CURSOR, 10).await.unwrap();
+        let captured = || async { snapshot::Entity::find_by_id("snap".to_string()).one(&db).await.unwrap().unwrap().is_captured };
+
+        // bob's change in block 6 is not in the ledger yet
+        Cursor::set(&db, balance_app::CURSOR, 5).await.unwrap();
+        capture_pending_snapshots(&db).await;
+        assert!(!captured().await);
+        assert!(snapshot_balance::Entity::find().all(&db).await.unwrap().is_empty());
+
+        balance_change::Model::change("t2", "bob", "LM", d(50), d(0), 6, 1).insert(&db).await.unwrap();
+        Cursor::set(&db, balance_app::CURSOR, 6).await.unwrap();
+        capture_pending_snapshots(&db).await;
+        assert!(captured().await);
+        assert_eq!(snapshot_balance::Entity::find().all(&db).await.unwrap().len(), 2);
+    }
+}
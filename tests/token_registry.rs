@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::library::common::parse_from_json_str;
+    use lmscan_agent::service::precision_service::{Precision, DEFAULT_PRECISION};
+    use lmscan_agent::transaction::TransactionWithResult;
+
+    #[test]
+    fn define_token_with_precision() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"founder"},"value":{"TokenTx":{"DefineTokenWithPrecision":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","name":"LeisureMeta","symbol":"LM","minterGroup":"mint-group","nftInfo":null,"precision":18}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let def = tx.signed_tx.value.get_token_definition().unwrap();
+        assert_eq!(def.definition_id, "LM");
+        assert_eq!(def.precision, Some(18));
+        assert!(!def.is_nft);
+    }
+
+    #[test]
+    fn define_nft_token_rarity() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"founder"},"value":{"TokenTx":{"DefineToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"nft-1","name":"NFT","symbol":null,"minterGroup":"mint-group","nftInfo":{"Some":{"value":{"minter":"minter","rarity":{"LGDY":100,"UNIQ":null},"dataUrl":"https://example.com/nft.json","contentHash":"abc"}}}}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let def = tx.signed_tx.value.get_token_definition().unwrap();
+        assert!(def.is_nft);
+        assert_eq!(def.precision, None);
+        assert_eq!(def.rarity.unwrap()["LGDY"], serde_json::json!("100"));
+    }
+
+    #[test]
+    fn mint_and_burn_supply() {
+        let mint = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"minter"},"value":{"TokenTx":{"MintFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","outputs":{"a":100,"b":50}}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(mint).unwrap();
+        let supply = tx.signed_tx.value.get_supply_change().unwrap();
+        assert_eq!(supply.total_minted, BigDecimal::from(150));
+
+        let burn = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"a"},"value":{"TokenTx":{"BurnFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","amount":30,"inputs":["h"]}}}},"result":{"BurnFungibleTokenResult":{"outputAmount":70}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(burn).unwrap();
+        let supply = tx.signed_tx.value.get_supply_change().unwrap().add_supply(&supply);
+        assert_eq!(supply.total_minted, BigDecimal::from(150));
+        assert_eq!(supply.total_burned, BigDecimal::from(30));
+    }
+
+    #[test]
+    fn entrust_and_dispose_lock() {
+        let entrust = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"a"},"value":{"TokenTx":{"EntrustFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","amount":40,"inputs":["h"],"to":"playnomm"}}}},"result":{"EntrustFungibleTokenResult":{"remainder":60}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(entrust).unwrap();
+        let locked = tx.signed_tx.value.get_supply_change().unwrap();
+        assert_eq!(locked.total_locked, BigDecimal::from(40));
+
+        let dispose = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"playnomm"},"value":{"TokenTx":{"DisposeEntrustedFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","inputs":["e"],"outputs":{"b":25}}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(dispose).unwrap();
+        let locked = tx.signed_tx.value.get_supply_change().unwrap().add_supply(&locked);
+        assert_eq!(locked.total_locked, BigDecimal::from(15));
+    }
+
+    #[test]
+    fn precision_scaling() {
+        let raw: BigDecimal = "277816019685259999999980000000".parse().unwrap();
+        let scaled = Precision::scale(&raw, DEFAULT_PRECISION);
+        assert_eq!(scaled, "277816019685.25999999998".parse::<BigDecimal>().unwrap());
+        assert_eq!(Precision::scale(&scaled, -DEFAULT_PRECISION), raw);
+    }
+
+    #[test]
+    fn raw_amount_keeps_precision() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"minter"},"value":{"TokenTx":{"MintFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","outputs":{"a":123456789012345678901234567}}}}},"result":{"BurnFungibleTokenResult":{"outputAmount":"98765432109876543210987654321"}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        let supply = tx.signed_tx.value.get_supply_change().unwrap();
+        assert_eq!(supply.total_minted, "123456789012345678901234567".parse::<BigDecimal>().unwrap());
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use lmscan_agent::library::common::parse_from_json_str;
+    use lmscan_agent::transaction::{
+        token_transaction::TokenTx, Transaction, TransactionResult, TransactionWithResult,
+    };
+
+    #[test]
+    fn unknown_token_tx() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"TokenTx":{"MintNFTBatch":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","amount":1000000000000000000000000000}}}},"result":null}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        match &tx.signed_tx.value {
+            Transaction::TokenTx(TokenTx::Unknown(t)) => {
+                assert_eq!(t.tag, "MintNFTBatch");
+                assert_eq!(t.created_at(), 1699336467);
+            }
+            v => panic!("unexpected {:?}", v),
+        }
+        assert_eq!(tx.unknown_tags(), vec!["TokenTx::MintNFTBatch".to_string()]);
+        // raw json is kept as is, big numbers included.
+        assert_eq!(serde_json::to_string(&tx).unwrap(), json);
+    }
+
+    #[test]
+    fn unknown_transaction_and_result() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"CreatorDaoTx":{"CreateCreatorDao":{"createdAt":"2023-11-07T05:54:27.867Z"}}}},"result":{"CreateCreatorDaoResult":{"id":"1"}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        match &tx.signed_tx.value {
+            Transaction::Unknown(t) => {
+                assert_eq!(t.tag, "CreatorDaoTx");
+                assert_eq!(t.inner_tag(), Some("CreateCreatorDao".to_string()));
+            }
+            v => panic!("unexpected {:?}", v),
+        }
+        assert!(matches!(tx.result, Some(TransactionResult::Unknown(_))));
+    }
+
+    #[test]
+    fn known_tx_keeps_precision() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"TokenTx":{"BurnFungibleToken":{"networkId":1000,"createdAt":"2023-11-07T05:54:27.867Z","definitionId":"LM","amount":277816019685259999999980000000,"inputs":["h"]}}}},"result":{"BurnFungibleTokenResult":{"outputAmount":1}}}"#;
+        let tx = parse_from_json_str::<TransactionWithResult>(json).unwrap();
+        match tx.signed_tx.value {
+            Transaction::TokenTx(TokenTx::BurnFungibleToken(t)) => assert_eq!(
+                t.amount,
+                "277816019685259999999980000000".parse::<BigDecimal>().unwrap()
+            ),
+            v => panic!("unexpected {:?}", v),
+        }
+    }
+
+    #[test]
+    fn malformed_known_tx_is_error() {
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"TokenTx":{"BurnFungibleToken":{"createdAt":"2023-11-07T05:54:27.867Z"}}}},"result":null}"#;
+        assert!(parse_from_json_str::<TransactionWithResult>(json).is_err());
+    }
+
+    #[test]
+    fn known_tag_with_bad_payload_is_error() {
+        // only tags outside the enum fall back to unknown, a known tag must parse.
+        let json = r#"{"signedTx":{"sig":{"sig":{"v":27,"r":"a3","s":"39"},"account":"alice"},"value":{"TokenTx":"MintNFTBatch"}},"result":null}"#;
+        assert!(parse_from_json_str::<TransactionWithResult>(json).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lmscan_agent::service::url_resolver::{Source, UrlResolver};
+
+    fn resolver(mirror_dir: Option<PathBuf>) -> UrlResolver {
+        UrlResolver::new("https://gw1.example/ipfs, https://gw2.example/ipfs/", "https://ar.example/", mirror_dir, 64, 5)
+    }
+
+    #[test]
+    fn ipfs_to_gateways() {
+        let r = resolver(Some(PathBuf::from("/mirror")));
+        assert_eq!(r.sources("ipfs://Qm123/meta.json"), vec![
+            Source::Mirror(PathBuf::from("/mirror/ipfs/Qm123/meta.json")),
+            Source::Http("https://gw1.example/ipfs/Qm123/meta.json".to_string()),
+            Source::Http("https://gw2.example/ipfs/Qm123/meta.json".to_string()),
+        ]);
+        assert_eq!(r.host("ipfs://Qm123/meta.json"), "gw1.example");
+    }
+
+    #[test]
+    fn gateway_url_falls_back() {
+        let r = resolver(None);
+        assert_eq!(r.sources("https://other.example/ipfs/Qm123"), vec![
+            Source::Http("https://other.example/ipfs/Qm123".to_string()),
+            Source::Http("https://gw1.example/ipfs/Qm123".to_string()),
+            Source::Http("https://gw2.example/ipfs/Qm123".to_string()),
+        ]);
+        assert_eq!(r.sources("ar://tx1"), vec![Source::Http("https://ar.example/tx1".to_string())]);
+        assert_eq!(r.sources("https://a.example/1.json"), vec![Source::Http("https://a.example/1.json".to_string())]);
+    }
+
+    #[test]
+    fn mirror_rejects_escaping_paths() {
+        let r = resolver(Some(PathBuf::from("/mirror")));
+        for url in ["ipfs://../../etc/passwd", "ipfs://Qm123/../../../etc/passwd", "ar:///abs"] {
+            assert!(
+                r.sources(url).iter().all(|s| matches!(s, Source::Http(_))),
+                "{url} {:?}",
+                r.sources(url)
+            );
+        }
+        assert_eq!(r.sources("ipfs://./Qm123")[0], Source::Mirror(PathBuf::from("/mirror/ipfs/Qm123")));
+    }
+
+    #[tokio::test]
+    async fn mirror_with_size_limit() {
+        let dir = std::env::temp_dir().join(format!("lmscan-mirror-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("http/a.example")).unwrap();
+        std::fs::create_dir_all(dir.join("http/127.0.0.1")).unwrap();
+        std::fs::write(dir.join("http/a.example/1.json"), br#"{"ok":true}"#).unwrap();
+        std::fs::write(dir.join("http/127.0.0.1/big.json"), vec![b' '; 100]).unwrap();
+        let r = resolver(Some(dir.clone()));
+
+        let v: serde_json::Value = r.fetch_json("https://a.example/1.json").await.unwrap();
+        assert_eq!(v["ok"], true);
+        // over the limit in the mirror, then nothing listens on the fallback
+        let err = r.fetch("http://127.0.0.1:1/big.json").await.unwrap_err();
+        assert!(err.contains("exceeds 64 bytes"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use lmscan_agent::service::event_service::EventHub;
+    use lmscan_agent::webhook_app::{backoff_secs, dispatch, enqueue, replay, sign, Replay};
+    use lmscan_agent::{agent_cursor, block_state, chain_event, webhook_delivery, webhook_watch};
+    use sea_orm::*;
+    use serde_json::{json, Value};
+
+    type Received = Arc<Mutex<Vec<(String, HeaderMap, String)>>>;
+
+    // answers 500 to the first request on /flaky, 200 otherwise
+    async fn receiver() -> (String, Received) {
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .route(
+                "/:name",
+                post(|Path(name): Path<String>, State(received): State<Received>, headers: HeaderMap, body: String| async move {
+                    let mut received = received.lock().unwrap();
+                    let first = !received.iter().any(|(n, _, _)| n == &name);
+                    received.push((name.clone(), headers, body));
+                    if name == "flaky" && first {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    } else {
+                        StatusCode::OK
+                    }
+                }),
+            )
+            .with_state(received.clone());
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        (url, received)
+    }
+
+    async fn sqlite() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for stmt in [
+            schema.create_table_from_entity(chain_event::Entity),
+            schema.create_table_from_entity(agent_cursor::Entity),
+            schema.create_table_from_entity(block_state::Entity),
+            schema.create_table_from_entity(webhook_watch::Entity),
+            schema.create_table_from_entity(webhook_delivery::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&stmt)).await.unwrap();
+        }
+        // events are sequenced once their block is built
+        block_state::ActiveModel {
+            hash: Set("h1".to_string()),
+            number: Set(1),
+            is_build: Set(true),
+            json: Set("{}".to_string()),
+            event_time: Set(0),
+            created_at: Set(0),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        db
+    }
+
+    fn event(kind: &str, addresses: &[&str], definition_id: &str) -> chain_event::ActiveModel {
+        chain_event::Model::event(
+            kind,
+            addresses.iter().map(|a| a.to_string()).collect(),
+            Some("NFT".to_string()),
+            1,
+            json!({ "hash": "h", "definition_id": definition_id }),
+        )
+    }
+
+    async fn deliveries(db: &DatabaseConnection) -> HashMap<i64, webhook_delivery::Model> {
+        webhook_delivery::Entity::find().all(db).await.unwrap().into_iter().map(|d| (d.watch_id, d)).collect()
+    }
+
+    #[test]
+    fn replay_args() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(Replay::parse(&args("3 5")), Ok(Replay::Deliveries(vec![3, 5])));
+        assert_eq!(Replay::parse(&args("failed")), Ok(Replay::Failed { watch_id: None }));
+        assert_eq!(Replay::parse(&args("since 10 2")), Ok(Replay::Since { seq: 10, watch_id: Some(2) }));
+        assert!(Replay::parse(&args("since x")).is_err());
+        assert!(Replay::parse(&[]).is_err());
+        assert_eq!(backoff_secs(0), 30);
+        assert_eq!(backoff_secs(2), 120);
+        assert_eq!(backoff_secs(30), 60 * 60 * 6);
+    }
+
+    #[tokio::test]
+    async fn signed_delivery_with_retry_and_replay() {
+        let db = sqlite().await;
+        let (url, received) = receiver().await;
+        let alice = webhook_watch::Model::watch("ops", &format!("{url}/flaky"), "s1", Some("alice".to_string()), None)
+            .insert(&db)
+            .await
+            .unwrap();
+        let mut nft = webhook_watch::Model::watch("partner", &format!("{url}/nft"), "s2", None, Some("col-1".to_string()));
+        nft.events = Set(Some(chain_event::NFT_TRANSFER.to_string()));
+        let nft = nft.insert(&db).await.unwrap();
+
+        // first run only pins the cursor at the head
+        event(chain_event::TX, &["alice"], "").insert(&db).await.unwrap();
+        EventHub::sequence(&db).await.unwrap();
+        assert_eq!(enqueue(&db).await.unwrap(), 0);
+
+        for e in [
+            event(chain_event::TX, &["alice", "bob"], "LM"),
+            event(chain_event::TX, &["bob"], "LM"),
+            event(chain_event::NFT_TRANSFER, &["bob", "carol"], "col-1"),
+            event(chain_event::NFT_TRANSFER, &["bob", "carol"], "col-2"),
+            event(chain_event::ACCOUNT, &["alice"], ""),
+        ] {
+            e.insert(&db).await.unwrap();
+        }
+        EventHub::sequence(&db).await.unwrap();
+        assert_eq!(enqueue(&db).await.unwrap(), 2);
+        assert_eq!(enqueue(&db).await.unwrap(), 0);
+
+        let now = 1_000;
+        assert_eq!(dispatch(&db, now).await.unwrap(), 2);
+        let rows = deliveries(&db).await;
+        assert_eq!(rows[&nft.id].status, "delivered");
+        assert_eq!(rows[&alice.id].status, "pending");
+        assert_eq!(rows[&alice.id].response_status, Some(500));
+        assert_eq!(rows[&alice.id].next_attempt_at, now + backoff_secs(0));
+
+        assert_eq!(dispatch(&db, now).await.unwrap(), 0);
+        assert_eq!(dispatch(&db, now + backoff_secs(0)).await.unwrap(), 1);
+        let rows = deliveries(&db).await;
+        assert_eq!(rows[&alice.id].status, "delivered");
+        assert_eq!(rows[&alice.id].attempts, 2);
+
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 3);
+            for (name, headers, body) in received.iter() {
+                let secret = if name == "flaky" { "s1" } else { "s2" };
+                let ts: i64 = headers["x-lmscan-timestamp"].to_str().unwrap().parse().unwrap();
+                let sig = headers["x-lmscan-signature"].to_str().unwrap();
+                assert_eq!(sig, format!("sha256={}", sign(secret, ts, body)));
+                let body: Value = serde_json::from_str(body).unwrap();
+                assert_eq!(body["event"]["seq"], if name == "flaky" { 2 } else { 4 });
+            }
+        }
+
+        assert_eq!(replay(&db, Replay::Deliveries(vec![rows[&nft.id].id])).await.unwrap(), 1);
+        assert_eq!(replay(&db, Replay::Since { seq: 0, watch_id: Some(alice.id) }).await.unwrap(), 2);
+        assert_eq!(dispatch(&db, now).await.unwrap(), 3);
+        assert_eq!(received.lock().unwrap().len(), 6);
+    }
+}